@@ -0,0 +1,4 @@
+// migrations are embedded by `sqlx::migrate!`, rebuild when one is added
+fn main() {
+    println!("cargo:rerun-if-changed=db/migrations");
+}
@@ -0,0 +1,1719 @@
+-- The sqlite schema ported to postgres, objects are read and written as jsonb through the
+-- same views. Dates are kept as text in the same formats so they compare and sort alike
+
+-- Timestamps in UTC to the millisecond, and dates, as sqlite strftime and date() give them
+CREATE FUNCTION utc_timestamp(value text) RETURNS text
+    LANGUAGE sql STABLE SET TimeZone = 'UTC'
+    AS $$ SELECT to_char(CAST(value AS timestamptz), 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"') $$;
+
+CREATE FUNCTION utc_date(value text) RETURNS text
+    LANGUAGE sql STABLE SET TimeZone = 'UTC'
+    AS $$ SELECT to_char(CAST(value AS timestamptz), 'YYYY-MM-DD') $$;
+
+CREATE FUNCTION utc_now() RETURNS text
+    LANGUAGE sql STABLE SET TimeZone = 'UTC'
+    AS $$ SELECT to_char(now(), 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"') $$;
+
+-- Orders json values as sqlite orders json_extract results, nulls then numbers and booleans
+-- then text compared bytewise, so sorted pages are the same whatever the database collation
+CREATE TYPE JsonSortKey AS (
+    kind integer
+    , number double precision
+    , string text COLLATE "C"
+);
+
+CREATE FUNCTION json_sort_key(value jsonb) RETURNS JsonSortKey
+    LANGUAGE sql IMMUTABLE
+    AS $$ SELECT CAST(ROW(
+        CASE jsonb_typeof(value)
+            WHEN 'number' THEN 1 WHEN 'boolean' THEN 1
+            WHEN 'string' THEN 2 WHEN 'object' THEN 2 WHEN 'array' THEN 2
+            ELSE 0 END
+        , CASE jsonb_typeof(value)
+            WHEN 'number' THEN CAST(value #>> '{}' AS double precision)
+            WHEN 'boolean' THEN CASE WHEN CAST(value AS boolean) THEN 1 ELSE 0 END END
+        , CASE WHEN jsonb_typeof(value) IN ('string', 'object', 'array') THEN value #>> '{}' END
+    ) AS JsonSortKey) $$;
+
+-- Auth tables
+CREATE TABLE credentials (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , client_id text UNIQUE NOT NULL
+    , client_secret text NOT NULL
+    , tag text NOT NULL
+);
+
+CREATE TABLE scopes (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , scope text UNIQUE NOT NULL
+);
+
+CREATE TABLE credential_scopes (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , credential_id bigint NOT NULL REFERENCES credentials (id) ON DELETE CASCADE
+    , scope_id bigint NOT NULL REFERENCES scopes (id) ON DELETE CASCADE
+);
+
+-- Enum tables
+CREATE TABLE StatusType (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , token text UNIQUE NOT NULL
+);
+
+CREATE TABLE OrgType (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , token text UNIQUE NOT NULL
+);
+
+CREATE TABLE ClassType (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , token text UNIQUE NOT NULL
+);
+
+CREATE TABLE RoleType (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , token text UNIQUE NOT NULL
+);
+
+CREATE TABLE SessionType (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , token text UNIQUE NOT NULL
+);
+
+CREATE TABLE GenderType (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , token text UNIQUE NOT NULL
+);
+
+CREATE TABLE ImportanceType (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , token text UNIQUE NOT NULL
+);
+
+CREATE TABLE ScoreStatusType (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , token text UNIQUE NOT NULL
+);
+
+CREATE TABLE GradeType (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , token text UNIQUE NOT NULL
+    , description text NOT NULL
+);
+
+-- OR:4.2
+CREATE TABLE AcademicSessions (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , title text NOT NULL
+    , startDate text NOT NULL
+    , endDate text NOT NULL
+    , sessionTypeId bigint NOT NULL REFERENCES SessionType (id)
+    , parentSourcedId text
+        REFERENCES AcademicSessions (sourcedId) DEFERRABLE INITIALLY DEFERRED
+    , schoolYear text -- YYYY
+);
+CREATE INDEX AcademicSessionsParentIndex ON AcademicSessions (parentSourcedId);
+CREATE INDEX AcademicSessionsDateLastModifiedIndex ON AcademicSessions (dateLastModified);
+
+-- OR:4.13
+CREATE TABLE Subjects (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , title text NOT NULL
+    , subjectCode text NOT NULL
+);
+CREATE INDEX SubjectsSubjectCodeIndex ON Subjects (subjectCode);
+CREATE INDEX SubjectsDateLastModifiedIndex ON Subjects (dateLastModified);
+
+-- OR:4.8
+CREATE TABLE Orgs (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , name text NOT NULL
+    , orgTypeId bigint NOT NULL REFERENCES OrgType (id)
+    , identifier text
+    , parentSourcedId text REFERENCES Orgs (sourcedId) DEFERRABLE INITIALLY DEFERRED
+);
+CREATE INDEX OrgsParentIndex ON Orgs (parentSourcedId);
+CREATE INDEX OrgsDateLastModifiedIndex ON Orgs (dateLastModified);
+
+-- OR:4.4
+CREATE TABLE Courses (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , title text NOT NULL
+    , schoolYearSourcedId text REFERENCES AcademicSessions (sourcedId)
+    , courseCode text
+    , orgSourcedId text NOT NULL REFERENCES Orgs (sourcedId)
+);
+CREATE INDEX CoursesDateLastModifiedIndex ON Courses (dateLastModified);
+
+-- OR:4.3
+CREATE TABLE Classes (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , title text NOT NULL
+    , classCode text
+    , classTypeId bigint NOT NULL REFERENCES ClassType (id)
+    , location text
+    , courseSourcedId text NOT NULL REFERENCES Courses (sourcedId)
+    , orgSourcedId text NOT NULL REFERENCES Orgs (sourcedId)
+);
+CREATE INDEX ClassesCourseIndex ON Classes (courseSourcedId);
+CREATE INDEX ClassesOrgIndex ON Classes (orgSourcedId);
+CREATE INDEX ClassesDateLastModifiedIndex ON Classes (dateLastModified);
+
+CREATE TABLE Periods (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , title text NOT NULL
+    , periodCode text NOT NULL
+    , description text
+);
+CREATE INDEX PeriodsPeriodCodeIndex ON Periods (periodCode);
+CREATE INDEX PeriodsDateLastModifiedIndex ON Periods (dateLastModified);
+
+CREATE TABLE ClassGrades (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , classSourcedId text NOT NULL REFERENCES Classes (sourcedId)
+    , gradeTypeId bigint NOT NULL REFERENCES GradeType (id)
+);
+CREATE UNIQUE INDEX ClassGradeIndex ON ClassGrades (classSourcedId, gradeTypeId);
+
+CREATE TABLE ClassSubjects (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , classSourcedId text NOT NULL REFERENCES Classes (sourcedId)
+    , subjectSourcedId text NOT NULL REFERENCES Subjects (sourcedId)
+);
+CREATE UNIQUE INDEX ClassSubjectIndex ON ClassSubjects (classSourcedId, subjectSourcedId);
+
+CREATE TABLE ClassAcademicSessions (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , classSourcedId text NOT NULL REFERENCES Classes (sourcedId)
+    , academicSessionSourcedId text NOT NULL REFERENCES AcademicSessions (sourcedId)
+);
+CREATE UNIQUE INDEX ClassAcademicSessionsIndex
+    ON ClassAcademicSessions (classSourcedId, academicSessionSourcedId);
+CREATE INDEX ClassAcademicSessionsSessionIndex
+    ON ClassAcademicSessions (academicSessionSourcedId);
+
+CREATE TABLE OrgPeriods (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , periodSourcedId text NOT NULL REFERENCES Periods (sourcedId)
+    , orgSourcedId text NOT NULL REFERENCES Orgs (sourcedId)
+);
+CREATE UNIQUE INDEX OrgPeriodsIndex ON OrgPeriods (periodSourcedId, orgSourcedId);
+
+CREATE TABLE ClassPeriods (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , classSourcedId text NOT NULL REFERENCES Classes (sourcedId)
+    , periodSourcedId text NOT NULL REFERENCES Periods (sourcedId)
+);
+CREATE UNIQUE INDEX ClassPeriodIndex ON ClassPeriods (classSourcedId, periodSourcedId);
+
+CREATE TABLE CourseGrades (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , courseSourcedId text NOT NULL REFERENCES Courses (sourcedId)
+    , gradeTypeId bigint NOT NULL REFERENCES GradeType (id)
+);
+CREATE UNIQUE INDEX CourseGradeIndex ON CourseGrades (courseSourcedId, gradeTypeId);
+
+CREATE TABLE CourseSubjects (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , courseSourcedId text NOT NULL REFERENCES Courses (sourcedId)
+    , subjectSourcedId text NOT NULL REFERENCES Subjects (sourcedId)
+);
+CREATE UNIQUE INDEX CourseSubjectIndex ON CourseSubjects (courseSourcedId, subjectSourcedId);
+
+-- OR:4.12
+CREATE TABLE Users (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id) ON DELETE RESTRICT
+    , dateLastModified text NOT NULL
+    , username text NOT NULL
+    , enabledUser smallint NOT NULL -- bool 0/1
+    , givenName text NOT NULL
+    , familyName text NOT NULL
+    , middleName text
+    , roleTypeId bigint NOT NULL REFERENCES RoleType (id) ON DELETE RESTRICT
+    , identifier text
+    , email text
+    , sms text
+    , phone text
+    , password text
+);
+CREATE INDEX UsersDateLastModifiedIndex ON Users (dateLastModified);
+
+CREATE TABLE UserIds (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id) ON DELETE RESTRICT
+    , userSourcedId text NOT NULL REFERENCES Users (sourcedId) ON DELETE CASCADE
+    , type text NOT NULL
+    , identifier text NOT NULL
+);
+CREATE UNIQUE INDEX UserIdsIndex ON UserIds (userSourcedId, type);
+
+CREATE TABLE UserGrades (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id) ON DELETE RESTRICT
+    , userSourcedId text NOT NULL REFERENCES Users (sourcedId) ON DELETE CASCADE
+    , gradeTypeId bigint NOT NULL REFERENCES GradeType (id) ON DELETE RESTRICT
+);
+CREATE UNIQUE INDEX UserGradesIndex ON UserGrades (userSourcedId, gradeTypeId);
+
+CREATE TABLE UserAgents (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id) ON DELETE RESTRICT
+    , userSourcedId text NOT NULL
+        REFERENCES Users (sourcedId) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED
+    , agentUserSourcedId text NOT NULL
+        REFERENCES Users (sourcedId) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED
+);
+CREATE UNIQUE INDEX UserAgentsIndex ON UserAgents (userSourcedId, agentUserSourcedId);
+CREATE INDEX UserAgentsAgentIndex ON UserAgents (agentUserSourcedId);
+
+CREATE TABLE UserOrgs (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id) ON DELETE RESTRICT
+    , userSourcedId text NOT NULL REFERENCES Users (sourcedId) ON DELETE CASCADE
+    , orgSourcedId text NOT NULL REFERENCES Orgs (sourcedId) ON DELETE CASCADE
+);
+CREATE UNIQUE INDEX UserOrgsIndex ON UserOrgs (userSourcedId, orgSourcedId);
+CREATE INDEX UserOrgsOrgIndex ON UserOrgs (orgSourcedId);
+
+-- OR:4.6
+CREATE TABLE Enrollments (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , userSourcedId text NOT NULL REFERENCES Users (sourcedId)
+    , classSourcedId text NOT NULL REFERENCES Classes (sourcedId)
+    , orgSourcedId text NOT NULL REFERENCES Orgs (sourcedId)
+    , roleTypeId bigint NOT NULL REFERENCES RoleType (id)
+    , "primary" smallint -- bool 0/1
+    , beginDate text
+    , endDate text
+);
+CREATE UNIQUE INDEX EnrollmentsIndex ON Enrollments (userSourcedId, classSourcedId, orgSourcedId);
+CREATE INDEX EnrollmentsClassIndex ON Enrollments (classSourcedId);
+CREATE INDEX EnrollmentsOrgIndex ON Enrollments (orgSourcedId);
+CREATE INDEX EnrollmentsDateLastModifiedIndex ON Enrollments (dateLastModified);
+
+-- OR:4.5
+CREATE TABLE Demographics (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , birthDate text
+    , genderTypeId bigint REFERENCES GenderType (id)
+    , americanIndianOrAlaskaNative text -- 'true'/'false'
+    , asian text
+    , blackOrAfricanAmerican text
+    , nativeHawaiianOrOtherPacificIslander text
+    , white text
+    , demographicRaceTwoOrMoreRaces text
+    , hispanicOrLatinoEthnicity text
+    , countryOfBirthCode text
+    , stateOfBirthAbbreviation text
+    , cityOfBirth text
+    , publicSchoolResidenceStatus text
+);
+CREATE INDEX DemographicsDateLastModifiedIndex ON Demographics (dateLastModified);
+
+-- OR:4.10
+CREATE TABLE Resources (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , title text
+    , importanceTypeId bigint REFERENCES ImportanceType (id)
+    , vendorResourceId text NOT NULL
+    , vendorId text
+    , applicationId text
+);
+CREATE INDEX ResourcesDateLastModifiedIndex ON Resources (dateLastModified);
+
+CREATE TABLE ResourceRoles (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , resourceSourcedId text NOT NULL REFERENCES Resources (sourcedId)
+    , roleTypeId bigint NOT NULL REFERENCES RoleType (id)
+);
+CREATE UNIQUE INDEX ResourceRolesIndex ON ResourceRoles (resourceSourcedId, roleTypeId);
+
+CREATE TABLE ClassResources (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , classSourcedId text NOT NULL REFERENCES Classes (sourcedId)
+    , resourceSourcedId text NOT NULL REFERENCES Resources (sourcedId)
+);
+CREATE UNIQUE INDEX ClassResourcesIndex ON ClassResources (classSourcedId, resourceSourcedId);
+CREATE INDEX ClassResourcesResourceIndex ON ClassResources (resourceSourcedId);
+
+CREATE TABLE CourseResources (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , courseSourcedId text NOT NULL REFERENCES Courses (sourcedId)
+    , resourceSourcedId text NOT NULL REFERENCES Resources (sourcedId)
+);
+CREATE UNIQUE INDEX CourseResourcesIndex ON CourseResources (courseSourcedId, resourceSourcedId);
+CREATE INDEX CourseResourcesResourceIndex ON CourseResources (resourceSourcedId);
+
+-- OR:4.7
+CREATE TABLE Categories (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , title text NOT NULL
+);
+CREATE INDEX CategoriesDateLastModifiedIndex ON Categories (dateLastModified);
+
+-- OR:4.9
+CREATE TABLE LineItems (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , title text NOT NULL
+    , description text
+    , assignDate text NOT NULL
+    , dueDate text NOT NULL
+    , classSourcedId text NOT NULL REFERENCES Classes (sourcedId)
+    , categorySourcedId text NOT NULL REFERENCES Categories (sourcedId)
+    , gradingPeriodSourcedId text NOT NULL REFERENCES AcademicSessions (sourcedId)
+    , resultValueMin double precision NOT NULL
+    , resultValueMax double precision NOT NULL
+);
+CREATE INDEX LineItemsClassIndex ON LineItems (classSourcedId);
+CREATE INDEX LineItemsDateLastModifiedIndex ON LineItems (dateLastModified);
+
+-- OR:4.11
+CREATE TABLE Results (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , sourcedId text UNIQUE NOT NULL
+    , statusTypeId bigint NOT NULL REFERENCES StatusType (id)
+    , dateLastModified text NOT NULL
+    , lineItemSourcedId text NOT NULL REFERENCES LineItems (sourcedId)
+    , studentSourcedId text NOT NULL REFERENCES Users (sourcedId)
+    , scoreStatusTypeId bigint NOT NULL REFERENCES ScoreStatusType (id)
+    , score double precision NOT NULL
+    , scoreDate text NOT NULL
+    , comment text
+);
+CREATE INDEX ResultsLineItemIndex ON Results (lineItemSourcedId);
+CREATE INDEX ResultsDateLastModifiedIndex ON Results (dateLastModified);
+
+-- Records purged objects so delta consumers can remove them
+CREATE TABLE Tombstones (
+    id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+    , type text NOT NULL
+    , sourcedId text NOT NULL
+    , dateLastModified text NOT NULL DEFAULT utc_now()
+);
+CREATE INDEX TombstonesDateLastModifiedIndex ON Tombstones (dateLastModified);
+
+-- Json views, one object per row alongside its sourcedId
+
+CREATE VIEW AcademicSessionsJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', a.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', a.dateLastModified
+        , 'title', a.title
+        , 'startDate', a.startDate
+        , 'endDate', a.endDate
+        , 'type', SessionType.token
+        , 'parent', CASE WHEN a.parentSourcedId IS NOT NULL THEN
+            jsonb_build_object(
+                'href', 'academicSessions/' || a.parentSourcedId
+                , 'sourcedId', a.parentSourcedId
+                , 'type', 'academicSession'
+            ) END
+        , 'children', (
+            SELECT jsonb_agg(jsonb_build_object(
+                'href', 'academicSessions/' || child.sourcedId
+                , 'sourcedId', child.sourcedId
+                , 'type', 'academicSession'
+            ) ORDER BY child.id)
+            FROM AcademicSessions child
+            WHERE child.parentSourcedId = a.sourcedId
+        )
+        , 'schoolYear', a.schoolYear
+    ) AS "academicSession"
+    , a.sourcedId AS sourcedId
+    FROM
+        AcademicSessions a
+        LEFT JOIN StatusType ON a.statusTypeId = StatusType.id
+        LEFT JOIN SessionType ON a.sessionTypeId = SessionType.id;
+
+CREATE VIEW PeriodsJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', Periods.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Periods.dateLastModified
+        , 'title', Periods.title
+        , 'periodCode', Periods.periodCode
+        , 'description', Periods.description
+        , 'orgs', (
+            SELECT jsonb_agg(jsonb_build_object(
+                'href', 'users/' || OrgPeriods.orgSourcedId
+                , 'sourcedId', OrgPeriods.orgSourcedId
+                , 'type', 'org'
+            ) ORDER BY OrgPeriods.orgSourcedId)
+            FROM OrgPeriods
+            WHERE OrgPeriods.periodSourcedId = Periods.sourcedId
+                AND OrgPeriods.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        )
+    ) AS "period"
+    , Periods.sourcedId AS sourcedId
+    FROM
+        Periods
+        LEFT JOIN StatusType ON Periods.statusTypeId = StatusType.id;
+
+CREATE VIEW SubjectsJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', Subjects.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Subjects.dateLastModified
+        , 'title', Subjects.title
+        , 'subjectCode', Subjects.subjectCode
+    ) AS "subject"
+    , Subjects.sourcedId AS sourcedId
+    FROM
+        Subjects
+        LEFT JOIN StatusType ON Subjects.statusTypeId = StatusType.id;
+
+CREATE VIEW CoursesJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', Courses.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Courses.dateLastModified
+        , 'title', Courses.title
+        , 'schoolYear', CASE WHEN Courses.schoolYearSourcedId IS NOT NULL THEN
+            jsonb_build_object(
+                'href', 'academicSessions/' || Courses.schoolYearSourcedId
+                , 'sourcedId', Courses.schoolYearSourcedId
+                , 'type', 'academicSession'
+            ) END
+        , 'courseCode', Courses.courseCode
+        , 'grades', (
+            SELECT jsonb_agg(GradeType.token ORDER BY CourseGrades.gradeTypeId)
+            FROM CourseGrades
+                INNER JOIN GradeType ON CourseGrades.gradeTypeId = GradeType.id
+            WHERE CourseGrades.courseSourcedId = Courses.sourcedId
+        )
+        , 'subjects', CS.titles
+        , 'org', jsonb_build_object(
+            'href', 'orgs/' || Courses.orgSourcedId
+            , 'sourcedId', Courses.orgSourcedId
+            , 'type', 'org'
+        )
+        , 'subjectCodes', CS.codes
+        , 'resources', (
+            SELECT jsonb_agg(jsonb_build_object(
+                'href', 'resources/' || CourseResources.resourceSourcedId
+                , 'sourcedId', CourseResources.resourceSourcedId
+                , 'type', 'resource'
+            ) ORDER BY CourseResources.resourceSourcedId)
+            FROM CourseResources
+            WHERE CourseResources.courseSourcedId = Courses.sourcedId
+                AND CourseResources.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        )
+    ) AS "course"
+    , Courses.sourcedId AS sourcedId
+    FROM
+        Courses
+        LEFT JOIN StatusType ON Courses.statusTypeId = StatusType.id
+        LEFT JOIN LATERAL (
+            SELECT
+                jsonb_agg(Subjects.title ORDER BY Subjects.sourcedId) AS titles
+                , jsonb_agg(Subjects.subjectCode ORDER BY Subjects.sourcedId) AS codes
+            FROM CourseSubjects
+                INNER JOIN Subjects ON CourseSubjects.subjectSourcedId = Subjects.sourcedId
+            WHERE CourseSubjects.courseSourcedId = Courses.sourcedId
+                AND CourseSubjects.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        ) AS CS ON true;
+
+CREATE VIEW DemographicsJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', Demographics.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Demographics.dateLastModified
+        , 'birthDate', Demographics.birthDate
+        , 'sex', GenderType.token
+        , 'americanIndianOrAlaskaNative', Demographics.americanIndianOrAlaskaNative
+        , 'asian', Demographics.asian
+        , 'blackOrAfricanAmerican', Demographics.blackOrAfricanAmerican
+        , 'nativeHawaiianOrOtherPacificIslander', Demographics.nativeHawaiianOrOtherPacificIslander
+        , 'white', Demographics.white
+        , 'demographicRaceTwoOrMoreRaces', Demographics.demographicRaceTwoOrMoreRaces
+        , 'hispanicOrLatinoEthnicity', Demographics.hispanicOrLatinoEthnicity
+        , 'countryOfBirthCode', Demographics.countryOfBirthCode
+        , 'stateOfBirthAbbreviation', Demographics.stateOfBirthAbbreviation
+        , 'cityOfBirth', Demographics.cityOfBirth
+        , 'publicSchoolResidenceStatus', Demographics.publicSchoolResidenceStatus
+    ) AS "demographics"
+    , Demographics.sourcedId AS sourcedId
+    FROM
+        Demographics
+        LEFT JOIN StatusType ON Demographics.statusTypeId = StatusType.id
+        LEFT JOIN GenderType ON Demographics.genderTypeId = GenderType.id;
+
+CREATE VIEW EnrollmentsJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', Enrollments.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Enrollments.dateLastModified
+        , 'role', RoleType.token
+        , 'primary', Enrollments."primary"
+        , 'user', jsonb_build_object(
+            'href', 'users/' || Enrollments.userSourcedId
+            , 'sourcedId', Enrollments.userSourcedId
+            , 'type', 'user'
+        )
+        , 'class', jsonb_build_object(
+            'href', 'classes/' || Enrollments.classSourcedId
+            , 'sourcedId', Enrollments.classSourcedId
+            , 'type', 'class'
+        )
+        , 'school', jsonb_build_object(
+            'href', 'orgs/' || Enrollments.orgSourcedId
+            , 'sourcedId', Enrollments.orgSourcedId
+            , 'type', 'org'
+        )
+        , 'beginDate', Enrollments.beginDate
+        , 'endDate', Enrollments.endDate
+    ) AS "enrollment"
+    , Enrollments.sourcedId AS sourcedId
+    FROM
+        Enrollments
+        LEFT JOIN StatusType ON Enrollments.statusTypeId = StatusType.id
+        LEFT JOIN RoleType ON Enrollments.roleTypeId = RoleType.id;
+
+CREATE VIEW OrgsJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', Orgs.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Orgs.dateLastModified
+        , 'name', Orgs.name
+        , 'type', OrgType.token
+        , 'identifier', Orgs.identifier
+        , 'parent', CASE WHEN Orgs.parentSourcedId IS NOT NULL THEN
+            jsonb_build_object(
+                'href', 'orgs/' || Orgs.parentSourcedId
+                , 'sourcedId', Orgs.parentSourcedId
+                , 'type', 'org'
+            ) END
+        , 'children', (
+            SELECT jsonb_agg(jsonb_build_object(
+                'href', 'orgs/' || child.sourcedId
+                , 'sourcedId', child.sourcedId
+                , 'type', 'org'
+            ) ORDER BY child.id)
+            FROM Orgs child
+            WHERE child.parentSourcedId = Orgs.sourcedId
+        )
+    ) AS "org"
+    , Orgs.sourcedId AS sourcedId
+    FROM
+        Orgs
+        LEFT JOIN StatusType ON Orgs.statusTypeId = StatusType.id
+        LEFT JOIN OrgType ON Orgs.orgTypeId = OrgType.id;
+
+CREATE VIEW ClassesJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', Classes.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Classes.dateLastModified
+        , 'title', Classes.title
+        , 'classCode', Classes.classCode
+        , 'classType', ClassType.token
+        , 'locations', Classes.location
+        , 'grades', (
+            SELECT jsonb_agg(GradeType.token ORDER BY ClassGrades.gradeTypeId)
+            FROM ClassGrades
+                INNER JOIN GradeType ON ClassGrades.gradeTypeId = GradeType.id
+            WHERE ClassGrades.classSourcedId = Classes.sourcedId
+                AND ClassGrades.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        )
+        , 'subjects', CS.titles
+        , 'course', jsonb_build_object(
+            'href', 'courses/' || Classes.courseSourcedId
+            , 'sourcedId', Classes.courseSourcedId
+            , 'type', 'course'
+        )
+        , 'school', jsonb_build_object(
+            'href', 'orgs/' || Classes.orgSourcedId
+            , 'sourcedId', Classes.orgSourcedId
+            , 'type', 'org'
+        )
+        , 'terms', (
+            SELECT jsonb_agg(jsonb_build_object(
+                'href', 'academicSessions/' || ClassAcademicSessions.academicSessionSourcedId
+                , 'sourcedId', ClassAcademicSessions.academicSessionSourcedId
+                , 'type', 'academicSession'
+            ) ORDER BY ClassAcademicSessions.academicSessionSourcedId)
+            FROM ClassAcademicSessions
+            WHERE ClassAcademicSessions.classSourcedId = Classes.sourcedId
+                AND ClassAcademicSessions.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        )
+        , 'subjectCodes', CS.codes
+        , 'periods', (
+            SELECT jsonb_agg(Periods.periodCode ORDER BY Periods.sourcedId)
+            FROM ClassPeriods
+                INNER JOIN Periods ON ClassPeriods.periodSourcedId = Periods.sourcedId
+            WHERE ClassPeriods.classSourcedId = Classes.sourcedId
+                AND ClassPeriods.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        )
+        , 'resources', (
+            SELECT jsonb_agg(jsonb_build_object(
+                'href', 'resources/' || ClassResources.resourceSourcedId
+                , 'sourcedId', ClassResources.resourceSourcedId
+                , 'type', 'resource'
+            ) ORDER BY ClassResources.resourceSourcedId)
+            FROM ClassResources
+            WHERE ClassResources.classSourcedId = Classes.sourcedId
+                AND ClassResources.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        )
+    ) AS "class"
+    , Classes.sourcedId AS sourcedId
+    FROM
+        Classes
+        LEFT JOIN StatusType ON Classes.statusTypeId = StatusType.id
+        LEFT JOIN ClassType ON Classes.classTypeId = ClassType.id
+        LEFT JOIN LATERAL (
+            SELECT
+                jsonb_agg(Subjects.title ORDER BY Subjects.sourcedId) AS titles
+                , jsonb_agg(Subjects.subjectCode ORDER BY Subjects.sourcedId) AS codes
+            FROM ClassSubjects
+                INNER JOIN Subjects ON ClassSubjects.subjectSourcedId = Subjects.sourcedId
+            WHERE ClassSubjects.classSourcedId = Classes.sourcedId
+                AND ClassSubjects.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        ) AS CS ON true;
+
+CREATE VIEW UsersJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', Users.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Users.dateLastModified
+        , 'username', Users.username
+        , 'userIds', (
+            SELECT jsonb_agg(jsonb_build_object(
+                'type', UserIds.type
+                , 'identifier', UserIds.identifier
+            ) ORDER BY UserIds.type)
+            FROM UserIds
+            WHERE UserIds.userSourcedId = Users.sourcedId
+                AND UserIds.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        )
+        , 'enabledUser', Users.enabledUser
+        , 'givenName', Users.givenName
+        , 'familyName', Users.familyName
+        , 'middleName', Users.middleName
+        , 'role', RoleType.token
+        , 'identifier', Users.identifier
+        , 'email', Users.email
+        , 'sms', Users.sms
+        , 'phone', Users.phone
+        , 'agents', (
+            SELECT jsonb_agg(jsonb_build_object(
+                'href', 'users/' || UserAgents.agentUserSourcedId
+                , 'sourcedId', UserAgents.agentUserSourcedId
+                , 'type', 'user'
+            ) ORDER BY UserAgents.agentUserSourcedId)
+            FROM UserAgents
+            WHERE UserAgents.userSourcedId = Users.sourcedId
+                AND UserAgents.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        )
+        , 'orgs', (
+            SELECT jsonb_agg(jsonb_build_object(
+                'href', 'orgs/' || UserOrgs.orgSourcedId
+                , 'sourcedId', UserOrgs.orgSourcedId
+                , 'type', 'org'
+            ) ORDER BY UserOrgs.orgSourcedId)
+            FROM UserOrgs
+            WHERE UserOrgs.userSourcedId = Users.sourcedId
+                AND UserOrgs.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        )
+        , 'grades', (
+            SELECT jsonb_agg(GradeType.token ORDER BY UserGrades.gradeTypeId)
+            FROM UserGrades
+                INNER JOIN GradeType ON UserGrades.gradeTypeId = GradeType.id
+            WHERE UserGrades.userSourcedId = Users.sourcedId
+                AND UserGrades.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        )
+        , 'password', Users.password
+    ) AS "user"
+    , Users.sourcedId AS sourcedId
+    FROM
+        Users
+        LEFT JOIN StatusType ON Users.statusTypeId = StatusType.id
+        LEFT JOIN RoleType ON Users.roleTypeId = RoleType.id;
+
+CREATE VIEW LineItemsJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', LineItems.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', LineItems.dateLastModified
+        , 'title', LineItems.title
+        , 'description', LineItems.description
+        , 'assignDate', LineItems.assignDate
+        , 'dueDate', LineItems.dueDate
+        , 'class', jsonb_build_object(
+            'href', 'classes/' || LineItems.classSourcedId
+            , 'sourcedId', LineItems.classSourcedId
+            , 'type', 'class'
+        )
+        , 'category', jsonb_build_object(
+            'href', 'categories/' || LineItems.categorySourcedId
+            , 'sourcedId', LineItems.categorySourcedId
+            , 'type', 'category'
+        )
+        , 'gradingPeriod', jsonb_build_object(
+            'href', 'academicSessions/' || LineItems.gradingPeriodSourcedId
+            , 'sourcedId', LineItems.gradingPeriodSourcedId
+            , 'type', 'academicSession'
+        )
+        , 'resultValueMin', LineItems.resultValueMin
+        , 'resultValueMax', LineItems.resultValueMax
+    ) AS "lineItem"
+    , LineItems.sourcedId AS sourcedId
+    FROM
+        LineItems
+        LEFT JOIN StatusType ON LineItems.statusTypeId = StatusType.id;
+
+CREATE VIEW CategoriesJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', Categories.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Categories.dateLastModified
+        , 'title', Categories.title
+    ) AS "category"
+    , Categories.sourcedId AS sourcedId
+    FROM
+        Categories
+        LEFT JOIN StatusType ON Categories.statusTypeId = StatusType.id;
+
+CREATE VIEW ResourcesJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', Resources.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Resources.dateLastModified
+        , 'title', Resources.title
+        , 'roles', (
+            SELECT jsonb_agg(RoleType.token ORDER BY ResourceRoles.roleTypeId)
+            FROM ResourceRoles
+                INNER JOIN RoleType ON ResourceRoles.roleTypeId = RoleType.id
+            WHERE ResourceRoles.resourceSourcedId = Resources.sourcedId
+                AND ResourceRoles.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        )
+        , 'importance', ImportanceType.token
+        , 'vendorResourceId', Resources.vendorResourceId
+        , 'vendorId', Resources.vendorId
+        , 'applicationId', Resources.applicationId
+    ) AS "resource"
+    , Resources.sourcedId AS sourcedId
+    FROM
+        Resources
+        LEFT JOIN StatusType ON Resources.statusTypeId = StatusType.id
+        LEFT JOIN ImportanceType ON Resources.importanceTypeId = ImportanceType.id;
+
+CREATE VIEW ResultsJson AS
+    SELECT jsonb_build_object(
+        'sourcedId', Results.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Results.dateLastModified
+        , 'lineItem', jsonb_build_object(
+            'href', 'lineItems/' || Results.lineItemSourcedId
+            , 'sourcedId', Results.lineItemSourcedId
+            , 'type', 'lineItem'
+        )
+        , 'student', jsonb_build_object(
+            'href', 'users/' || Results.studentSourcedId
+            , 'sourcedId', Results.studentSourcedId
+            , 'type', 'user'
+        )
+        , 'scoreStatus', ScoreStatusType.token
+        , 'score', Results.score
+        , 'scoreDate', Results.scoreDate
+        , 'comment', Results.comment
+    ) AS "result"
+    , Results.sourcedId AS sourcedId
+    FROM
+        Results
+        LEFT JOIN StatusType ON Results.statusTypeId = StatusType.id
+        LEFT JOIN ScoreStatusType ON Results.scoreStatusTypeId = ScoreStatusType.id;
+
+-- Upsert triggers, writing an object through its view. Links to other objects are first set
+-- to tobedeleted then upserted as active, so those no longer listed are left inactive
+
+CREATE FUNCTION UpsertAcademicSessionsJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO AcademicSessions (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , startDate
+        , endDate
+        , sessionTypeId
+        , parentSourcedId
+        , schoolYear
+    )
+    VALUES (
+        NEW."academicSession" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."academicSession" ->> 'status')
+        , utc_timestamp(NEW."academicSession" ->> 'dateLastModified')
+        , NEW."academicSession" ->> 'title'
+        , utc_date(NEW."academicSession" ->> 'startDate')
+        , utc_date(NEW."academicSession" ->> 'endDate')
+        , (SELECT id FROM SessionType WHERE token = NEW."academicSession" ->> 'type')
+        , NEW."academicSession" #>> '{parent,sourcedId}'
+        , NEW."academicSession" ->> 'schoolYear'
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , title = excluded.title
+        , startDate = excluded.startDate
+        , endDate = excluded.endDate
+        , sessionTypeId = excluded.sessionTypeId
+        , parentSourcedId = excluded.parentSourcedId
+        , schoolYear = excluded.schoolYear
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertAcademicSessionsJson
+    INSTEAD OF INSERT ON AcademicSessionsJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertAcademicSessionsJson();
+
+CREATE FUNCTION UpsertPeriodsJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO Periods (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , periodCode
+        , description
+    )
+    VALUES (
+        NEW."period" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."period" ->> 'status')
+        , utc_timestamp(NEW."period" ->> 'dateLastModified')
+        , NEW."period" ->> 'title'
+        , NEW."period" ->> 'periodCode'
+        , NEW."period" ->> 'description'
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , title = excluded.title
+        , periodCode = excluded.periodCode
+        , description = excluded.description
+    ;
+
+    UPDATE OrgPeriods
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE periodSourcedId = NEW."period" ->> 'sourcedId';
+
+    INSERT INTO OrgPeriods (periodSourcedId, statusTypeId, orgSourcedId)
+    SELECT DISTINCT
+        NEW."period" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , org.value ->> 'sourcedId'
+    FROM jsonb_array_elements(NEW."period" -> 'orgs') AS org
+    ON CONFLICT (periodSourcedId, orgSourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertPeriodsJson
+    INSTEAD OF INSERT ON PeriodsJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertPeriodsJson();
+
+CREATE FUNCTION UpsertSubjectsJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO Subjects (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , subjectCode
+    )
+    VALUES (
+        NEW."subject" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."subject" ->> 'status')
+        , utc_timestamp(NEW."subject" ->> 'dateLastModified')
+        , NEW."subject" ->> 'title'
+        , NEW."subject" ->> 'subjectCode'
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , title = excluded.title
+        , subjectCode = excluded.subjectCode
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertSubjectsJson
+    INSTEAD OF INSERT ON SubjectsJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertSubjectsJson();
+
+CREATE FUNCTION UpsertCoursesJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO Courses (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , schoolYearSourcedId
+        , courseCode
+        , orgSourcedId
+    )
+    VALUES (
+        NEW."course" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."course" ->> 'status')
+        , utc_timestamp(NEW."course" ->> 'dateLastModified')
+        , NEW."course" ->> 'title'
+        , NEW."course" #>> '{schoolYear,sourcedId}'
+        , NEW."course" ->> 'courseCode'
+        , NEW."course" #>> '{org,sourcedId}'
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , title = excluded.title
+        , schoolYearSourcedId = excluded.schoolYearSourcedId
+        , courseCode = excluded.courseCode
+        , orgSourcedId = excluded.orgSourcedId
+    ;
+
+    UPDATE CourseGrades
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE courseSourcedId = NEW."course" ->> 'sourcedId';
+
+    INSERT INTO CourseGrades (courseSourcedId, statusTypeId, gradeTypeId)
+    SELECT DISTINCT
+        NEW."course" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , GradeType.id
+    FROM jsonb_array_elements_text(NEW."course" -> 'grades') AS grades
+        INNER JOIN GradeType ON GradeType.token = grades.value
+    ON CONFLICT (courseSourcedId, gradeTypeId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+
+    UPDATE CourseSubjects
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE courseSourcedId = NEW."course" ->> 'sourcedId';
+
+    INSERT INTO CourseSubjects (courseSourcedId, statusTypeId, subjectSourcedId)
+    SELECT DISTINCT
+        NEW."course" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , Subjects.sourcedId
+    FROM jsonb_array_elements_text(NEW."course" -> 'subjectCodes') AS sc
+        INNER JOIN Subjects ON Subjects.subjectCode = sc.value
+    ON CONFLICT (courseSourcedId, subjectSourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+
+    UPDATE CourseResources
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE courseSourcedId = NEW."course" ->> 'sourcedId';
+
+    INSERT INTO CourseResources (courseSourcedId, statusTypeId, resourceSourcedId)
+    SELECT DISTINCT
+        NEW."course" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , resource.value ->> 'sourcedId'
+    FROM jsonb_array_elements(NEW."course" -> 'resources') AS resource
+    ON CONFLICT (courseSourcedId, resourceSourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertCoursesJson
+    INSTEAD OF INSERT ON CoursesJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertCoursesJson();
+
+CREATE FUNCTION UpsertDemographicsJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO Demographics (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , birthDate
+        , genderTypeId
+        , americanIndianOrAlaskaNative
+        , asian
+        , blackOrAfricanAmerican
+        , nativeHawaiianOrOtherPacificIslander
+        , white
+        , demographicRaceTwoOrMoreRaces
+        , hispanicOrLatinoEthnicity
+        , countryOfBirthCode
+        , stateOfBirthAbbreviation
+        , cityOfBirth
+        , publicSchoolResidenceStatus
+    )
+    VALUES (
+        NEW."demographics" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."demographics" ->> 'status')
+        , utc_timestamp(NEW."demographics" ->> 'dateLastModified')
+        , utc_date(NEW."demographics" ->> 'birthDate')
+        , (SELECT id FROM GenderType WHERE token = NEW."demographics" ->> 'sex')
+        , NEW."demographics" ->> 'americanIndianOrAlaskaNative'
+        , NEW."demographics" ->> 'asian'
+        , NEW."demographics" ->> 'blackOrAfricanAmerican'
+        , NEW."demographics" ->> 'nativeHawaiianOrOtherPacificIslander'
+        , NEW."demographics" ->> 'white'
+        , NEW."demographics" ->> 'demographicRaceTwoOrMoreRaces'
+        , NEW."demographics" ->> 'hispanicOrLatinoEthnicity'
+        , NEW."demographics" ->> 'countryOfBirthCode'
+        , NEW."demographics" ->> 'stateOfBirthAbbreviation'
+        , NEW."demographics" ->> 'cityOfBirth'
+        , NEW."demographics" ->> 'publicSchoolResidenceStatus'
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , birthDate = excluded.birthDate
+        , genderTypeId = excluded.genderTypeId
+        , americanIndianOrAlaskaNative = excluded.americanIndianOrAlaskaNative
+        , asian = excluded.asian
+        , blackOrAfricanAmerican = excluded.blackOrAfricanAmerican
+        , nativeHawaiianOrOtherPacificIslander = excluded.nativeHawaiianOrOtherPacificIslander
+        , white = excluded.white
+        , demographicRaceTwoOrMoreRaces = excluded.demographicRaceTwoOrMoreRaces
+        , hispanicOrLatinoEthnicity = excluded.hispanicOrLatinoEthnicity
+        , countryOfBirthCode = excluded.countryOfBirthCode
+        , stateOfBirthAbbreviation = excluded.stateOfBirthAbbreviation
+        , cityOfBirth = excluded.cityOfBirth
+        , publicSchoolResidenceStatus = excluded.publicSchoolResidenceStatus
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertDemographicsJson
+    INSTEAD OF INSERT ON DemographicsJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertDemographicsJson();
+
+CREATE FUNCTION UpsertEnrollmentsJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO Enrollments (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , userSourcedId
+        , classSourcedId
+        , orgSourcedId
+        , roleTypeId
+        , "primary"
+        , beginDate
+        , endDate
+    )
+    VALUES (
+        NEW."enrollment" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."enrollment" ->> 'status')
+        , utc_timestamp(NEW."enrollment" ->> 'dateLastModified')
+        , NEW."enrollment" #>> '{user,sourcedId}'
+        , NEW."enrollment" #>> '{class,sourcedId}'
+        , NEW."enrollment" #>> '{school,sourcedId}'
+        , (SELECT id FROM RoleType WHERE token = NEW."enrollment" ->> 'role')
+        , CAST(NEW."enrollment" ->> 'primary' AS smallint)
+        , utc_date(NEW."enrollment" ->> 'beginDate')
+        , utc_date(NEW."enrollment" ->> 'endDate')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , userSourcedId = excluded.userSourcedId
+        , classSourcedId = excluded.classSourcedId
+        , orgSourcedId = excluded.orgSourcedId
+        , roleTypeId = excluded.roleTypeId
+        , "primary" = excluded."primary"
+        , beginDate = excluded.beginDate
+        , endDate = excluded.endDate
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertEnrollmentsJson
+    INSTEAD OF INSERT ON EnrollmentsJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertEnrollmentsJson();
+
+CREATE FUNCTION UpsertOrgsJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO Orgs (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , name
+        , orgTypeId
+        , identifier
+        , parentSourcedId
+    )
+    VALUES (
+        NEW."org" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."org" ->> 'status')
+        , utc_timestamp(NEW."org" ->> 'dateLastModified')
+        , NEW."org" ->> 'name'
+        , (SELECT id FROM OrgType WHERE token = NEW."org" ->> 'type')
+        , NEW."org" ->> 'identifier'
+        , NEW."org" #>> '{parent,sourcedId}'
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , name = excluded.name
+        , orgTypeId = excluded.orgTypeId
+        , identifier = excluded.identifier
+        , parentSourcedId = excluded.parentSourcedId
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertOrgsJson
+    INSTEAD OF INSERT ON OrgsJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertOrgsJson();
+
+CREATE FUNCTION UpsertClassesJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO Classes (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , classCode
+        , classTypeId
+        , location
+        , courseSourcedId
+        , orgSourcedId
+    )
+    VALUES (
+        NEW."class" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."class" ->> 'status')
+        , utc_timestamp(NEW."class" ->> 'dateLastModified')
+        , NEW."class" ->> 'title'
+        , NEW."class" ->> 'classCode'
+        , (SELECT id FROM ClassType WHERE token = NEW."class" ->> 'classType')
+        , NEW."class" ->> 'location'
+        , NEW."class" #>> '{course,sourcedId}'
+        , NEW."class" #>> '{school,sourcedId}'
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , title = excluded.title
+        , classCode = excluded.classCode
+        , classTypeId = excluded.classTypeId
+        , location = excluded.location
+        , courseSourcedId = excluded.courseSourcedId
+        , orgSourcedId = excluded.orgSourcedId
+    ;
+
+    UPDATE ClassGrades
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE classSourcedId = NEW."class" ->> 'sourcedId';
+
+    INSERT INTO ClassGrades (classSourcedId, statusTypeId, gradeTypeId)
+    SELECT DISTINCT
+        NEW."class" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , GradeType.id
+    FROM jsonb_array_elements_text(NEW."class" -> 'grades') AS grades
+        INNER JOIN GradeType ON GradeType.token = grades.value
+    ON CONFLICT (classSourcedId, gradeTypeId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+
+    UPDATE ClassSubjects
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE classSourcedId = NEW."class" ->> 'sourcedId';
+
+    INSERT INTO ClassSubjects (classSourcedId, statusTypeId, subjectSourcedId)
+    SELECT DISTINCT
+        NEW."class" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , Subjects.sourcedId
+    FROM jsonb_array_elements_text(NEW."class" -> 'subjectCodes') AS sc
+        INNER JOIN Subjects ON Subjects.subjectCode = sc.value
+    ON CONFLICT (classSourcedId, subjectSourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+
+    UPDATE ClassAcademicSessions
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE classSourcedId = NEW."class" ->> 'sourcedId';
+
+    INSERT INTO ClassAcademicSessions (classSourcedId, statusTypeId, academicSessionSourcedId)
+    SELECT DISTINCT
+        NEW."class" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , term.value ->> 'sourcedId'
+    FROM jsonb_array_elements(NEW."class" -> 'terms') AS term
+    ON CONFLICT (classSourcedId, academicSessionSourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+
+    UPDATE ClassPeriods
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE classSourcedId = NEW."class" ->> 'sourcedId';
+
+    INSERT INTO ClassPeriods (classSourcedId, statusTypeId, periodSourcedId)
+    SELECT DISTINCT
+        NEW."class" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , Periods.sourcedId
+    FROM jsonb_array_elements_text(NEW."class" -> 'periods') AS pc
+        INNER JOIN Periods ON Periods.periodCode = pc.value
+    ON CONFLICT (classSourcedId, periodSourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+
+    UPDATE ClassResources
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE classSourcedId = NEW."class" ->> 'sourcedId';
+
+    INSERT INTO ClassResources (classSourcedId, statusTypeId, resourceSourcedId)
+    SELECT DISTINCT
+        NEW."class" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , resource.value ->> 'sourcedId'
+    FROM jsonb_array_elements(NEW."class" -> 'resources') AS resource
+    ON CONFLICT (classSourcedId, resourceSourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertClassesJson
+    INSTEAD OF INSERT ON ClassesJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertClassesJson();
+
+CREATE FUNCTION UpsertUsersJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO Users (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , username
+        , enabledUser
+        , givenName
+        , familyName
+        , middleName
+        , roleTypeId
+        , identifier
+        , email
+        , sms
+        , phone
+        , password
+    )
+    VALUES (
+        NEW."user" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."user" ->> 'status')
+        , utc_timestamp(NEW."user" ->> 'dateLastModified')
+        , NEW."user" ->> 'username'
+        , CAST(NEW."user" ->> 'enabledUser' AS smallint)
+        , NEW."user" ->> 'givenName'
+        , NEW."user" ->> 'familyName'
+        , NEW."user" ->> 'middleName'
+        , (SELECT id FROM RoleType WHERE token = NEW."user" ->> 'role')
+        , NEW."user" ->> 'identifier'
+        , NEW."user" ->> 'email'
+        , NEW."user" ->> 'sms'
+        , NEW."user" ->> 'phone'
+        , NEW."user" ->> 'password'
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , username = excluded.username
+        , enabledUser = excluded.enabledUser
+        , givenName = excluded.givenName
+        , familyName = excluded.familyName
+        , middleName = excluded.middleName
+        , roleTypeId = excluded.roleTypeId
+        , identifier = excluded.identifier
+        , email = excluded.email
+        , sms = excluded.sms
+        , phone = excluded.phone
+        , password = excluded.password
+    ;
+
+    UPDATE UserIds
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE userSourcedId = NEW."user" ->> 'sourcedId';
+
+    INSERT INTO UserIds (userSourcedId, statusTypeId, type, identifier)
+    SELECT DISTINCT ON (ids.value ->> 'type')
+        NEW."user" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , ids.value ->> 'type'
+        , ids.value ->> 'identifier'
+    FROM jsonb_array_elements(NEW."user" -> 'userIds') WITH ORDINALITY AS ids (value, n)
+    ORDER BY ids.value ->> 'type', ids.n DESC
+    ON CONFLICT (userSourcedId, type) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , identifier = excluded.identifier
+    ;
+
+    UPDATE UserOrgs
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE userSourcedId = NEW."user" ->> 'sourcedId';
+
+    INSERT INTO UserOrgs (userSourcedId, statusTypeId, orgSourcedId)
+    SELECT DISTINCT
+        NEW."user" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , org.value ->> 'sourcedId'
+    FROM jsonb_array_elements(NEW."user" -> 'orgs') AS org
+    ON CONFLICT (userSourcedId, orgSourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+
+    UPDATE UserAgents
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE userSourcedId = NEW."user" ->> 'sourcedId';
+
+    INSERT INTO UserAgents (userSourcedId, statusTypeId, agentUserSourcedId)
+    SELECT DISTINCT
+        NEW."user" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , agent.value ->> 'sourcedId'
+    FROM jsonb_array_elements(NEW."user" -> 'agents') AS agent
+    ON CONFLICT (userSourcedId, agentUserSourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+
+    UPDATE UserGrades
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE userSourcedId = NEW."user" ->> 'sourcedId';
+
+    INSERT INTO UserGrades (userSourcedId, statusTypeId, gradeTypeId)
+    SELECT DISTINCT
+        NEW."user" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , GradeType.id
+    FROM jsonb_array_elements_text(NEW."user" -> 'grades') AS grades
+        INNER JOIN GradeType ON GradeType.token = grades.value
+    ON CONFLICT (userSourcedId, gradeTypeId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertUsersJson
+    INSTEAD OF INSERT ON UsersJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertUsersJson();
+
+CREATE FUNCTION UpsertLineItemsJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO LineItems (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , description
+        , assignDate
+        , dueDate
+        , classSourcedId
+        , categorySourcedId
+        , gradingPeriodSourcedId
+        , resultValueMin
+        , resultValueMax
+    )
+    VALUES (
+        NEW."lineItem" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."lineItem" ->> 'status')
+        , utc_timestamp(NEW."lineItem" ->> 'dateLastModified')
+        , NEW."lineItem" ->> 'title'
+        , NEW."lineItem" ->> 'description'
+        , utc_date(NEW."lineItem" ->> 'assignDate')
+        , utc_date(NEW."lineItem" ->> 'dueDate')
+        , NEW."lineItem" #>> '{class,sourcedId}'
+        , NEW."lineItem" #>> '{category,sourcedId}'
+        , NEW."lineItem" #>> '{gradingPeriod,sourcedId}'
+        , CAST(NEW."lineItem" ->> 'resultValueMin' AS double precision)
+        , CAST(NEW."lineItem" ->> 'resultValueMax' AS double precision)
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , title = excluded.title
+        , description = excluded.description
+        , assignDate = excluded.assignDate
+        , dueDate = excluded.dueDate
+        , classSourcedId = excluded.classSourcedId
+        , categorySourcedId = excluded.categorySourcedId
+        , gradingPeriodSourcedId = excluded.gradingPeriodSourcedId
+        , resultValueMin = excluded.resultValueMin
+        , resultValueMax = excluded.resultValueMax
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertLineItemsJson
+    INSTEAD OF INSERT ON LineItemsJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertLineItemsJson();
+
+CREATE FUNCTION UpsertCategoriesJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO Categories (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+    )
+    VALUES (
+        NEW."category" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."category" ->> 'status')
+        , utc_timestamp(NEW."category" ->> 'dateLastModified')
+        , NEW."category" ->> 'title'
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , title = excluded.title
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertCategoriesJson
+    INSTEAD OF INSERT ON CategoriesJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertCategoriesJson();
+
+CREATE FUNCTION UpsertResultsJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO Results (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , lineItemSourcedId
+        , studentSourcedId
+        , scoreStatusTypeId
+        , score
+        , scoreDate
+        , comment
+    )
+    VALUES (
+        NEW."result" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."result" ->> 'status')
+        , utc_timestamp(NEW."result" ->> 'dateLastModified')
+        , NEW."result" #>> '{lineItem,sourcedId}'
+        , NEW."result" #>> '{student,sourcedId}'
+        , (SELECT id FROM ScoreStatusType WHERE token = NEW."result" ->> 'scoreStatus')
+        , CAST(NEW."result" ->> 'score' AS double precision)
+        , utc_date(NEW."result" ->> 'scoreDate')
+        , NEW."result" ->> 'comment'
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , lineItemSourcedId = excluded.lineItemSourcedId
+        , studentSourcedId = excluded.studentSourcedId
+        , scoreStatusTypeId = excluded.scoreStatusTypeId
+        , score = excluded.score
+        , scoreDate = excluded.scoreDate
+        , comment = excluded.comment
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertResultsJson
+    INSTEAD OF INSERT ON ResultsJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertResultsJson();
+
+CREATE FUNCTION UpsertResourcesJson() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO Resources (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , importanceTypeId
+        , vendorResourceId
+        , vendorId
+        , applicationId
+    )
+    VALUES (
+        NEW."resource" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = NEW."resource" ->> 'status')
+        , utc_timestamp(NEW."resource" ->> 'dateLastModified')
+        , NEW."resource" ->> 'title'
+        , (SELECT id FROM ImportanceType WHERE token = NEW."resource" ->> 'importance')
+        , NEW."resource" ->> 'vendorResourceId'
+        , NEW."resource" ->> 'vendorId'
+        , NEW."resource" ->> 'applicationId'
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+        , dateLastModified = excluded.dateLastModified
+        , title = excluded.title
+        , importanceTypeId = excluded.importanceTypeId
+        , vendorResourceId = excluded.vendorResourceId
+        , vendorId = excluded.vendorId
+        , applicationId = excluded.applicationId
+    ;
+
+    UPDATE ResourceRoles
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE resourceSourcedId = NEW."resource" ->> 'sourcedId';
+
+    INSERT INTO ResourceRoles (resourceSourcedId, statusTypeId, roleTypeId)
+    SELECT DISTINCT
+        NEW."resource" ->> 'sourcedId'
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , RoleType.id
+    FROM jsonb_array_elements_text(NEW."resource" -> 'roles') AS roles
+        INNER JOIN RoleType ON RoleType.token = roles.value
+    ON CONFLICT (resourceSourcedId, roleTypeId) DO UPDATE SET
+        statusTypeId = excluded.statusTypeId
+    ;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER TriggerUpsertResourcesJson
+    INSTEAD OF INSERT ON ResourcesJson
+    FOR EACH ROW EXECUTE FUNCTION UpsertResourcesJson();
+
+INSERT INTO scopes (scope) VALUES
+    ('roster-core.readonly')
+    , ('roster-core.createput')
+    , ('roster-core.delete')
+    , ('admin.readonly')
+    , ('admin.delete')
+    , ('gradebook.readonly')
+    , ('gradebook.createput')
+    , ('gradebook.delete')
+    , ('resource.readonly')
+    , ('resource.createput')
+    , ('resource.delete')
+    , ('roster-demographics.readonly')
+    , ('roster-demographics.createput')
+    , ('roster-demographics.delete')
+    ;
+
+INSERT INTO StatusType (token) VALUES
+    ('active')
+    , ('tobedeleted')
+    , ('inactive')
+    ;
+
+INSERT INTO OrgType (token) VALUES
+    ('department')
+    , ('school')
+    , ('district')
+    , ('local')
+    , ('state')
+    , ('national')
+    ;
+
+INSERT INTO ClassType (token) VALUES
+    ('homeroom')
+    , ('scheduled')
+    ;
+
+INSERT INTO RoleType (token) VALUES
+    ('administrator')
+    , ('aide')
+    , ('guardian')
+    , ('parent')
+    , ('proctor')
+    , ('relative')
+    , ('student')
+    , ('teacher')
+    ;
+
+INSERT INTO SessionType (token) VALUES
+    ('gradingPeriod')
+    , ('semester')
+    , ('schoolYear')
+    , ('term')
+    ;
+
+INSERT INTO GenderType (token) VALUES
+    ('male')
+    , ('female')
+    ;
+
+INSERT INTO ImportanceType (token) VALUES
+    ('primary')
+    , ('secondary')
+    ;
+
+INSERT INTO ScoreStatusType (token) VALUES
+    ('exempt')
+    , ('fully graded')
+    , ('not submitted')
+    , ('partially graded')
+    , ('submitted')
+    ;
+
+INSERT INTO GradeType (token, description) VALUES
+    ('IT', 'Infant/toddler')
+    , ('PR', 'Preschool')
+    , ('PK', 'Prekindergarten')
+    , ('TK', 'Transitional Kindergarten')
+    , ('KG', 'Kindergarten')
+    , ('01', 'First grade')
+    , ('02', 'Second grade')
+    , ('03', 'Third grade')
+    , ('04', 'Fourth grade')
+    , ('05', 'Fifth grade')
+    , ('06', 'Sixth grade')
+    , ('07', 'Seventh grade')
+    , ('08', 'Eigth grade')
+    , ('09', 'Ninth grade')
+    , ('10', 'Tenth grade')
+    , ('11', 'Eleventh grade')
+    , ('12', 'Twelfth grade')
+    , ('13', 'Grade 13')
+    , ('PS', 'Postsecondary')
+    , ('UG', 'Ungraded')
+    , ('Other', 'Other')
+    ;
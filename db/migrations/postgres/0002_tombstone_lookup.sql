@@ -0,0 +1,2 @@
+-- Tombstones are looked up by object so writing a purged object again can remove them
+CREATE INDEX TombstonesTypeSourcedIdIndex ON Tombstones (type, sourcedId);
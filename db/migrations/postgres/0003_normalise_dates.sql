@@ -0,0 +1,16 @@
+-- Dates written before they were normalised on the way in are kept in the UTC format they
+-- are now stored in, so they compare and sort with those written since
+UPDATE AcademicSessions SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Periods SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Subjects SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Classes SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Courses SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Orgs SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Users SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Enrollments SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Demographics SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE LineItems SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Categories SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Results SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Resources SET dateLastModified = utc_timestamp(dateLastModified);
+UPDATE Tombstones SET dateLastModified = utc_timestamp(dateLastModified);
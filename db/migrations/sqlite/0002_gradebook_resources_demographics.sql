@@ -0,0 +1,1650 @@
+-- Gradebook, resources and demographics, indexes and tombstones for delta queries, and
+-- the sourcedId of each object alongside its json in the views
+
+CREATE INDEX IF NOT EXISTS AcademicSessionsDateLastModifiedIndex ON AcademicSessions (dateLastModified);
+
+CREATE INDEX IF NOT EXISTS SubjectsDateLastModifiedIndex ON Subjects (dateLastModified);
+
+CREATE INDEX IF NOT EXISTS ClassesDateLastModifiedIndex ON Classes (dateLastModified);
+
+CREATE INDEX IF NOT EXISTS PeriodsDateLastModifiedIndex ON Periods (dateLastModified);
+
+CREATE INDEX IF NOT EXISTS CoursesDateLastModifiedIndex ON Courses (dateLastModified);
+
+-- OR:4.5
+CREATE TABLE IF NOT EXISTS Demographics (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "sourcedId" text UNIQUE NOT NULL
+    , "statusTypeId" integer NOT NULL
+    , "dateLastModified" text NOT NULL
+    , "birthDate" text
+    , "genderTypeId" integer
+    , "americanIndianOrAlaskaNative" text -- 'true'/'false'
+    , "asian" text
+    , "blackOrAfricanAmerican" text
+    , "nativeHawaiianOrOtherPacificIslander" text
+    , "white" text
+    , "demographicRaceTwoOrMoreRaces" text
+    , "hispanicOrLatinoEthnicity" text
+    , "countryOfBirthCode" text
+    , "stateOfBirthAbbreviation" text
+    , "cityOfBirth" text
+    , "publicSchoolResidenceStatus" text
+    , FOREIGN KEY (statusTypeId) REFERENCES StatusType (id)
+    , FOREIGN KEY (genderTypeId) REFERENCES GenderType (id)
+);
+
+CREATE INDEX IF NOT EXISTS DemographicsDateLastModifiedIndex ON Demographics (dateLastModified);
+
+CREATE INDEX IF NOT EXISTS EnrollmentsDateLastModifiedIndex ON Enrollments (dateLastModified);
+
+CREATE INDEX IF NOT EXISTS OrgsDateLastModifiedIndex ON Orgs (dateLastModified);
+
+CREATE INDEX IF NOT EXISTS UsersDateLastModifiedIndex ON Users (dateLastModified);
+
+-- OR:4.7
+CREATE TABLE IF NOT EXISTS LineItems (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "sourcedId" text UNIQUE NOT NULL
+    , "statusTypeId" integer NOT NULL
+    , "dateLastModified" text NOT NULL
+    , "title" text NOT NULL
+    , "description" text
+    , "assignDate" text NOT NULL
+    , "dueDate" text NOT NULL
+    , "classSourcedId" text NOT NULL
+    , "categorySourcedId" text NOT NULL
+    , "gradingPeriodSourcedId" text NOT NULL
+    , "resultValueMin" real NOT NULL
+    , "resultValueMax" real NOT NULL
+    , FOREIGN KEY (statusTypeId) REFERENCES StatusType (id)
+    , FOREIGN KEY (classSourcedId) REFERENCES Classes (sourcedId)
+    , FOREIGN KEY (categorySourcedId) REFERENCES Categories (sourcedId)
+    , FOREIGN KEY (gradingPeriodSourcedId) REFERENCES AcademicSessions (sourcedId)
+);
+
+CREATE INDEX IF NOT EXISTS LineItemsDateLastModifiedIndex ON LineItems (dateLastModified);
+
+-- OR:4.8
+CREATE TABLE IF NOT EXISTS Categories (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "sourcedId" text UNIQUE NOT NULL
+    , "statusTypeId" integer NOT NULL
+    , "dateLastModified" text NOT NULL
+    , "title" text NOT NULL
+    , FOREIGN KEY (statusTypeId) REFERENCES StatusType (id)
+);
+
+CREATE INDEX IF NOT EXISTS CategoriesDateLastModifiedIndex ON Categories (dateLastModified);
+
+-- OR:4.10
+CREATE TABLE IF NOT EXISTS Resources (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "sourcedId" text UNIQUE NOT NULL
+    , "statusTypeId" integer NOT NULL
+    , "dateLastModified" text NOT NULL
+    , "title" text
+    , "importanceTypeId" integer
+    , "vendorResourceId" text NOT NULL
+    , "vendorId" text
+    , "applicationId" text
+    , FOREIGN KEY (statusTypeId) REFERENCES StatusType (id)
+    , FOREIGN KEY (importanceTypeId) REFERENCES ImportanceType (id)
+);
+
+CREATE INDEX IF NOT EXISTS ResourcesDateLastModifiedIndex ON Resources (dateLastModified);
+
+CREATE TABLE IF NOT EXISTS ResourceRoles (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "statusTypeId" integer NOT NULL
+    , "resourceSourcedId" text NOT NULL
+    , "roleTypeId" integer NOT NULL
+    , FOREIGN KEY (statusTypeId) REFERENCES StatusType (id)
+    , FOREIGN KEY (resourceSourcedId) REFERENCES Resources (sourcedId)
+    , FOREIGN KEY (roleTypeId) REFERENCES RoleType (id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS ResourceRolesIndex ON ResourceRoles (resourceSourcedId, roleTypeId);
+
+CREATE TABLE IF NOT EXISTS ClassResources (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "statusTypeId" integer NOT NULL
+    , "classSourcedId" text NOT NULL
+    , "resourceSourcedId" text NOT NULL
+    , FOREIGN KEY (statusTypeId) REFERENCES StatusType (id)
+    , FOREIGN KEY (classSourcedId) REFERENCES Classes (sourcedId)
+    , FOREIGN KEY (resourceSourcedId) REFERENCES Resources (sourcedId)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS ClassResourcesIndex ON ClassResources (classSourcedId, resourceSourcedId);
+
+CREATE TABLE IF NOT EXISTS CourseResources (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "statusTypeId" integer NOT NULL
+    , "courseSourcedId" text NOT NULL
+    , "resourceSourcedId" text NOT NULL
+    , FOREIGN KEY (statusTypeId) REFERENCES StatusType (id)
+    , FOREIGN KEY (courseSourcedId) REFERENCES Courses (sourcedId)
+    , FOREIGN KEY (resourceSourcedId) REFERENCES Resources (sourcedId)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS CourseResourcesIndex ON CourseResources (courseSourcedId, resourceSourcedId);
+
+-- OR:4.11
+CREATE TABLE IF NOT EXISTS Results (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "sourcedId" text UNIQUE NOT NULL
+    , "statusTypeId" integer NOT NULL
+    , "dateLastModified" text NOT NULL
+    , "lineItemSourcedId" text NOT NULL
+    , "studentSourcedId" text NOT NULL
+    , "scoreStatusTypeId" integer NOT NULL
+    , "score" real NOT NULL
+    , "scoreDate" text NOT NULL
+    , "comment" text
+    , FOREIGN KEY (statusTypeId) REFERENCES StatusType (id)
+    , FOREIGN KEY (lineItemSourcedId) REFERENCES LineItems (sourcedId)
+    , FOREIGN KEY (studentSourcedId) REFERENCES Users (sourcedId)
+    , FOREIGN KEY (scoreStatusTypeId) REFERENCES ScoreStatusType (id)
+);
+
+CREATE INDEX IF NOT EXISTS ResultsDateLastModifiedIndex ON Results (dateLastModified);
+
+-- purged objects, kept so delta consumers can still see the deletion
+CREATE TABLE IF NOT EXISTS Tombstones (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "type" text NOT NULL
+    , "sourcedId" text NOT NULL
+    , "dateLastModified" text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS TombstonesDateLastModifiedIndex ON Tombstones (dateLastModified);
+
+-- OR:4.13
+
+CREATE TABLE IF NOT EXISTS ClassType (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "token" text UNIQUE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS GenderType (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "token" text UNIQUE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ImportanceType (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "token" text UNIQUE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ScoreStatusType (
+    "id" integer PRIMARY KEY AUTOINCREMENT
+    , "token" text UNIQUE NOT NULL
+);
+
+-- views are replaced as a whole, dropping a view drops its triggers. The JsonArray and
+-- VwORGet views are not recreated, objects are read from the Json views one per row
+DROP VIEW IF EXISTS AcademicSessionsJsonArray;
+DROP VIEW IF EXISTS AcademicSessionsJson;
+DROP VIEW IF EXISTS VwORGetAcademicSession;
+DROP VIEW IF EXISTS VwORGetAllGradingPeriods;
+DROP VIEW IF EXISTS VwORGetGradingPeriod;
+DROP VIEW IF EXISTS VwORGetAllTerms;
+DROP VIEW IF EXISTS VwORGetTerm;
+DROP VIEW IF EXISTS PeriodsJsonArray;
+DROP VIEW IF EXISTS PeriodsJson;
+DROP VIEW IF EXISTS SubjectsJsonArray;
+DROP VIEW IF EXISTS SubjectsJson;
+DROP VIEW IF EXISTS CoursesJsonArray;
+DROP VIEW IF EXISTS CoursesJson;
+DROP VIEW IF EXISTS VwORGetCourse;
+DROP VIEW IF EXISTS DemographicsJson;
+DROP VIEW IF EXISTS EnrollmentsJsonArray;
+DROP VIEW IF EXISTS EnrollmentsJson;
+DROP VIEW IF EXISTS VwORGetEnrollment;
+DROP VIEW IF EXISTS OrgsJsonArray;
+DROP VIEW IF EXISTS OrgsJson;
+DROP VIEW IF EXISTS VwORGetOrg;
+DROP VIEW IF EXISTS VwORGetAllSchools;
+DROP VIEW IF EXISTS VwORGetSchool;
+DROP VIEW IF EXISTS ClassesJsonArray;
+DROP VIEW IF EXISTS ClassesJson;
+DROP VIEW IF EXISTS VwORGetClass;
+DROP VIEW IF EXISTS UsersJsonArray;
+DROP VIEW IF EXISTS UsersJson;
+DROP VIEW IF EXISTS VwORGetUser;
+DROP VIEW IF EXISTS VwORGetAllStudents;
+DROP VIEW IF EXISTS VwORGetStudent;
+DROP VIEW IF EXISTS VwORGetAllTeachers;
+DROP VIEW IF EXISTS VwORGetTeacher;
+DROP VIEW IF EXISTS LineItemsJson;
+DROP VIEW IF EXISTS CategoriesJson;
+DROP VIEW IF EXISTS ResourcesJson;
+DROP VIEW IF EXISTS ResultsJson;
+
+-- OR:5.1
+CREATE VIEW IF NOT EXISTS AcademicSessionsJson AS
+    SELECT json_object(
+        'sourcedId', a.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', a.dateLastModified
+        , 'title', a.title
+        , 'startDate', a.startDate
+        , 'endDate', a.endDate
+        , 'type', SessionType.token
+        , 'parent', CASE WHEN a.parentSourcedId IS NOT NULL THEN
+            json_object(
+                'href', 'academicSessions/' || a.parentSourcedId
+                , 'sourcedId', a.parentSourcedId
+                , 'type', 'academicSession'
+            ) ELSE NULL
+        END
+        , 'children', CASE WHEN ap.sourcedId IS NOT NULL THEN
+            json_group_array(
+                json_object(
+                    'href', 'academicSessions/' || ap.sourcedId
+                    , 'sourcedId', ap.sourcedId
+                    , 'type', 'academicSession'
+                )
+            ) ELSE NULL
+        END
+        , 'schoolYear', a.schoolYear
+    ) AS 'academicSession'
+    , a.sourcedId AS sourcedId
+    FROM
+        AcademicSessions a
+        LEFT JOIN AcademicSessions ap ON a.sourcedId = ap.parentSourcedId
+        LEFT JOIN StatusType ON a.statusTypeId = StatusType.id
+        LEFT JOIN SessionType ON a.sessionTypeId = SessionType.id
+    GROUP BY
+        a.sourcedId
+    ORDER BY
+        a.sourcedId
+;
+
+CREATE VIEW IF NOT EXISTS PeriodsJson AS
+    SELECT json_object(
+        'sourcedId', Periods.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Periods.dateLastModified
+        , 'title', Periods.title
+        , 'periodCode', Periods.periodCode
+        , 'description', Periods.description
+        , 'orgs', json(OP.orgs)
+    ) AS 'period'
+    , Periods.sourcedId AS sourcedId
+    FROM
+        Periods
+        LEFT JOIN StatusType ON Periods.statusTypeId = StatusType.id
+        LEFT JOIN (
+            SELECT
+                periodSourcedId
+                , json_group_array(json_object(
+                    'href', 'users/' || OrgPeriods.orgSourcedId
+                    , 'sourcedId', OrgPeriods.orgSourcedId
+                    , 'type', 'org'
+                )) AS orgs
+            FROM OrgPeriods
+            WHERE statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            GROUP BY periodSourcedId
+        ) AS OP ON Periods.sourcedId = OP.periodSourcedId
+    GROUP BY
+        Periods.sourcedId
+    ORDER BY
+        Periods.sourcedId
+;
+
+CREATE VIEW IF NOT EXISTS SubjectsJson AS
+    SELECT json_object(
+        'sourcedId', Subjects.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Subjects.dateLastModified
+        , 'title', Subjects.title
+        , 'subjectCode', Subjects.subjectCode
+    ) AS 'subject'
+    , Subjects.sourcedId AS sourcedId
+    FROM
+        Subjects
+        LEFT JOIN StatusType ON Subjects.statusTypeId = StatusType.id
+    ORDER BY
+        Subjects.sourcedId
+;
+
+-- OR 5.3
+CREATE VIEW IF NOT EXISTS CoursesJson AS
+    SELECT json_object(
+        'sourcedId', Courses.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Courses.dateLastModified
+        , 'title', Courses.title
+        , 'schoolYear', CASE WHEN Courses.schoolYearSourcedId IS NOT NULL THEN
+            json_object (
+                    'href', 'academicSessions/' || Courses.schoolYearSourcedId
+                    , 'sourcedId', Courses.schoolYearSourcedId
+                    , 'type', 'academicSession'
+            ) ELSE NULL END
+        , 'courseCode', Courses.courseCode
+        , 'grades', CASE WHEN CourseGrades.courseSourcedId IS NOT NULL THEN
+            json_group_array(GradeType.token)
+        ELSE NULL END
+        , 'subjects', CASE WHEN CourseSubjects.courseSourcedId IS NOT NULL THEN
+            json_group_array(Subjects.title)
+        ELSE NULL END
+        , 'org', json_object(
+            'href', 'orgs/' || Courses.orgSourcedId
+            , 'sourcedId', Courses.orgSourcedId
+            , 'type', 'org'
+        )
+        , 'subjectCodes', CASE WHEN CourseSubjects.courseSourcedId IS NOT NULL THEN
+            json_group_array(Subjects.subjectCode)
+        ELSE NULL END
+        , 'resources', json(CR.resources)
+    ) AS 'course'
+    , Courses.sourcedId AS sourcedId
+    FROM
+        Courses
+        LEFT JOIN StatusType ON Courses.statusTypeId = StatusType.id
+        LEFT JOIN (
+            SELECT
+                courseSourcedId
+                , json_group_array(json_object(
+                    'href', 'resources/' || CourseResources.resourceSourcedId
+                    , 'sourcedId', CourseResources.resourceSourcedId
+                    , 'type', 'resource'
+                )) AS resources
+            FROM CourseResources
+            WHERE statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            GROUP BY courseSourcedId
+        ) AS CR ON Courses.sourcedId = CR.courseSourcedId
+        LEFT JOIN CourseGrades ON Courses.sourcedId = CourseGrades.courseSourcedId
+        LEFT JOIN GradeType ON CourseGrades.gradeTypeId = GradeType.id
+        LEFT JOIN CourseSubjects ON Courses.sourcedId = CourseSubjects.courseSourcedId
+        LEFT JOIN Subjects ON CourseSubjects.subjectSourcedId = Subjects.id
+    GROUP BY
+        Courses.sourcedId
+    ORDER BY
+        Courses.sourcedId
+;
+
+-- OR 5.4
+CREATE VIEW IF NOT EXISTS DemographicsJson AS
+    SELECT json_object(
+        'sourcedId', Demographics.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Demographics.dateLastModified
+        , 'birthDate', Demographics.birthDate
+        , 'sex', GenderType.token
+        , 'americanIndianOrAlaskaNative', Demographics.americanIndianOrAlaskaNative
+        , 'asian', Demographics.asian
+        , 'blackOrAfricanAmerican', Demographics.blackOrAfricanAmerican
+        , 'nativeHawaiianOrOtherPacificIslander', Demographics.nativeHawaiianOrOtherPacificIslander
+        , 'white', Demographics.white
+        , 'demographicRaceTwoOrMoreRaces', Demographics.demographicRaceTwoOrMoreRaces
+        , 'hispanicOrLatinoEthnicity', Demographics.hispanicOrLatinoEthnicity
+        , 'countryOfBirthCode', Demographics.countryOfBirthCode
+        , 'stateOfBirthAbbreviation', Demographics.stateOfBirthAbbreviation
+        , 'cityOfBirth', Demographics.cityOfBirth
+        , 'publicSchoolResidenceStatus', Demographics.publicSchoolResidenceStatus
+    ) AS 'demographics'
+    , Demographics.sourcedId AS sourcedId
+    FROM
+        Demographics
+        LEFT JOIN StatusType ON Demographics.statusTypeId = StatusType.id
+        LEFT JOIN GenderType ON Demographics.genderTypeId = GenderType.id
+    ORDER BY
+        Demographics.sourcedId
+;
+
+-- OR 5.5
+CREATE VIEW IF NOT EXISTS EnrollmentsJson AS
+    SELECT json_object(
+        'sourcedId', Enrollments.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Enrollments.dateLastModified
+        , 'role', RoleType.token
+        , 'primary', Enrollments."primary"
+        , 'user', json_object(
+            'href', 'users/' || Enrollments.userSourcedId
+            , 'sourcedId', Enrollments.userSourcedId
+            , 'type', 'user'
+        )
+        , 'class', json_object(
+            'href', 'classes/' || Enrollments.classSourcedId
+            , 'sourcedId', Enrollments.classSourcedId
+            , 'type', 'class'
+        )
+        , 'school', json_object(
+            'href', 'orgs/' || Enrollments.orgSourcedId
+            , 'sourcedId', Enrollments.orgSourcedId
+            , 'type', 'org'
+        )
+        , 'beginDate', Enrollments.beginDate
+        , 'endDate', Enrollments.endDate
+    ) AS 'enrollment'
+    , Enrollments.sourcedId AS sourcedId
+    FROM
+        Enrollments
+        LEFT JOIN StatusType ON Enrollments.statusTypeId = StatusType.id
+        LEFT JOIN RoleType ON Enrollments.roleTypeId = RoleType.id
+    GROUP BY
+        Enrollments.sourcedId
+    ORDER BY
+        Enrollments.sourcedId
+;
+
+-- TODO: update styling
+-- OR:5.8
+CREATE VIEW IF NOT EXISTS OrgsJson AS
+    SELECT json_object(
+        'sourcedId', Orgs.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Orgs.dateLastModified
+        , 'name', Orgs.name
+        , 'type', OrgType.token
+        , 'identifier', Orgs.identifier
+        , 'parent', CASE WHEN Orgs.parentSourcedId IS NOT NULL THEN
+            json_object(
+                'href', 'orgs/' || Orgs.parentSourcedId
+                , 'sourcedId', Orgs.parentSourcedId
+                , 'type', 'org'
+            ) ELSE NULL
+        END
+        , 'children', CASE WHEN OrgParent.sourcedId IS NOT NULL THEN
+            json_group_array(
+                json_object(
+                    'href', 'orgs/' || OrgParent.sourcedId
+                    , 'sourcedId', OrgParent.sourcedId
+                    , 'type', 'org'
+                )
+            ) ELSE NULL
+        END
+    ) AS 'org'
+    , Orgs.sourcedId AS sourcedId
+    FROM
+        Orgs
+        LEFT JOIN Orgs OrgParent ON Orgs.sourcedId = OrgParent.parentSourcedId
+        LEFT JOIN StatusType ON Orgs.statusTypeId = StatusType.id
+        LEFT JOIN OrgType ON Orgs.orgTypeId = OrgType.id
+    GROUP BY
+        Orgs.sourcedId
+    ORDER BY
+        Orgs.sourcedId
+;
+
+CREATE VIEW IF NOT EXISTS ClassesJson AS
+    SELECT json_object(
+        'sourcedId', Classes.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Classes.dateLastModified
+        , 'title', Classes.title
+        , 'classCode', Classes.classCode
+        , 'classType', ClassType.token
+        , 'locations', Classes.location
+        , 'grades', json(CG.grades)
+        , 'subjects', json(CS.title)
+        , 'course', json_object(
+            'href', 'courses/' || Classes.courseSourcedId
+            , 'sourcedId', Classes.courseSourcedId
+            , 'type', 'course'
+        )
+        , 'school', json_object(
+            'href', 'orgs/' || Classes.orgSourcedId
+            , 'sourcedId', Classes.orgSourcedId
+            , 'type', 'org'
+        )
+        , 'terms', json(CA.terms)
+        , 'subjectCodes', json(CS.code)
+        , 'periods', json(CP.period)
+        , 'resources', json(CR.resources)
+    ) AS 'class'
+    , Classes.sourcedId AS sourcedId
+    FROM
+        Classes
+        LEFT JOIN StatusType ON Classes.statusTypeId = StatusType.id
+        LEFT JOIN ClassType ON Classes.classTypeId = ClassType.id
+        LEFT JOIN (
+            SELECT
+                classSourcedId
+                , json_group_array(
+                    GradeType.token
+                ) AS grades
+            FROM ClassGrades
+            LEFT JOIN GradeType ON ClassGrades.gradeTypeId = GradeType.id
+            WHERE statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            GROUP BY classSourcedId
+        ) AS CG ON Classes.sourcedId = CG.classSourcedId
+        LEFT JOIN (
+            SELECT
+                classSourcedId
+                , Subjects.sourcedId -- needed to avoid proc macro bug?
+                , json_group_array(
+                    Subjects.title
+                ) AS title
+                , json_group_array(
+                    Subjects.subjectCode
+                ) AS code
+            FROM ClassSubjects
+            LEFT JOIN Subjects ON ClassSubjects.subjectSourcedId = Subjects.sourcedId
+            WHERE ClassSubjects.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            GROUP BY classSourcedId
+        ) AS CS ON Classes.sourcedId = CS.classSourcedId
+        LEFT JOIN (
+            SELECT
+                classSourcedId
+                , json_group_array(json_object(
+                        'href', 'academicSessions/' || ClassAcademicSessions.academicSessionSourcedId
+                        , 'sourcedId', ClassAcademicSessions.academicSessionSourcedId
+                        , 'type', 'academicSession'
+                )) AS terms
+            FROM ClassAcademicSessions
+            WHERE statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            GROUP BY classSourcedId
+        ) AS CA ON Classes.sourcedId = CA.classSourcedId
+        LEFT JOIN (
+            SELECT
+                classSourcedId
+                , Periods.sourcedId
+                , json_group_array(
+                    Periods.periodCode
+                ) AS period
+            FROM ClassPeriods
+            LEFT JOIN Periods ON ClassPeriods.periodSourcedId = Periods.sourcedId
+            WHERE ClassPeriods.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            GROUP BY classSourcedId
+        ) AS CP ON Classes.sourcedId = CP.classSourcedId
+        LEFT JOIN (
+            SELECT
+                classSourcedId
+                , json_group_array(json_object(
+                    'href', 'resources/' || ClassResources.resourceSourcedId
+                    , 'sourcedId', ClassResources.resourceSourcedId
+                    , 'type', 'resource'
+                )) AS resources
+            FROM ClassResources
+            WHERE statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            GROUP BY classSourcedId
+        ) AS CR ON Classes.sourcedId = CR.classSourcedId
+    GROUP BY
+        Classes.sourcedId
+    ORDER BY
+        Classes.sourcedId
+;
+
+-- OR 5.11
+CREATE VIEW IF NOT EXISTS UsersJson AS
+    SELECT json_object(
+        'sourcedId', Users.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Users.dateLastModified
+        , 'username', Users.username
+        , 'userIds', json(UI.userIds)
+        , 'enabledUser', Users.enabledUser
+        , 'givenName', Users.givenName
+        , 'familyName', Users.familyName
+        , 'middleName', Users.middleName
+        , 'role', RoleType.token
+        , 'identifier', Users.identifier
+        , 'email', Users.email
+        , 'sms', Users.sms
+        , 'phone', Users.phone
+        , 'agents', json(UA.agents)
+        , 'orgs', json(UO.orgs)
+        , 'grades', CASE WHEN UserGrades.userSourcedId IS NOT NULL THEN
+            json_group_array(GradeType.token)
+        ELSE NULL END
+        , 'password', Users.password
+    ) AS 'user'
+    , Users.sourcedId AS sourcedId
+    FROM
+        Users
+        LEFT JOIN StatusType ON Users.statusTypeId = StatusType.id
+        LEFT JOIN (
+            SELECT
+                userSourcedId
+                , json_group_array(json_object(
+                    'type', "type"
+                    , 'identifier', identifier
+                )) AS userIds
+            FROM UserIds
+            WHERE statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            GROUP BY userSourcedId
+        ) AS UI ON Users.sourcedId = UI.userSourcedId
+        LEFT JOIN RoleType ON Users.roleTypeId = RoleType.id
+        LEFT JOIN (
+            SELECT
+                userSourcedId
+                , json_group_array(json_object(
+                    'href', 'users/' || UserAgents.agentUserSourcedId
+                    , 'sourcedId', UserAgents.agentUserSourcedId
+                    , 'type', 'user'
+                )) AS agents
+            FROM UserAgents
+            WHERE statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            GROUP BY userSourcedId
+        ) AS UA ON Users.sourcedId = UA.userSourcedId
+        LEFT JOIN (
+            SELECT
+                userSourcedId
+                , json_group_array(json_object(
+                    'href', 'orgs/' || UserOrgs.orgSourcedId
+                    , 'sourcedId', UserOrgs.orgSourcedId
+                    , 'type', 'org'
+                )) AS orgs
+            FROM UserOrgs
+            WHERE statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            GROUP BY userSourcedId
+        ) AS UO ON Users.SourcedId = UO.userSourcedId
+        LEFT JOIN UserGrades ON Users.sourcedId = UserGrades.userSourcedId
+            AND UserGrades.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+        LEFT JOIN GradeType ON UserGrades.gradeTypeId = GradeType.id
+    GROUP BY
+        Users.sourcedId
+    ORDER BY
+        Users.sourcedId
+;
+
+-- OR 5.6
+CREATE VIEW IF NOT EXISTS LineItemsJson AS
+    SELECT json_object(
+        'sourcedId', LineItems.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', LineItems.dateLastModified
+        , 'title', LineItems.title
+        , 'description', LineItems.description
+        , 'assignDate', LineItems.assignDate
+        , 'dueDate', LineItems.dueDate
+        , 'class', json_object(
+            'href', 'classes/' || LineItems.classSourcedId
+            , 'sourcedId', LineItems.classSourcedId
+            , 'type', 'class'
+        )
+        , 'category', json_object(
+            'href', 'categories/' || LineItems.categorySourcedId
+            , 'sourcedId', LineItems.categorySourcedId
+            , 'type', 'category'
+        )
+        , 'gradingPeriod', json_object(
+            'href', 'academicSessions/' || LineItems.gradingPeriodSourcedId
+            , 'sourcedId', LineItems.gradingPeriodSourcedId
+            , 'type', 'academicSession'
+        )
+        , 'resultValueMin', LineItems.resultValueMin
+        , 'resultValueMax', LineItems.resultValueMax
+    ) AS 'lineItem'
+    , LineItems.sourcedId AS sourcedId
+    FROM
+        LineItems
+        LEFT JOIN StatusType ON LineItems.statusTypeId = StatusType.id
+    ORDER BY
+        LineItems.sourcedId
+;
+
+-- OR 5.7
+CREATE VIEW IF NOT EXISTS CategoriesJson AS
+    SELECT json_object(
+        'sourcedId', Categories.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Categories.dateLastModified
+        , 'title', Categories.title
+    ) AS 'category'
+    , Categories.sourcedId AS sourcedId
+    FROM
+        Categories
+        LEFT JOIN StatusType ON Categories.statusTypeId = StatusType.id
+    ORDER BY
+        Categories.sourcedId
+;
+
+-- OR 5.9
+CREATE VIEW IF NOT EXISTS ResourcesJson AS
+    SELECT json_object(
+        'sourcedId', Resources.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Resources.dateLastModified
+        , 'title', Resources.title
+        , 'roles', json(RR.roles)
+        , 'importance', ImportanceType.token
+        , 'vendorResourceId', Resources.vendorResourceId
+        , 'vendorId', Resources.vendorId
+        , 'applicationId', Resources.applicationId
+    ) AS 'resource'
+    , Resources.sourcedId AS sourcedId
+    FROM
+        Resources
+        LEFT JOIN StatusType ON Resources.statusTypeId = StatusType.id
+        LEFT JOIN ImportanceType ON Resources.importanceTypeId = ImportanceType.id
+        LEFT JOIN (
+            SELECT
+                resourceSourcedId
+                , json_group_array(
+                    RoleType.token
+                ) AS roles
+            FROM ResourceRoles
+            LEFT JOIN RoleType ON ResourceRoles.roleTypeId = RoleType.id
+            WHERE statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            GROUP BY resourceSourcedId
+        ) AS RR ON Resources.sourcedId = RR.resourceSourcedId
+    ORDER BY
+        Resources.sourcedId
+;
+
+-- OR 5.10
+CREATE VIEW IF NOT EXISTS ResultsJson AS
+    SELECT json_object(
+        'sourcedId', Results.sourcedId
+        , 'status', StatusType.token
+        , 'dateLastModified', Results.dateLastModified
+        , 'lineItem', json_object(
+            'href', 'lineItems/' || Results.lineItemSourcedId
+            , 'sourcedId', Results.lineItemSourcedId
+            , 'type', 'lineItem'
+        )
+        , 'student', json_object(
+            'href', 'users/' || Results.studentSourcedId
+            , 'sourcedId', Results.studentSourcedId
+            , 'type', 'user'
+        )
+        , 'scoreStatus', ScoreStatusType.token
+        , 'score', Results.score
+        , 'scoreDate', Results.scoreDate
+        , 'comment', Results.comment
+    ) AS 'result'
+    , Results.sourcedId AS sourcedId
+    FROM
+        Results
+        LEFT JOIN StatusType ON Results.statusTypeId = StatusType.id
+        LEFT JOIN ScoreStatusType ON Results.scoreStatusTypeId = ScoreStatusType.id
+    ORDER BY
+        Results.sourcedId
+;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertAcademicSessionsJson
+    INSTEAD OF INSERT ON AcademicSessionsJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO AcademicSessions (sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , startDate
+        , endDate
+        , sessionTypeId
+        , parentSourcedId
+        , schoolYear
+    )
+    VALUES (
+        json_extract(NEW.academicSession, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW.academicSession, '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW.academicSession, '$.dateLastModified'))
+        , json_extract(NEW.academicSession, '$.title')
+        , date(json_extract(NEW.academicSession, '$.startDate'))
+        , date(json_extract(NEW.academicSession, '$.endDate'))
+        , (SELECT id FROM SessionType WHERE token = json_extract(NEW.academicSession, '$.type'))
+        , json_extract(NEW.academicSession, '$.parent.sourcedId')
+        , json_extract(NEW.academicSession, '$.schoolYear')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , title=excluded.title
+        , startDate=excluded.startDate
+        , endDate=excluded.endDate
+        , sessionTypeId=excluded.sessionTypeId
+        , parentSourcedId=excluded.parentSourcedId
+        , schoolYear=excluded.schoolYear
+    ;
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertPeriodsJson
+    INSTEAD OF INSERT ON PeriodsJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO Periods (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , periodCode
+        , description
+    )
+    VALUES (
+        json_extract(NEW.period, '$.sourcedId')
+        , ( SELECT id FROM StatusType WHERE token = json_extract(NEW.period, '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW.period, '$.dateLastModified'))
+        , json_extract(NEW.period, '$.title')
+        , json_extract(NEW.period, '$.periodCode')
+        , json_extract(NEW.period, '$.description')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , title=excluded.title
+        , periodCode=excluded.periodCode
+        , description=excluded.description
+    ;
+
+    UPDATE OrgPeriods
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE periodSourcedId = json_extract(NEW.period, '$.sourcedId');
+
+    INSERT OR IGNORE INTO OrgPeriods(
+        periodSourcedId
+        , statusTypeId
+        , orgSourcedId
+    )
+    SELECT
+        json_extract(NEW.period, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , json_extract(org.value, '$.sourcedId')
+    FROM
+        json_each(NEW.period, '$.orgs') AS  org
+    WHERE true
+    ON CONFLICT (periodSourcedId, orgSourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertClassesJson
+    INSTEAD OF INSERT ON ClassesJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO Classes (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , classCode
+        , classTypeId
+        , location
+        , courseSourcedId
+        , orgSourcedId
+    )
+    VALUES (
+        json_extract(NEW.class, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW.class, '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW.class, '$.dateLastModified'))
+        , json_extract(NEW.class, '$.title')
+        , json_extract(NEW.class, '$.classCode')
+        , (SELECT id FROM classType WHERE token = json_extract(NEW.class, '$.classType'))
+        , json_extract(NEW.class, '$.location')
+        , json_extract(NEW.class, '$.course.sourcedId')
+        , json_extract(NEW.class, '$.school.sourcedId')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , title=excluded.title
+        , classCode=excluded.classCode
+        , classTypeId=excluded.classTypeId
+        , location=excluded.location
+        , courseSourcedId=excluded.courseSourcedId
+        , orgSourcedId=excluded.orgSourcedId
+    ;
+
+    UPDATE ClassGrades
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE classSourcedId = json_extract(NEW.class, '$.sourcedId');
+
+    INSERT OR IGNORE INTO ClassGrades(
+        classSourcedId
+        , statusTypeId
+        , gradeTypeId
+    )
+    SELECT
+        json_extract(NEW.class, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , (SELECT id FROM GradeType WHERE token = grades.value)
+    FROM
+        json_each(NEW.class, '$.grades') AS grades
+    WHERE true
+    ON CONFLICT (classSourcedId, gradeTypeId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+
+    UPDATE ClassSubjects
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE classSourcedId = json_extract(NEW.class, '$.sourcedId');
+
+    INSERT OR IGNORE INTO ClassSubjects(
+        classSourcedId
+        , statusTypeId
+        , subjectSourcedId
+    )
+    SELECT
+        json_extract(NEW.class, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , (SELECT sourcedId FROM Subjects WHERE subjectCode = sc.value)
+    FROM
+        json_each(NEW.class, '$.subjectCodes') AS sc
+    WHERE true
+    ON CONFLICT (classSourcedId, subjectSourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+
+    UPDATE ClassAcademicSessions
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE classSourcedId = json_extract(NEW.class, '$.sourcedId');
+
+    INSERT OR IGNORE INTO ClassAcademicSessions(
+        classSourcedId
+        , statusTypeId
+        , academicSessionSourcedId
+    )
+    SELECT
+        json_extract(NEW.class, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , json_extract(term.value, '$.sourcedId')
+    FROM
+        json_each(NEW.class, '$.terms') AS term
+    WHERE true
+    ON CONFLICT (classSourcedId, academicSessionSourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+
+    UPDATE ClassPeriods
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE classSourcedId = json_extract(NEW.class, '$.sourcedId');
+
+    INSERT INTO ClassPeriods(
+        classSourcedId
+        , statusTypeId
+        , periodSourcedId
+    )
+    SELECT
+        json_extract(NEW.class, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , (SELECT sourcedId FROM Periods WHERE periodCode = pc.value)
+    FROM
+        json_each(NEW.class, '$.periods') AS pc
+    WHERE true
+    ON CONFLICT (classSourcedId, periodSourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+
+    UPDATE ClassResources
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE classSourcedId = json_extract(NEW.class, '$.sourcedId');
+
+    INSERT OR IGNORE INTO ClassResources(
+        classSourcedId
+        , statusTypeId
+        , resourceSourcedId
+    )
+    SELECT
+        json_extract(NEW.class, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , json_extract(resource.value, '$.sourcedId')
+    FROM
+        json_each(NEW.class, '$.resources') AS resource
+    WHERE true
+    ON CONFLICT (classSourcedId, resourceSourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertOrgsJson
+    INSTEAD OF INSERT ON OrgsJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO Orgs (sourcedId
+    , statusTypeId
+    , dateLastModified
+    , name
+    , orgTypeId
+    , identifier
+    , parentSourcedId
+    )
+    VALUES (
+        json_extract(NEW.org, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW.org, '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW.org, '$.dateLastModified'))
+        , json_extract(NEW.org, '$.name')
+        , ( SELECT id FROM OrgType WHERE token = json_extract(NEW.org, '$.type') )
+        , json_extract(NEW.org, '$.identifier')
+        , json_extract(NEW.org, '$.parent.sourcedId')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , name=excluded.name
+        , orgTypeId=excluded.orgTypeId
+        , identifier=excluded.identifier
+        , parentSourcedId=excluded.parentSourcedId
+    ;
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertSubjectsJson
+    INSTEAD OF INSERT ON SubjectsJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO Subjects(
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , subjectCode
+    )
+    VALUES(
+        json_extract(NEW.subject, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW.subject, '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW.subject, '$.dateLastModified'))
+        , json_extract(NEW.subject, '$.title')
+        , json_extract(NEW.subject, '$.subjectCode')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , title=excluded.title
+        , subjectCode=excluded.subjectCode
+    ;
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertCoursesJson
+    INSTEAD OF INSERT ON CoursesJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO Courses(
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , schoolYearSourcedId
+        , courseCode
+        , orgSourcedId
+    )
+    VALUES(
+        json_extract(NEW.course, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW.course, '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW.course, '$.dateLastModified'))
+        , json_extract(NEW.course, '$.title')
+        , json_extract(NEW.course, '$.schoolYear.sourcedId')
+        , json_extract(NEW.course, '$.courseCode')
+        , json_extract(NEW.course, '$.org.sourcedId')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , title=excluded.title
+        , schoolYearSourcedId=excluded.schoolYearSourcedId
+        , courseCode=excluded.courseCode
+        , orgSourcedId=excluded.orgSourcedId
+    ;
+
+    UPDATE CourseGrades
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE courseSourcedId = json_extract(NEW.course, '$.sourcedId');
+
+    INSERT OR IGNORE INTO CourseGrades(
+        courseSourcedId
+        , statusTypeId
+        , gradeTypeId
+    )
+    SELECT
+        json_extract(NEW.course, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , (SELECT id FROM GradeType WHERE token = grades.value)
+    FROM
+        json_each(NEW.course, '$.grades') AS grades
+    WHERE true
+    ON CONFLICT (courseSourcedId, gradeTypeId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+
+    UPDATE CourseSubjects
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE courseSourcedId = json_extract(NEW.course, '$.sourcedId');
+
+    INSERT OR IGNORE INTO CourseSubjects(
+        courseSourcedId
+        , statusTypeId
+        , subjectSourcedId
+    )
+    SELECT
+        json_extract(NEW.course, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , (SELECT sourcedId FROM Subjects WHERE subjectCode = sc.value)
+    FROM
+        json_each(NEW.course, '$.subjectCodes') AS sc
+    WHERE true
+    ON CONFLICT (courseSourcedId, subjectSourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+
+    UPDATE CourseResources
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE courseSourcedId = json_extract(NEW.course, '$.sourcedId');
+
+    INSERT OR IGNORE INTO CourseResources(
+        courseSourcedId
+        , statusTypeId
+        , resourceSourcedId
+    )
+    SELECT
+        json_extract(NEW.course, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , json_extract(resource.value, '$.sourcedId')
+    FROM
+        json_each(NEW.course, '$.resources') AS resource
+    WHERE true
+    ON CONFLICT (courseSourcedId, resourceSourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertEnrollmentsJson
+    INSTEAD OF INSERT ON EnrollmentsJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO Enrollments (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , userSourcedId
+        , classSourcedId
+        , orgSourcedId
+        , roleTypeId
+        , "primary"
+        , beginDate
+        , endDate
+    )
+    VALUES (
+        json_extract(NEW.enrollment, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW.enrollment, '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW.enrollment, '$.dateLastModified'))
+        , json_extract(NEW.enrollment, '$.user.sourcedId')
+        , json_extract(NEW.enrollment, '$.class.sourcedId')
+        , json_extract(NEW.enrollment, '$.school.sourcedId')
+        , (SELECT id FROM RoleType WHERE token = json_extract(NEW.enrollment, '$.role'))
+        , json_extract(NEW.enrollment, '$.primary')
+        , date(json_extract(NEW.enrollment, '$.beginDate'))
+        , date(json_extract(NEW.enrollment, '$.endDate'))
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , userSourcedId=excluded.userSourcedId
+        , classSourcedId=excluded.classSourcedId
+        , orgSourcedId=excluded.orgSourcedId
+        , roleTypeId=excluded.roleTypeId
+        , "primary"=excluded."primary"
+        , beginDate=excluded.beginDate
+        , endDate=excluded.endDate
+    ;
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertUsersJson
+    INSTEAD OF INSERT ON UsersJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO Users (sourcedId
+        , statusTypeId
+        , dateLastModified
+        , username
+        , enabledUser
+        , givenName
+        , familyName
+        , middleName
+        , roleTypeId
+        , identifier
+        , email
+        , sms
+        , phone
+        , password
+    )
+    VALUES (
+        json_extract(NEW."user", '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW."user", '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW."user", '$.dateLastModified'))
+        , json_extract(NEW."user", '$.username')
+        , json_extract(NEW."user", '$.enabledUser')
+        , json_extract(NEW."user", '$.givenName')
+        , json_extract(NEW."user", '$.familyName')
+        , json_extract(NEW."user", '$.middleName')
+        , (SELECT id FROM RoleType WHERE token = json_extract(NEW."user", '$.role'))
+        , json_extract(NEW."user", '$.identifier')
+        , json_extract(NEW."user", '$.email')
+        , json_extract(NEW."user", '$.sms')
+        , json_extract(NEW."user", '$.phone')
+        , json_extract(NEW."user", '$.password')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , username=excluded.username
+        , enabledUser=excluded.enabledUser
+        , givenName=excluded.givenName
+        , familyName=excluded.familyName
+        , middleName=excluded.middleName
+        , roleTypeId=excluded.roleTypeId
+        , identifier=excluded.identifier
+        , email=excluded.email
+        , sms=excluded.sms
+        , phone=excluded.phone
+        , password=excluded.password
+    ;
+
+    -- Upserts UserIds table
+    UPDATE UserIds
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE userSourcedId = json_extract(NEW."user", '$.sourcedId');
+
+    INSERT OR IGNORE INTO UserIds (userSourcedId
+        , statusTypeId
+        , "type"
+        , identifier
+    )
+    SELECT
+        json_extract(NEW."user", '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , json_extract(userIds.value, '$.type')
+        , json_extract(userIds.value, '$.identifier')
+    FROM
+        json_each(NEW."user", '$.userIds') AS userIds
+    WHERE true
+    ON CONFLICT (userSourcedId, "type") DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , identifier=excluded.identifier
+    ;
+
+    -- Upserts UserOrgs table
+    UPDATE UserOrgs
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE userSourcedId = json_extract(NEW."user", '$.sourcedId');
+
+    INSERT OR IGNORE INTO UserOrgs(
+        userSourcedId
+        , statusTypeId
+        , orgSourcedId
+    )
+    SELECT
+        json_extract(NEW."user", '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , json_extract(orgs.value, '$.sourcedId')
+    FROM
+        json_each(NEW."user", '$.orgs') AS orgs
+    WHERE true
+    ON CONFLICT (userSourcedId, orgSourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+
+    /*
+
+       Upserts the UserAgents table
+
+       This first sets a users User/Agent links to the 'tobedeleted' status
+       then upserts the passed items to 'active' status.
+       This avoids the need for an explicit delete command and instead assumes any
+       entities not passed are now obsolete.
+       Sets users User/Agent links to 'tobedeleted' status
+
+    */
+    UPDATE UserAgents
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE userSourcedId = json_extract(NEW."user", '$.sourcedId');
+
+    INSERT OR IGNORE INTO UserAgents(
+        userSourcedId
+        , statusTypeId
+        , agentUserSourcedId
+    )
+    SELECT
+        json_extract(NEW."user", '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , json_extract(agents.value, '$.sourcedId')
+    FROM
+        json_each(NEW."user", '$.agents') AS agents
+    WHERE true
+    ON CONFLICT (userSourcedId, agentUserSourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+
+    -- Upserts UserGrades table
+    UPDATE UserGrades
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE userSourcedId = json_extract(NEW."user", '$.sourcedId');
+
+    INSERT OR IGNORE INTO UserGrades(
+        userSourcedId
+        , statusTypeId
+        , gradeTypeId
+    )
+    SELECT
+        json_extract(NEW."user", '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , (SELECT id FROM GradeType WHERE token = grades.value)
+    FROM
+        json_each(NEW."user", '$.grades') AS grades
+    WHERE true
+    ON CONFLICT (userSourcedId, gradeTypeId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertLineItemsJson
+    INSTEAD OF INSERT ON LineItemsJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO LineItems (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , description
+        , assignDate
+        , dueDate
+        , classSourcedId
+        , categorySourcedId
+        , gradingPeriodSourcedId
+        , resultValueMin
+        , resultValueMax
+    )
+    VALUES (
+        json_extract(NEW.lineItem, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW.lineItem, '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW.lineItem, '$.dateLastModified'))
+        , json_extract(NEW.lineItem, '$.title')
+        , json_extract(NEW.lineItem, '$.description')
+        , date(json_extract(NEW.lineItem, '$.assignDate'))
+        , date(json_extract(NEW.lineItem, '$.dueDate'))
+        , json_extract(NEW.lineItem, '$.class.sourcedId')
+        , json_extract(NEW.lineItem, '$.category.sourcedId')
+        , json_extract(NEW.lineItem, '$.gradingPeriod.sourcedId')
+        , json_extract(NEW.lineItem, '$.resultValueMin')
+        , json_extract(NEW.lineItem, '$.resultValueMax')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , title=excluded.title
+        , description=excluded.description
+        , assignDate=excluded.assignDate
+        , dueDate=excluded.dueDate
+        , classSourcedId=excluded.classSourcedId
+        , categorySourcedId=excluded.categorySourcedId
+        , gradingPeriodSourcedId=excluded.gradingPeriodSourcedId
+        , resultValueMin=excluded.resultValueMin
+        , resultValueMax=excluded.resultValueMax
+    ;
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertCategoriesJson
+    INSTEAD OF INSERT ON CategoriesJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO Categories (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+    )
+    VALUES (
+        json_extract(NEW.category, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW.category, '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW.category, '$.dateLastModified'))
+        , json_extract(NEW.category, '$.title')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , title=excluded.title
+    ;
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertResultsJson
+    INSTEAD OF INSERT ON ResultsJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO Results (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , lineItemSourcedId
+        , studentSourcedId
+        , scoreStatusTypeId
+        , score
+        , scoreDate
+        , comment
+    )
+    VALUES (
+        json_extract(NEW."result", '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW."result", '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW."result", '$.dateLastModified'))
+        , json_extract(NEW."result", '$.lineItem.sourcedId')
+        , json_extract(NEW."result", '$.student.sourcedId')
+        , (SELECT id FROM ScoreStatusType WHERE token = json_extract(NEW."result", '$.scoreStatus'))
+        , json_extract(NEW."result", '$.score')
+        , date(json_extract(NEW."result", '$.scoreDate'))
+        , json_extract(NEW."result", '$.comment')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , lineItemSourcedId=excluded.lineItemSourcedId
+        , studentSourcedId=excluded.studentSourcedId
+        , scoreStatusTypeId=excluded.scoreStatusTypeId
+        , score=excluded.score
+        , scoreDate=excluded.scoreDate
+        , comment=excluded.comment
+    ;
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertResourcesJson
+    INSTEAD OF INSERT ON ResourcesJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO Resources (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , title
+        , importanceTypeId
+        , vendorResourceId
+        , vendorId
+        , applicationId
+    )
+    VALUES (
+        json_extract(NEW.resource, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW.resource, '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW.resource, '$.dateLastModified'))
+        , json_extract(NEW.resource, '$.title')
+        , (SELECT id FROM ImportanceType WHERE token = json_extract(NEW.resource, '$.importance'))
+        , json_extract(NEW.resource, '$.vendorResourceId')
+        , json_extract(NEW.resource, '$.vendorId')
+        , json_extract(NEW.resource, '$.applicationId')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , title=excluded.title
+        , importanceTypeId=excluded.importanceTypeId
+        , vendorResourceId=excluded.vendorResourceId
+        , vendorId=excluded.vendorId
+        , applicationId=excluded.applicationId
+    ;
+
+    UPDATE ResourceRoles
+    SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+    WHERE resourceSourcedId = json_extract(NEW.resource, '$.sourcedId');
+
+    INSERT OR IGNORE INTO ResourceRoles(
+        resourceSourcedId
+        , statusTypeId
+        , roleTypeId
+    )
+    SELECT
+        json_extract(NEW.resource, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = 'active')
+        , (SELECT id FROM RoleType WHERE token = roles.value)
+    FROM
+        json_each(NEW.resource, '$.roles') AS roles
+    WHERE true
+    ON CONFLICT (resourceSourcedId, roleTypeId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+    ;
+END;
+
+CREATE TRIGGER IF NOT EXISTS TriggerUpsertDemographicsJson
+    INSTEAD OF INSERT ON DemographicsJson
+    FOR EACH ROW
+BEGIN
+    INSERT INTO Demographics (
+        sourcedId
+        , statusTypeId
+        , dateLastModified
+        , birthDate
+        , genderTypeId
+        , americanIndianOrAlaskaNative
+        , asian
+        , blackOrAfricanAmerican
+        , nativeHawaiianOrOtherPacificIslander
+        , white
+        , demographicRaceTwoOrMoreRaces
+        , hispanicOrLatinoEthnicity
+        , countryOfBirthCode
+        , stateOfBirthAbbreviation
+        , cityOfBirth
+        , publicSchoolResidenceStatus
+    )
+    VALUES (
+        json_extract(NEW.demographics, '$.sourcedId')
+        , (SELECT id FROM StatusType WHERE token = json_extract(NEW.demographics, '$.status'))
+        , strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(NEW.demographics, '$.dateLastModified'))
+        , date(json_extract(NEW.demographics, '$.birthDate'))
+        , (SELECT id FROM GenderType WHERE token = json_extract(NEW.demographics, '$.sex'))
+        , json_extract(NEW.demographics, '$.americanIndianOrAlaskaNative')
+        , json_extract(NEW.demographics, '$.asian')
+        , json_extract(NEW.demographics, '$.blackOrAfricanAmerican')
+        , json_extract(NEW.demographics, '$.nativeHawaiianOrOtherPacificIslander')
+        , json_extract(NEW.demographics, '$.white')
+        , json_extract(NEW.demographics, '$.demographicRaceTwoOrMoreRaces')
+        , json_extract(NEW.demographics, '$.hispanicOrLatinoEthnicity')
+        , json_extract(NEW.demographics, '$.countryOfBirthCode')
+        , json_extract(NEW.demographics, '$.stateOfBirthAbbreviation')
+        , json_extract(NEW.demographics, '$.cityOfBirth')
+        , json_extract(NEW.demographics, '$.publicSchoolResidenceStatus')
+    )
+    ON CONFLICT (sourcedId) DO UPDATE SET
+        statusTypeId=excluded.statusTypeId
+        , dateLastModified=excluded.dateLastModified
+        , birthDate=excluded.birthDate
+        , genderTypeId=excluded.genderTypeId
+        , americanIndianOrAlaskaNative=excluded.americanIndianOrAlaskaNative
+        , asian=excluded.asian
+        , blackOrAfricanAmerican=excluded.blackOrAfricanAmerican
+        , nativeHawaiianOrOtherPacificIslander=excluded.nativeHawaiianOrOtherPacificIslander
+        , white=excluded.white
+        , demographicRaceTwoOrMoreRaces=excluded.demographicRaceTwoOrMoreRaces
+        , hispanicOrLatinoEthnicity=excluded.hispanicOrLatinoEthnicity
+        , countryOfBirthCode=excluded.countryOfBirthCode
+        , stateOfBirthAbbreviation=excluded.stateOfBirthAbbreviation
+        , cityOfBirth=excluded.cityOfBirth
+        , publicSchoolResidenceStatus=excluded.publicSchoolResidenceStatus
+    ;
+END;
+
+INSERT
+    OR IGNORE INTO scopes (scope) VALUES
+    ('roster-core.readonly')
+    , ('roster-core.createput')
+    , ('roster-core.delete')
+    , ('admin.readonly')
+    , ('admin.delete')
+    , ('gradebook.readonly')
+    , ('gradebook.createput')
+    , ('gradebook.delete')
+    , ('resource.readonly')
+    , ('resource.createput')
+    , ('resource.delete')
+    , ('roster-demographics.readonly')
+    , ('roster-demographics.createput')
+    , ('roster-demographics.delete')
+    ;
+
+INSERT
+    OR IGNORE INTO StatusType (token) VALUES
+    ('active')
+    , ('tobedeleted')
+    , ('inactive')
+    ;
+
+INSERT
+    OR IGNORE INTO OrgType (token) VALUES
+    ('department')
+    , ('school')
+    , ('district')
+    , ('local')
+    , ('state')
+    , ('national')
+    ;
+
+INSERT
+    OR IGNORE INTO ClassType (token) VALUES
+    ('homeroom')
+    , ('scheduled');
+
+INSERT
+    OR IGNORE INTO RoleType (token) VALUES
+    ('administrator')
+    , ('aide')
+    , ('guardian')
+    , ('parent')
+    , ('proctor')
+    , ('relative')
+    , ('student')
+    , ('teacher')
+    ;
+
+INSERT
+    OR IGNORE INTO SessionType (token) VALUES
+    ('gradingPeriod')
+    , ('semester')
+    , ('schoolYear')
+    , ('term')
+    ;
+
+INSERT
+    OR IGNORE INTO GenderType (token) VALUES
+    ('male')
+    , ('female')
+    ;
+
+INSERT
+    OR IGNORE INTO ImportanceType (token) VALUES
+    ('primary')
+    , ('secondary')
+    ;
+
+INSERT
+    OR IGNORE INTO ScoreStatusType (token) VALUES
+    ('exempt')
+    , ('fully graded')
+    , ('not submitted')
+    , ('partially graded')
+    , ('submitted')
+    ;
+
+INSERT
+    OR IGNORE INTO GradeType (token, description) VALUES
+    ('IT', 'Infant/toddler')
+    , ('PR', 'Preschool')
+    , ('PK', 'Prekindergarten')
+    , ('TK', 'Transitional Kindergarten')
+    , ('KG', 'Kindergarten')
+    , ('01', 'First grade')
+    , ('02', 'Second grade')
+    , ('03', 'Third grade')
+    , ('04', 'Fourth grade')
+    , ('05', 'Fifth grade')
+    , ('06', 'Sixth grade')
+    , ('07', 'Seventh grade')
+    , ('08', 'Eigth grade')
+    , ('09', 'Ninth grade')
+    , ('10', 'Tenth grade')
+    , ('11', 'Eleventh grade')
+    , ('12', 'Twelfth grade')
+    , ('13', 'Grade 13')
+    , ('PS', 'Postsecondary')
+    , ('UG', 'Ungraded')
+    , ('Other', 'Other')
+    ;
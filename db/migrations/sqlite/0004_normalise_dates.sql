@@ -0,0 +1,17 @@
+-- Dates written before they were normalised on the way in are kept in the UTC format they
+-- are now stored in, so they compare and sort with those written since. A value which is
+-- not a date is left as it is
+UPDATE AcademicSessions SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Periods SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Subjects SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Classes SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Courses SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Orgs SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Users SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Enrollments SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Demographics SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE LineItems SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Categories SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Results SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Resources SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
+UPDATE Tombstones SET dateLastModified = coalesce(strftime('%Y-%m-%dT%H:%M:%fZ', dateLastModified), dateLastModified);
@@ -26,15 +26,12 @@
             jq
             openssl.dev
             sqlite
-            oniguruma
             pkg-config
           ];
-          JQ_LIB_DIR = "${pkgs.jq}";
           DATABASE_URL = "sqlite:db/oneroster.db";
           shellHook = ''
             if [ ! -f db/oneroster.db ]; then
-              sqlite3 db/oneroster.db < db/schema.sql
-              sqlite3 db/oneroster.db < db/init.sql
+              cat db/migrations/sqlite/*.sql | sqlite3 db/oneroster.db
             fi
           '';
 
@@ -50,16 +47,10 @@
             pkgs.openssl.dev
             pkgs.sqlite
           ];
-          buildInputs = [
-            pkgs.jq
-            pkgs.oniguruma
-          ];
           preConfigure = ''
-            sqlite3 db/oneroster.db < db/schema.sql
-            sqlite3 db/oneroster.db < db/init.sql
+            cat db/migrations/sqlite/*.sql | sqlite3 db/oneroster.db
           '';
           doCheck = false;
-          JQ_LIB_DIR = "${pkgs.jq}";
           DATABASE_URL = "sqlite:db/oneroster.db";
           PKG_CONFIG_PATH = "${pkgs.openssl.dev}/lib/pkgconfig";
         };
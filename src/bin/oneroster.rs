@@ -1,5 +1,4 @@
 use async_std::task;
-use clap;
 use libre_oneroster::server::ServerError;
 use libre_oneroster::{client, server};
 
@@ -7,10 +6,17 @@ fn main() {
     env_logger::init();
     match cli() {
         Ok(_) => (),
-        Err(err) => match err {
-            ServerError::Io(ref e) => log::error!("File error: {}", e),
-            _ => println!("placeholder: {}", err),
-        },
+        Err(err) => {
+            match err {
+                ServerError::Io(ref e) => log::error!("File error: {}", e),
+                ServerError::Sqlx(_)
+                | ServerError::Migrate(_)
+                | ServerError::NewerSchema(_)
+                | ServerError::NoDatabaseFound => log::error!("Database error: {}", err),
+                _ => println!("placeholder: {}", err),
+            }
+            std::process::exit(1);
+        }
     }
 }
 
@@ -94,7 +100,7 @@ fn cli() -> Result<(), ServerError> {
                         .long("scope")
                         .env("OR_SCOPE")
                         .value_name("STRING")
-                        .default_value("roster-core.createput roster.createput"),
+                        .default_value("roster-core.createput roster.createput roster-demographics.createput"),
                 )
                 .arg(
                     clap::Arg::new("delta")
@@ -121,6 +127,24 @@ fn cli() -> Result<(), ServerError> {
                         .required(true),
                 ),
         )
+        .subcommand(
+            clap::Command::new("db")
+                .about("Manages the server database")
+                .subcommand_required(true)
+                .subcommand(
+                    clap::Command::new("migrate")
+                        .about("Applies pending schema migrations")
+                        .arg(
+                            clap::Arg::new("database")
+                                .help("Path to the database file, or a postgres:// URL")
+                                .short('d')
+                                .long("database")
+                                .env("OR_DB")
+                                .value_name("PATH")
+                                .default_value("oneroster.db"),
+                        ),
+                ),
+        )
         .subcommand(
             clap::Command::new("server")
                 .about("Starts the oneroster server")
@@ -143,7 +167,7 @@ fn cli() -> Result<(), ServerError> {
                 )
                 .arg(
                     clap::Arg::new("database")
-                        .help("Path to the database file")
+                        .help("Path to the database file, or a postgres:// URL")
                         .short('d')
                         .long("database")
                         .env("OR_DB")
@@ -185,6 +209,56 @@ fn cli() -> Result<(), ServerError> {
                         .env("OR_HTTPS_CRT")
                         .value_name("PATH")
                         .required(true),
+                )
+                .arg(
+                    clap::Arg::new("external_url")
+                        .help("public base url used in pagination links when behind a reverse proxy")
+                        .long("external-url")
+                        .env("OR_EXTERNAL_URL")
+                        .value_name("URL"),
+                )
+                .arg(
+                    clap::Arg::new("trust_proxy")
+                        .help("use the X-Forwarded-* headers of a trusted reverse proxy in pagination links when no external url is set")
+                        .long("trust-proxy")
+                        .env("OR_TRUST_PROXY")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    clap::Arg::new("max_body_size")
+                        .help("largest request body accepted in MiB, after decompression")
+                        .long("max-body-size")
+                        .env("OR_MAX_BODY_SIZE")
+                        .value_name("MIB")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("512"),
+                )
+                .arg(
+                    clap::Arg::new("max_limit")
+                        .help("largest page size a client may request with the limit parameter")
+                        .long("max-limit")
+                        .env("OR_MAX_LIMIT")
+                        .value_name("OBJECTS")
+                        .value_parser(clap::value_parser!(u32).range(1..))
+                        .default_value("10000"),
+                )
+                .arg(
+                    clap::Arg::new("db_max_connections")
+                        .help("largest number of connections held to the database, for postgres each server counts towards its max_connections")
+                        .long("db-max-connections")
+                        .env("OR_DB_MAX_CONNECTIONS")
+                        .value_name("CONNECTIONS")
+                        .value_parser(clap::value_parser!(u32).range(1..))
+                        .default_value("8"),
+                )
+                .arg(
+                    clap::Arg::new("db_acquire_timeout")
+                        .help("seconds a request waits for a free database connection before failing")
+                        .long("db-acquire-timeout")
+                        .env("OR_DB_ACQUIRE_TIMEOUT")
+                        .value_name("SECONDS")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("30"),
                 ),
         )
         .get_matches();
@@ -202,15 +276,9 @@ fn cli() -> Result<(), ServerError> {
         }
         Some(("server", args)) => {
             let encode_key = server::read_private_key(args.get_one::<String>("private_key").unwrap())
-                .map_err(|e| {
-                    log::error!("Problem reading private key");
-                    e
-                })?;
+                .inspect_err(|_| log::error!("Problem reading private key"))?;
             let decode_key = server::read_public_key(args.get_one::<String>("public_key").unwrap())
-                .map_err(|e| {
-                    log::error!("Problem reading public key");
-                    e
-                })?;
+                .inspect_err(|_| log::error!("Problem reading public key"))?;
             let c = server::Config {
                 database: args.get_one::<String>("database").unwrap().to_string(),
                 init: args.get_flag("init"),
@@ -219,10 +287,23 @@ fn cli() -> Result<(), ServerError> {
                 decode_key,
                 web_public_key: args.get_one::<String>("web_public_key").unwrap().to_string(),
                 web_private_key: args.get_one::<String>("web_private_key").unwrap().to_string(),
+                external_url: args.get_one::<String>("external_url").cloned(),
+                trust_proxy: args.get_flag("trust_proxy"),
+                max_body_size: args.get_one::<u64>("max_body_size").unwrap() * 1024 * 1024,
+                max_limit: *args.get_one::<u32>("max_limit").unwrap(),
+                db_max_connections: *args.get_one::<u32>("db_max_connections").unwrap(),
+                db_acquire_timeout: std::time::Duration::from_secs(
+                    *args.get_one::<u64>("db_acquire_timeout").unwrap(),
+                ),
             };
-            task::block_on(server::run(c)).unwrap();
-            Ok(())
+            task::block_on(server::run(c))
         }
+        Some(("db", args)) => match args.subcommand() {
+            Some(("migrate", args)) => {
+                task::block_on(server::migrate(args.get_one::<String>("database").unwrap()))
+            }
+            _ => Ok(()),
+        },
         Some(("sync", args)) => {
             let or = crate::client::Config {
                 url: args.get_one::<String>("api").unwrap().to_string(),
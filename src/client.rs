@@ -60,17 +60,26 @@ async fn get_all_academic_sessions(c: &surf::Client, token: &String) -> surf::Re
 
 pub async fn put_all<T>(
     c: &surf::Client,
-    token: &String,
+    token: &str,
     data: T,
     endpoint: &str,
 ) -> surf::Result<()>
 where
     for<'a> T: serde::Serialize,
 {
-    c.put("ims/oneroster/v1p1/".to_owned() + endpoint)
+    let mut r = c
+        .put("ims/oneroster/v1p1/".to_owned() + endpoint)
         .body(serde_json::json!(data).to_string())
         .header("Authorization", "Bearer ".to_owned() + token)
         .await?;
+    // a rejected upload, such as a token missing the endpoint's createput scope, must not
+    // look like a successful sync
+    if !r.status().is_success() {
+        let body = r.body_string().await.unwrap_or_default();
+        let message = format!("PUT {} failed with {}: {}", endpoint, r.status(), body);
+        log::error!("{}", message);
+        return Err(surf::Error::from_str(r.status(), message));
+    }
     Ok(())
 }
 
@@ -80,8 +80,7 @@ async fn connect_database(connection_string: &str) -> Client<TcpStream> {
     let creds = tiberius::Config::from_ado_string(connection_string).unwrap();
     log::debug!("SQL server connection info: {:?}", creds);
     let tcp = TcpStream::connect_named(&creds).await.unwrap();
-    let client = Client::connect(creds, tcp).await.unwrap();
-    return client;
+    Client::connect(creds, tcp).await.unwrap()
 }
 
 struct SyncConf {
@@ -106,7 +105,7 @@ where
         .await?;
     for row in rows {
         if let Some(data) = row.try_get::<&str, _>(endpoint)? {
-            let out: T = serde_json::from_str(&data)?;
+            let out: T = serde_json::from_str(data)?;
             client::put_all(&config.oneroster, &config.token, out, endpoint).await?;
         }
     }
@@ -170,6 +169,8 @@ pub async fn sync(config: Config) -> Result<()> {
             sync3::<model::Courses>(&mut sync_conf, "courses", isams::QUERY_COURSES).await?;
             sync3::<model::Classes>(&mut sync_conf, "classes", isams::QUERY_CLASSES).await?;
             sync3::<model::Users>(&mut sync_conf, "users", isams::QUERY_USERS).await?;
+            sync3::<model::Demographics>(&mut sync_conf, "demographics", isams::QUERY_DEMOGRAPHICS)
+                .await?;
             sync3::<model::Enrollments>(&mut sync_conf, "enrollments", isams::QUERY_ENROLLMENTS)
                 .await?;
             Ok(())
@@ -187,6 +188,12 @@ pub async fn sync(config: Config) -> Result<()> {
             sync2::<model::Courses>(&mut sync_conf, "courses", wcbs_pass::QUERY_COURSES).await?;
             sync2::<model::Classes>(&mut sync_conf, "classes", wcbs_pass::QUERY_CLASSES).await?;
             sync2::<model::Users>(&mut sync_conf, "users", wcbs_pass::QUERY_USERS).await?;
+            sync2::<model::Demographics>(
+                &mut sync_conf,
+                "demographics",
+                wcbs_pass::QUERY_DEMOGRAPHICS,
+            )
+            .await?;
             sync2::<model::Enrollments>(
                 &mut sync_conf,
                 "enrollments",
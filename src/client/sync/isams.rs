@@ -373,6 +373,27 @@ SELECT cast((
 )
 "#;
 
+// iSAMS holds no US race/ethnicity flags so only birth details are synced
+pub static QUERY_DEMOGRAPHICS: &str = r#"
+SELECT cast((
+    SELECT
+        cast(pupils.txtSchoolId AS varchar(36)) AS sourcedId
+        , CASE WHEN intSystemStatus = -1 THEN 'tobedeleted' ELSE 'active' END AS status
+        , cast(pupils.txtSubmitDateTime AS datetimeoffset) AS dateLastModified
+        , cast(pupils.txtDOB AS date) AS birthDate
+        , CASE pupils.txtGender WHEN 'M' THEN 'male' WHEN 'F' THEN 'female' ELSE NULL END AS sex
+    -- the same pupils as the users query, so each demographics record has a user
+    FROM TblPupilManagementPupils pupils
+		INNER JOIN TblSchoolManagementYears years ON years.intNCYear = pupils.intNCYear
+    WHERE pupils.txtSubmitDateTime > @p1
+        AND ( intSystemStatus = 1 OR intSystemStatus = 0 )
+        AND pupils.txtPreName <> ''
+    ORDER BY sourcedId
+    FOR JSON PATH, WITHOUT_ARRAY_WRAPPER --, root('demographics')
+    ) AS nvarchar(max)
+) AS demographics
+"#;
+
 pub static QUERY_ENROLLMENTS: &str = r#"
 -- scheduled pupil
 SELECT cast((
@@ -367,6 +367,26 @@ SELECT cast((
 )
 "#;
 
+pub(super) static QUERY_DEMOGRAPHICS: &str = r#"
+-- name: select-demographics-pupil
+SELECT cast((
+    SELECT
+        cast(pupil.name_id AS varchar(36)) AS sourcedId
+        , CASE WHEN pupil.in_use = 'Y' THEN 'active' ELSE 'tobedeleted' END AS status
+        , cast(pupil.last_amend_date AS datetimeoffset) AS dateLastModified
+        , cast(name.dob AS date) AS birthDate
+        , CASE name.gender WHEN 'M' THEN 'male' WHEN 'F' THEN 'female' ELSE NULL END AS sex
+    FROM dbo.pupil
+        INNER JOIN dbo.name ON pupil.name_id = name.name_id
+    WHERE pupil.last_amend_date > @p1
+        AND pupil.academic_year = @p2
+        AND pupil.record_type = 1
+    ORDER BY sourcedId
+    FOR JSON PATH, root('demographics')
+    ) AS nvarchar(max)
+) AS 'demographics'
+"#;
+
 //TODO: add delta to enrollments
 pub(super) static QUERY_ENROLLMENTS: &str = r#"
 -- name: select-enrollments-scheduled-pupil
@@ -1,7 +1,10 @@
 use chrono::{DateTime, NaiveDate, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
+pub mod v1p2;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct TokenReturn {
     pub access_token: String,
@@ -14,7 +17,7 @@ pub(crate) struct TokenReturn {
 // in the spec output, they are for the purposes
 // of ingest. Their required state is enforced by
 // the accompanying sql query
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GUIDRef {
     pub href: Option<String>,
@@ -24,7 +27,7 @@ pub struct GUIDRef {
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AcademicSession {
     pub sourced_id: String,
@@ -41,7 +44,7 @@ pub struct AcademicSession {
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Period {
     pub sourced_id: String,
@@ -54,7 +57,7 @@ pub struct Period {
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Subject {
     pub sourced_id: String,
@@ -65,7 +68,7 @@ pub struct Subject {
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Class {
     pub sourced_id: String,
@@ -86,7 +89,7 @@ pub struct Class {
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Course {
     pub sourced_id: String,
@@ -99,9 +102,34 @@ pub struct Course {
     pub subjects: Option<Vec<String>>,
     pub org: GUIDRef,
     pub subject_codes: Option<Vec<String>>,
+    pub resources: Option<Vec<GUIDRef>>,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+// the race and ethnicity flags are bound as "true"/"false"
+// strings in the 1.1 json binding rather than booleans
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Demographic {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub birth_date: Option<NaiveDate>,
+    pub sex: Option<GenderType>,
+    pub american_indian_or_alaska_native: Option<String>,
+    pub asian: Option<String>,
+    pub black_or_african_american: Option<String>,
+    pub native_hawaiian_or_other_pacific_islander: Option<String>,
+    pub white: Option<String>,
+    pub demographic_race_two_or_more_races: Option<String>,
+    pub hispanic_or_latino_ethnicity: Option<String>,
+    pub country_of_birth_code: Option<String>,
+    pub state_of_birth_abbreviation: Option<String>,
+    pub city_of_birth: Option<String>,
+    pub public_school_residence_status: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Enrollment {
     pub sourced_id: String,
@@ -116,7 +144,7 @@ pub struct Enrollment {
     pub end_date: Option<NaiveDate>,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Org {
     pub sourced_id: String,
@@ -131,7 +159,7 @@ pub struct Org {
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct User {
     pub sourced_id: String,
@@ -149,12 +177,14 @@ pub struct User {
     pub sms: Option<String>,
     pub phone: Option<String>,
     pub agents: Option<Vec<GUIDRef>>,
+    // at least one org is required by the spec, and for the roles of a 1.2 user
+    #[schemars(required, length(min = 1))]
     pub orgs: Option<Vec<GUIDRef>>,
     pub grades: Option<Vec<String>>,
     pub password: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UserId {
     #[serde(rename = "type")]
@@ -162,7 +192,65 @@ pub struct UserId {
     pub identifier: String,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineItem {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub title: String,
+    pub description: Option<String>,
+    pub assign_date: NaiveDate,
+    pub due_date: NaiveDate,
+    pub class: GUIDRef,
+    pub category: GUIDRef,
+    pub grading_period: GUIDRef,
+    pub result_value_min: f64,
+    pub result_value_max: f64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Category {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub title: String,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub title: Option<String>,
+    pub roles: Option<Vec<RoleType>>,
+    pub importance: Option<ImportanceType>,
+    pub vendor_resource_id: String,
+    pub vendor_id: Option<String>,
+    pub application_id: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "Result")]
+pub struct GradebookResult {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub line_item: GUIDRef,
+    pub student: GUIDRef,
+    pub score_status: ScoreStatusType,
+    pub score: f64,
+    pub score_date: NaiveDate,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 // required due to writing to db as
 // UpperCamelCase, causing serialization errors
@@ -170,9 +258,10 @@ pub struct UserId {
 pub enum StatusType {
     active,
     tobedeleted,
+    inactive,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[allow(non_camel_case_types)]
 pub enum OrgType {
     department,
@@ -183,7 +272,7 @@ pub enum OrgType {
     national,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[allow(non_camel_case_types)]
 pub enum SessionType {
     gradingPeriod,
@@ -192,7 +281,7 @@ pub enum SessionType {
     term,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 #[allow(non_camel_case_types)]
 pub enum RoleType {
     administrator,
@@ -205,7 +294,7 @@ pub enum RoleType {
     teacher,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 #[allow(non_camel_case_types)]
 pub enum GUIDType {
     academicSession,
@@ -225,93 +314,194 @@ pub enum GUIDType {
     user,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[allow(non_camel_case_types)]
 pub enum ClassType {
     homeroom,
     scheduled,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[allow(non_camel_case_types)]
+pub enum GenderType {
+    male,
+    female,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[allow(non_camel_case_types)]
+pub enum ImportanceType {
+    primary,
+    secondary,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[allow(non_camel_case_types)]
+pub enum ScoreStatusType {
+    exempt,
+    #[serde(rename = "fully graded")]
+    fullyGraded,
+    #[serde(rename = "not submitted")]
+    notSubmitted,
+    #[serde(rename = "partially graded")]
+    partiallyGraded,
+    submitted,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Classes {
     pub classes: Vec<Class>,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ClassSingle {
     pub class: Class,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AcademicSessions {
     pub academic_sessions: Vec<AcademicSession>,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AcademicSessionSingle {
     pub academic_session: AcademicSession,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Periods {
     pub periods: Vec<Period>,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodSingle {
+    pub period: Period,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Orgs {
     pub orgs: Vec<Org>,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OrgSingle {
     pub org: Org,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Users {
     pub users: Vec<User>,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UserSingle {
     pub user: User,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Subjects {
     pub subjects: Vec<Subject>,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubjectSingle {
+    pub subject: Subject,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Courses {
     pub courses: Vec<Course>,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CourseSingle {
     pub course: Course,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Enrollments {
     pub enrollments: Vec<Enrollment>,
 }
 
-#[derive(Debug, Deserialize, Serialize, sqlx::Type)]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EnrollmentSingle {
     pub enrollment: Enrollment,
 }
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineItems {
+    pub line_items: Vec<LineItem>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineItemSingle {
+    pub line_item: LineItem,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Categories {
+    pub categories: Vec<Category>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategorySingle {
+    pub category: Category,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "Results")]
+pub struct GradebookResults {
+    pub results: Vec<GradebookResult>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "ResultSingle")]
+pub struct GradebookResultSingle {
+    pub result: GradebookResult,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resources {
+    pub resources: Vec<Resource>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceSingle {
+    pub resource: Resource,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Demographics {
+    pub demographics: Vec<Demographic>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DemographicSingle {
+    pub demographics: Demographic,
+}
@@ -0,0 +1,912 @@
+//! OneRoster 1.2 rostering models
+//!
+//! The database stores and emits the 1.1 json binding, these types are built from
+//! the 1.1 models to serve the same data under the 1.2 rostering service, and are
+//! converted back into them when written through it. 1.2 data which has no 1.1
+//! equivalent, such as a counselor role, is rejected rather than changed
+use crate::model::{self, ClassType, GUIDRef, OrgType, SessionType};
+use chrono::{DateTime, NaiveDate, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcademicSession {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub metadata: Option<serde_json::Value>,
+    pub title: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    #[serde(rename = "type")]
+    pub academic_session_type: SessionType,
+    pub parent: Option<GUIDRef>,
+    pub children: Option<Vec<GUIDRef>>,
+    pub school_year: String,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Class {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub metadata: Option<serde_json::Value>,
+    pub title: String,
+    pub class_code: Option<String>,
+    pub class_type: ClassType,
+    pub location: Option<String>,
+    pub grades: Option<Vec<String>>,
+    pub subjects: Option<Vec<String>>,
+    pub course: GUIDRef,
+    pub school: GUIDRef,
+    pub terms: Vec<GUIDRef>,
+    pub subject_codes: Option<Vec<String>>,
+    pub periods: Option<Vec<String>>,
+    pub resources: Option<Vec<GUIDRef>>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Course {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub metadata: Option<serde_json::Value>,
+    pub title: String,
+    pub school_year: Option<GUIDRef>,
+    pub course_code: Option<String>,
+    pub grades: Option<Vec<String>>,
+    pub subjects: Option<Vec<String>>,
+    pub org: GUIDRef,
+    pub subject_codes: Option<Vec<String>>,
+    pub resources: Option<Vec<GUIDRef>>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Demographic {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub metadata: Option<serde_json::Value>,
+    pub birth_date: Option<NaiveDate>,
+    pub sex: Option<GenderType>,
+    pub american_indian_or_alaska_native: Option<String>,
+    pub asian: Option<String>,
+    pub black_or_african_american: Option<String>,
+    pub native_hawaiian_or_other_pacific_islander: Option<String>,
+    pub white: Option<String>,
+    pub demographic_race_two_or_more_races: Option<String>,
+    pub hispanic_or_latino_ethnicity: Option<String>,
+    pub country_of_birth_code: Option<String>,
+    pub state_of_birth_abbreviation: Option<String>,
+    pub city_of_birth: Option<String>,
+    pub public_school_residence_status: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Enrollment {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub metadata: Option<serde_json::Value>,
+    pub user: GUIDRef,
+    pub class: GUIDRef,
+    pub school: GUIDRef,
+    pub role: RoleType,
+    pub primary: Option<String>,
+    pub begin_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Org {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub metadata: Option<serde_json::Value>,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub org_type: OrgType,
+    pub identifier: Option<String>,
+    pub parent: Option<GUIDRef>,
+    pub children: Option<Vec<GUIDRef>>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub sourced_id: String,
+    pub status: StatusType,
+    pub date_last_modified: DateTime<Utc>,
+    pub metadata: Option<serde_json::Value>,
+    pub user_master_identifier: Option<String>,
+    pub username: String,
+    pub user_ids: Option<Vec<model::UserId>>,
+    pub enabled_user: String,
+    pub given_name: String,
+    pub family_name: String,
+    pub middle_name: Option<String>,
+    pub preferred_first_name: Option<String>,
+    pub preferred_middle_name: Option<String>,
+    pub preferred_last_name: Option<String>,
+    pub pronouns: Option<String>,
+    #[schemars(length(min = 1))]
+    pub roles: Vec<Role>,
+    pub user_profiles: Option<Vec<UserProfile>>,
+    pub primary_org: Option<GUIDRef>,
+    pub identifier: Option<String>,
+    pub email: Option<String>,
+    pub sms: Option<String>,
+    pub phone: Option<String>,
+    pub agents: Option<Vec<GUIDRef>>,
+    pub grades: Option<Vec<String>>,
+    pub password: Option<String>,
+    pub resources: Option<Vec<GUIDRef>>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Role {
+    pub role_type: RoleAssignmentType,
+    pub role: RoleType,
+    pub org: GUIDRef,
+    pub user_profile: Option<String>,
+    pub begin_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserProfile {
+    pub profile_id: String,
+    pub profile_type: String,
+    pub vendor_id: String,
+    pub application_id: Option<String>,
+    pub description: Option<String>,
+    pub credentials: Option<Vec<Credential>>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Credential {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub username: String,
+    pub password: Option<String>,
+}
+
+// 1.2 drops the deprecated inactive status
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[allow(non_camel_case_types)]
+pub enum StatusType {
+    active,
+    tobedeleted,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[allow(non_camel_case_types)]
+pub enum RoleType {
+    aide,
+    counselor,
+    districtAdministrator,
+    guardian,
+    parent,
+    principal,
+    proctor,
+    relative,
+    siteAdministrator,
+    student,
+    systemAdministrator,
+    teacher,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[allow(non_camel_case_types)]
+pub enum RoleAssignmentType {
+    primary,
+    secondary,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Serialize)]
+#[allow(non_camel_case_types)]
+pub enum GenderType {
+    male,
+    female,
+    other,
+    unspecified,
+}
+
+// an inactive object is still current, it is active in 1.2 and marked by its metadata
+impl From<model::StatusType> for StatusType {
+    fn from(status: model::StatusType) -> Self {
+        match status {
+            model::StatusType::active | model::StatusType::inactive => StatusType::active,
+            model::StatusType::tobedeleted => StatusType::tobedeleted,
+        }
+    }
+}
+
+/// the metadata of a 1.2 object, which records a 1.1 inactive status
+fn metadata(status: &model::StatusType) -> Option<serde_json::Value> {
+    match status {
+        model::StatusType::inactive => Some(serde_json::json!({ "inactive": true })),
+        _ => None,
+    }
+}
+
+// 1.1 has a single administrator role, 1.2 splits it by org level
+impl From<model::RoleType> for RoleType {
+    fn from(role: model::RoleType) -> Self {
+        match role {
+            model::RoleType::administrator => RoleType::siteAdministrator,
+            model::RoleType::aide => RoleType::aide,
+            model::RoleType::guardian => RoleType::guardian,
+            model::RoleType::parent => RoleType::parent,
+            model::RoleType::proctor => RoleType::proctor,
+            model::RoleType::relative => RoleType::relative,
+            model::RoleType::student => RoleType::student,
+            model::RoleType::teacher => RoleType::teacher,
+        }
+    }
+}
+
+impl From<model::GenderType> for GenderType {
+    fn from(sex: model::GenderType) -> Self {
+        match sex {
+            model::GenderType::male => GenderType::male,
+            model::GenderType::female => GenderType::female,
+        }
+    }
+}
+
+impl From<model::AcademicSession> for AcademicSession {
+    fn from(a: model::AcademicSession) -> Self {
+        Self {
+            sourced_id: a.sourced_id,
+            metadata: metadata(&a.status),
+            status: a.status.into(),
+            date_last_modified: a.date_last_modified,
+            title: a.title,
+            start_date: a.start_date,
+            end_date: a.end_date,
+            academic_session_type: a.academic_session_type,
+            parent: a.parent,
+            children: a.children,
+            school_year: a.school_year,
+        }
+    }
+}
+
+impl From<model::Class> for Class {
+    fn from(c: model::Class) -> Self {
+        Self {
+            sourced_id: c.sourced_id,
+            metadata: metadata(&c.status),
+            status: c.status.into(),
+            date_last_modified: c.date_last_modified,
+            title: c.title,
+            class_code: c.class_code,
+            class_type: c.class_type,
+            location: c.location,
+            grades: c.grades,
+            subjects: c.subjects,
+            course: c.course,
+            school: c.school,
+            terms: c.terms,
+            subject_codes: c.subject_codes,
+            periods: c.periods,
+            resources: c.resources,
+        }
+    }
+}
+
+impl From<model::Course> for Course {
+    fn from(c: model::Course) -> Self {
+        Self {
+            sourced_id: c.sourced_id,
+            metadata: metadata(&c.status),
+            status: c.status.into(),
+            date_last_modified: c.date_last_modified,
+            title: c.title,
+            school_year: c.school_year,
+            course_code: c.course_code,
+            grades: c.grades,
+            subjects: c.subjects,
+            org: c.org,
+            subject_codes: c.subject_codes,
+            resources: c.resources,
+        }
+    }
+}
+
+impl From<model::Demographic> for Demographic {
+    fn from(d: model::Demographic) -> Self {
+        Self {
+            sourced_id: d.sourced_id,
+            metadata: metadata(&d.status),
+            status: d.status.into(),
+            date_last_modified: d.date_last_modified,
+            birth_date: d.birth_date,
+            sex: d.sex.map(GenderType::from),
+            american_indian_or_alaska_native: d.american_indian_or_alaska_native,
+            asian: d.asian,
+            black_or_african_american: d.black_or_african_american,
+            native_hawaiian_or_other_pacific_islander: d.native_hawaiian_or_other_pacific_islander,
+            white: d.white,
+            demographic_race_two_or_more_races: d.demographic_race_two_or_more_races,
+            hispanic_or_latino_ethnicity: d.hispanic_or_latino_ethnicity,
+            country_of_birth_code: d.country_of_birth_code,
+            state_of_birth_abbreviation: d.state_of_birth_abbreviation,
+            city_of_birth: d.city_of_birth,
+            public_school_residence_status: d.public_school_residence_status,
+        }
+    }
+}
+
+impl From<model::Enrollment> for Enrollment {
+    fn from(e: model::Enrollment) -> Self {
+        Self {
+            sourced_id: e.sourced_id,
+            metadata: metadata(&e.status),
+            status: e.status.into(),
+            date_last_modified: e.date_last_modified,
+            user: e.user,
+            class: e.class,
+            school: e.school,
+            role: e.role.into(),
+            primary: e.primary.map(|p| (p != 0).to_string()),
+            begin_date: e.begin_date,
+            end_date: e.end_date,
+        }
+    }
+}
+
+impl From<model::Org> for Org {
+    fn from(o: model::Org) -> Self {
+        Self {
+            sourced_id: o.sourced_id,
+            metadata: metadata(&o.status),
+            status: o.status.into(),
+            date_last_modified: o.date_last_modified,
+            name: o.name,
+            org_type: o.org_type,
+            identifier: o.identifier,
+            parent: o.parent,
+            children: o.children,
+        }
+    }
+}
+
+// 1.1 users hold a single role across all their orgs, this is expanded into a
+// role per org with the first org treated as the primary. Users are written with at
+// least one org so there is always a role
+impl From<model::User> for User {
+    fn from(u: model::User) -> Self {
+        let orgs = u.orgs.unwrap_or_default();
+        let primary_org = orgs.first().cloned();
+        let roles = orgs
+            .into_iter()
+            .enumerate()
+            .map(|(i, org)| Role {
+                role_type: match i {
+                    0 => RoleAssignmentType::primary,
+                    _ => RoleAssignmentType::secondary,
+                },
+                role: u.role.clone().into(),
+                org,
+                user_profile: None,
+                begin_date: None,
+                end_date: None,
+            })
+            .collect();
+        Self {
+            sourced_id: u.sourced_id,
+            metadata: metadata(&u.status),
+            status: u.status.into(),
+            date_last_modified: u.date_last_modified,
+            user_master_identifier: None,
+            username: u.username,
+            user_ids: u.user_ids,
+            enabled_user: (u.enabled_user != 0).to_string(),
+            given_name: u.given_name,
+            family_name: u.family_name,
+            middle_name: u.middle_name,
+            preferred_first_name: None,
+            preferred_middle_name: None,
+            preferred_last_name: None,
+            pronouns: None,
+            roles,
+            user_profiles: None,
+            primary_org,
+            identifier: u.identifier,
+            email: u.email,
+            sms: u.sms,
+            phone: u.phone,
+            agents: u.agents,
+            grades: u.grades,
+            password: u.password,
+            resources: None,
+        }
+    }
+}
+
+/// the 1.1 status of a 1.2 object, inactive when marked by its metadata
+fn status(status: StatusType, metadata: &Option<serde_json::Value>) -> model::StatusType {
+    let inactive = metadata
+        .as_ref()
+        .and_then(|m| m.get("inactive"))
+        .and_then(|i| i.as_bool())
+        .unwrap_or(false);
+    match status {
+        StatusType::tobedeleted => model::StatusType::tobedeleted,
+        StatusType::active if inactive => model::StatusType::inactive,
+        StatusType::active => model::StatusType::active,
+    }
+}
+
+/// reads a 1.2 "true"/"false" string into the 1.1 binding
+fn flag(field: &str, value: &str) -> Result<i8, String> {
+    match value {
+        "true" => Ok(1),
+        "false" => Ok(0),
+        _ => Err(format!("{} must be \"true\" or \"false\"", field)),
+    }
+}
+
+// the 1.2 administrator roles are all the single 1.1 administrator
+impl TryFrom<RoleType> for model::RoleType {
+    type Error = String;
+
+    fn try_from(role: RoleType) -> Result<Self, Self::Error> {
+        match role {
+            RoleType::districtAdministrator
+            | RoleType::principal
+            | RoleType::siteAdministrator
+            | RoleType::systemAdministrator => Ok(model::RoleType::administrator),
+            RoleType::aide => Ok(model::RoleType::aide),
+            RoleType::guardian => Ok(model::RoleType::guardian),
+            RoleType::parent => Ok(model::RoleType::parent),
+            RoleType::proctor => Ok(model::RoleType::proctor),
+            RoleType::relative => Ok(model::RoleType::relative),
+            RoleType::student => Ok(model::RoleType::student),
+            RoleType::teacher => Ok(model::RoleType::teacher),
+            RoleType::counselor => {
+                Err("role counselor has no OneRoster 1.1 equivalent".to_string())
+            }
+        }
+    }
+}
+
+impl TryFrom<GenderType> for model::GenderType {
+    type Error = String;
+
+    fn try_from(sex: GenderType) -> Result<Self, Self::Error> {
+        match sex {
+            GenderType::male => Ok(model::GenderType::male),
+            GenderType::female => Ok(model::GenderType::female),
+            GenderType::other | GenderType::unspecified => {
+                Err(format!("sex {:?} has no OneRoster 1.1 equivalent", sex))
+            }
+        }
+    }
+}
+
+impl TryFrom<AcademicSession> for model::AcademicSession {
+    type Error = String;
+
+    fn try_from(a: AcademicSession) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sourced_id: a.sourced_id,
+            status: status(a.status, &a.metadata),
+            date_last_modified: a.date_last_modified,
+            title: a.title,
+            start_date: a.start_date,
+            end_date: a.end_date,
+            academic_session_type: a.academic_session_type,
+            parent: a.parent,
+            children: a.children,
+            school_year: a.school_year,
+        })
+    }
+}
+
+impl TryFrom<Class> for model::Class {
+    type Error = String;
+
+    fn try_from(c: Class) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sourced_id: c.sourced_id,
+            status: status(c.status, &c.metadata),
+            date_last_modified: c.date_last_modified,
+            title: c.title,
+            class_code: c.class_code,
+            class_type: c.class_type,
+            location: c.location,
+            grades: c.grades,
+            subjects: c.subjects,
+            course: c.course,
+            school: c.school,
+            terms: c.terms,
+            subject_codes: c.subject_codes,
+            periods: c.periods,
+            resources: c.resources,
+        })
+    }
+}
+
+impl TryFrom<Course> for model::Course {
+    type Error = String;
+
+    fn try_from(c: Course) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sourced_id: c.sourced_id,
+            status: status(c.status, &c.metadata),
+            date_last_modified: c.date_last_modified,
+            title: c.title,
+            school_year: c.school_year,
+            course_code: c.course_code,
+            grades: c.grades,
+            subjects: c.subjects,
+            org: c.org,
+            subject_codes: c.subject_codes,
+            resources: c.resources,
+        })
+    }
+}
+
+impl TryFrom<Demographic> for model::Demographic {
+    type Error = String;
+
+    fn try_from(d: Demographic) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sourced_id: d.sourced_id,
+            status: status(d.status, &d.metadata),
+            date_last_modified: d.date_last_modified,
+            birth_date: d.birth_date,
+            sex: d.sex.map(model::GenderType::try_from).transpose()?,
+            american_indian_or_alaska_native: d.american_indian_or_alaska_native,
+            asian: d.asian,
+            black_or_african_american: d.black_or_african_american,
+            native_hawaiian_or_other_pacific_islander: d.native_hawaiian_or_other_pacific_islander,
+            white: d.white,
+            demographic_race_two_or_more_races: d.demographic_race_two_or_more_races,
+            hispanic_or_latino_ethnicity: d.hispanic_or_latino_ethnicity,
+            country_of_birth_code: d.country_of_birth_code,
+            state_of_birth_abbreviation: d.state_of_birth_abbreviation,
+            city_of_birth: d.city_of_birth,
+            public_school_residence_status: d.public_school_residence_status,
+        })
+    }
+}
+
+impl TryFrom<Enrollment> for model::Enrollment {
+    type Error = String;
+
+    fn try_from(e: Enrollment) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sourced_id: e.sourced_id,
+            status: status(e.status, &e.metadata),
+            date_last_modified: e.date_last_modified,
+            user: e.user,
+            class: e.class,
+            school: e.school,
+            role: e.role.try_into()?,
+            primary: e.primary.map(|p| flag("primary", &p)).transpose()?,
+            begin_date: e.begin_date,
+            end_date: e.end_date,
+        })
+    }
+}
+
+impl TryFrom<Org> for model::Org {
+    type Error = String;
+
+    fn try_from(o: Org) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sourced_id: o.sourced_id,
+            status: status(o.status, &o.metadata),
+            date_last_modified: o.date_last_modified,
+            name: o.name,
+            org_type: o.org_type,
+            identifier: o.identifier,
+            parent: o.parent,
+            children: o.children,
+        })
+    }
+}
+
+// 1.1 holds a single role for all the orgs of a user, so every role must be the same.
+// The orgs are the primary org, then that of the primary role and each other role, so
+// the primary org is first when read back. The 1.2 only fields e.g. pronouns have
+// nowhere to be stored so must not be set
+impl TryFrom<User> for model::User {
+    type Error = String;
+
+    fn try_from(u: User) -> Result<Self, Self::Error> {
+        let mut roles = u.roles;
+        if roles.is_empty() {
+            return Err("roles must have at least one role".to_string());
+        }
+        if roles.iter().any(|r| r.role != roles[0].role) {
+            return Err("roles must all have the same role".to_string());
+        }
+        let unstored = [
+            ("userMasterIdentifier", u.user_master_identifier.is_some()),
+            ("preferredFirstName", u.preferred_first_name.is_some()),
+            ("preferredMiddleName", u.preferred_middle_name.is_some()),
+            ("preferredLastName", u.preferred_last_name.is_some()),
+            ("pronouns", u.pronouns.is_some()),
+            ("userProfiles", u.user_profiles.is_some()),
+            ("resources", u.resources.is_some()),
+            (
+                "roles.userProfile",
+                roles.iter().any(|r| r.user_profile.is_some()),
+            ),
+            (
+                "roles.beginDate",
+                roles.iter().any(|r| r.begin_date.is_some()),
+            ),
+            ("roles.endDate", roles.iter().any(|r| r.end_date.is_some())),
+        ];
+        if let Some((field, _)) = unstored.iter().find(|(_, set)| *set) {
+            return Err(format!("{} cannot be stored and must not be set", field));
+        }
+        let primary = roles
+            .iter()
+            .position(|r| matches!(r.role_type, RoleAssignmentType::primary))
+            .unwrap_or(0);
+        let mut orgs: Vec<GUIDRef> = u.primary_org.into_iter().collect();
+        for role in std::iter::once(&roles[primary]).chain(&roles) {
+            if !orgs.iter().any(|o| o.sourced_id == role.org.sourced_id) {
+                orgs.push(role.org.clone());
+            }
+        }
+        Ok(Self {
+            sourced_id: u.sourced_id,
+            status: status(u.status, &u.metadata),
+            date_last_modified: u.date_last_modified,
+            username: u.username,
+            user_ids: u.user_ids,
+            enabled_user: flag("enabledUser", &u.enabled_user)?,
+            given_name: u.given_name,
+            family_name: u.family_name,
+            middle_name: u.middle_name,
+            role: roles.swap_remove(primary).role.try_into()?,
+            identifier: u.identifier,
+            email: u.email,
+            sms: u.sms,
+            phone: u.phone,
+            agents: u.agents,
+            orgs: Some(orgs),
+            grades: u.grades,
+            password: u.password,
+        })
+    }
+}
+
+/// Creates the collection and single object wrappers for a 1.2 object, converting
+/// from the matching 1.1 wrappers and a single object back into its 1.1 wrapper
+macro_rules! create_wrappers {
+    ($collection:ident, $single:ident, $data:ident, $objects:ident, $object:ident) => {
+        #[derive(Debug, Deserialize, JsonSchema, Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct $collection {
+            pub $objects: Vec<$data>,
+        }
+
+        #[derive(Debug, Deserialize, JsonSchema, Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct $single {
+            pub $object: $data,
+        }
+
+        impl From<model::$collection> for $collection {
+            fn from(data: model::$collection) -> Self {
+                Self {
+                    $objects: data.$objects.into_iter().map($data::from).collect(),
+                }
+            }
+        }
+
+        impl From<model::$single> for $single {
+            fn from(data: model::$single) -> Self {
+                Self {
+                    $object: data.$object.into(),
+                }
+            }
+        }
+
+        impl TryFrom<$single> for model::$single {
+            type Error = String;
+
+            fn try_from(data: $single) -> Result<Self, Self::Error> {
+                Ok(Self {
+                    $object: data.$object.try_into()?,
+                })
+            }
+        }
+    };
+}
+
+create_wrappers!(
+    AcademicSessions,
+    AcademicSessionSingle,
+    AcademicSession,
+    academic_sessions,
+    academic_session
+);
+create_wrappers!(Classes, ClassSingle, Class, classes, class);
+create_wrappers!(Courses, CourseSingle, Course, courses, course);
+create_wrappers!(
+    Demographics,
+    DemographicSingle,
+    Demographic,
+    demographics,
+    demographics
+);
+create_wrappers!(
+    Enrollments,
+    EnrollmentSingle,
+    Enrollment,
+    enrollments,
+    enrollment
+);
+create_wrappers!(Orgs, OrgSingle, Org, orgs, org);
+create_wrappers!(Users, UserSingle, User, users, user);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_users() -> Vec<model::User> {
+        let content = std::fs::read_to_string("./sample/users.json").unwrap();
+        serde_json::from_str::<model::Users>(&content)
+            .unwrap()
+            .users
+    }
+
+    #[test]
+    fn inactive_is_active_with_metadata() {
+        let mut user = sample_users().remove(0);
+        user.status = model::StatusType::inactive;
+        let user = serde_json::to_value(User::from(user)).unwrap();
+        assert_eq!(user["status"], "active");
+        assert_eq!(user["metadata"]["inactive"], true);
+        assert_eq!(user["enabledUser"], "true");
+
+        let mut user = sample_users().remove(0);
+        user.status = model::StatusType::tobedeleted;
+        let user = serde_json::to_value(User::from(user)).unwrap();
+        assert_eq!(user["status"], "tobedeleted");
+        assert!(user.get("metadata").is_none());
+    }
+
+    #[test]
+    fn a_role_for_each_org() {
+        for user in sample_users() {
+            let orgs = user.orgs.clone().unwrap();
+            let user = User::from(user);
+            assert_eq!(user.roles.len(), orgs.len());
+            assert!(matches!(
+                user.roles[0].role_type,
+                RoleAssignmentType::primary
+            ));
+            assert_eq!(user.roles[0].org.sourced_id, orgs[0].sourced_id);
+            assert_eq!(user.primary_org.unwrap().sourced_id, orgs[0].sourced_id);
+        }
+    }
+
+    /// converts each 1.1 sample object into 1.2 and back
+    fn round_trip<T, U>(file: &str, wrapper: &str)
+    where
+        T: serde::de::DeserializeOwned + Serialize,
+        U: From<T> + TryInto<T, Error = String>,
+    {
+        let content = std::fs::read_to_string(format!("./sample/{}.json", file)).unwrap();
+        let objects: serde_json::Value = serde_json::from_str(&content).unwrap();
+        for object in objects[wrapper].as_array().unwrap() {
+            let v1p1: T = serde_json::from_value(object.clone()).unwrap();
+            let expected = serde_json::to_value(&v1p1).unwrap();
+            let back: T = U::from(v1p1).try_into().unwrap();
+            assert_eq!(serde_json::to_value(back).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn converts_back_into_the_same_object() {
+        round_trip::<model::AcademicSession, AcademicSession>(
+            "academicSessions",
+            "academicSessions",
+        );
+        round_trip::<model::Class, Class>("classes", "classes");
+        round_trip::<model::Course, Course>("courses", "courses");
+        round_trip::<model::Enrollment, Enrollment>("enrollments", "enrollments");
+        round_trip::<model::Org, Org>("orgs", "orgs");
+        round_trip::<model::User, User>("users", "users");
+
+        // an inactive user keeps whether it is enabled
+        for enabled_user in [0, 1] {
+            let mut user = sample_users().remove(0);
+            user.status = model::StatusType::inactive;
+            user.enabled_user = enabled_user;
+            let expected = serde_json::to_value(&user).unwrap();
+            let back: model::User = User::from(user).try_into().unwrap();
+            assert_eq!(serde_json::to_value(back).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn rejects_what_1p1_cannot_hold() {
+        let mut user = User::from(sample_users().remove(0));
+        user.roles[0].role = RoleType::counselor;
+        let reason = model::User::try_from(user).unwrap_err();
+        assert!(reason.contains("counselor"));
+
+        let mut user = User::from(sample_users().remove(0));
+        user.roles.clear();
+        assert!(model::User::try_from(user).is_err());
+
+        let mut user = User::from(sample_users().remove(0));
+        user.enabled_user = "yes".to_string();
+        assert!(model::User::try_from(user).is_err());
+
+        // a role per org which 1.1 would hold as one role
+        let mut user = User::from(sample_users().remove(0));
+        user.roles.push(other_role(&user, RoleType::principal));
+        let reason = model::User::try_from(user).unwrap_err();
+        assert_eq!(reason, "roles must all have the same role");
+
+        let mut user = User::from(sample_users().remove(0));
+        user.pronouns = Some("they/them".to_string());
+        let reason = model::User::try_from(user).unwrap_err();
+        assert_eq!(reason, "pronouns cannot be stored and must not be set");
+
+        let mut user = User::from(sample_users().remove(0));
+        user.roles[0].begin_date = NaiveDate::from_ymd_opt(2021, 1, 1);
+        let reason = model::User::try_from(user).unwrap_err();
+        assert!(reason.starts_with("roles.beginDate"));
+    }
+
+    /// a secondary role of the same user at another org
+    fn other_role(user: &User, role: RoleType) -> Role {
+        Role {
+            role_type: RoleAssignmentType::secondary,
+            role,
+            org: GUIDRef {
+                sourced_id: "other".to_string(),
+                ..user.roles[0].org.clone()
+            },
+            user_profile: None,
+            begin_date: None,
+            end_date: None,
+        }
+    }
+
+    #[test]
+    fn the_org_of_the_primary_role_is_first() {
+        let mut user = User::from(sample_users().remove(0));
+        let mut role = other_role(&user, user.roles[0].role.clone());
+        role.role_type = RoleAssignmentType::primary;
+        user.roles[0].role_type = RoleAssignmentType::secondary;
+        user.roles.push(role);
+        user.primary_org = None;
+        let user = model::User::try_from(user).unwrap();
+        assert_eq!(user.orgs.unwrap()[0].sourced_id, "other");
+    }
+}
@@ -1,11 +1,18 @@
 mod auth;
+mod body;
+mod compression;
+mod conditional;
 mod db;
 pub mod errors;
 mod params;
+mod schema;
+mod stream;
+mod v1p2;
 
-use async_std::prelude::*;
+use crate::model;
 pub use errors::*;
 use http_types::mime;
+use std::convert;
 use std::io::prelude::*;
 use tide::prelude::*;
 use tide::utils::After;
@@ -14,56 +21,51 @@ use tide_rustls::TlsListener;
 
 type Result<T> = std::result::Result<T, ServerError>;
 
+const V1P1: &str = "/ims/oneroster/v1p1";
+const V1P2: &str = "/ims/oneroster/rostering/v1p2";
+
 #[derive(Clone)]
 pub(crate) struct State {
-    db: sqlx::SqlitePool,
+    db: db::Database,
     encode_key: jsonwebtoken::EncodingKey,
     decode_key: jsonwebtoken::DecodingKey,
+    external_url: Option<String>,
+    trust_proxy: bool,
+    max_body_size: u64,
+    max_limit: u32,
 }
 
 /// Creates a GET endpoint function
 /// $name takes the name of the function to generate as well as the matching DB req function
-/// $object takes the name of the top level json object within the collection { "myObject": [{}] }
-/// $wrapper takes the name of the top level json object as a string for JQ to use in querying
+/// $wrapper takes the name of the top level json object within the collection { "myObject": [{}] }
 macro_rules! create_get_endpoint {
-    ($name:ident, $object:ident, $wrapper:literal) => {
+    ($name:ident, $wrapper:literal) => {
         async fn $name(req: Request<State>) -> tide::Result {
-            let params = req.query()?;
-            let data = db::$name(&req.state().db).await?;
-            let links = params::link_header_builder(&req, &params, data.$object.len()).await;
-            let (output, total) =
-                params::apply_parameters(&json!(data).to_string(), &params, $wrapper).await?;
-            Ok(tide::Response::builder(200)
-                .header("link", links)
-                .header("x-total-count", total.trim())
-                .content_type(mime::JSON)
-                .body(output)
-                .build())
+            let params = params::query(&req)?;
+            let collection = db::$name(&req.state().db, &params).await?;
+            stream::respond(&req, &params, collection, $wrapper, V1P1, convert::identity).await
         }
     };
 }
 
-create_get_endpoint!(get_all_classes, classes, "classes");
-create_get_endpoint!(
-    get_all_academic_sessions,
-    academic_sessions,
-    "academicSessions"
-);
-create_get_endpoint!(get_all_periods, periods, "periods");
-create_get_endpoint!(get_all_orgs, orgs, "orgs");
-create_get_endpoint!(get_all_users, users, "users");
-create_get_endpoint!(get_all_subjects, subjects, "subjects");
-create_get_endpoint!(get_all_courses, courses, "courses");
-create_get_endpoint!(get_all_enrollments, enrollments, "enrollments");
-create_get_endpoint!(
-    get_all_grading_periods,
-    academic_sessions,
-    "academicSessions"
-);
-create_get_endpoint!(get_all_schools, orgs, "orgs");
-create_get_endpoint!(get_all_students, users, "users");
-create_get_endpoint!(get_all_teachers, users, "users");
-create_get_endpoint!(get_all_terms, academic_sessions, "academicSessions");
+create_get_endpoint!(get_all_classes, "classes");
+create_get_endpoint!(get_all_academic_sessions, "academicSessions");
+create_get_endpoint!(get_all_periods, "periods");
+create_get_endpoint!(get_all_orgs, "orgs");
+create_get_endpoint!(get_all_users, "users");
+create_get_endpoint!(get_all_subjects, "subjects");
+create_get_endpoint!(get_all_courses, "courses");
+create_get_endpoint!(get_all_enrollments, "enrollments");
+create_get_endpoint!(get_all_grading_periods, "academicSessions");
+create_get_endpoint!(get_all_schools, "orgs");
+create_get_endpoint!(get_all_students, "users");
+create_get_endpoint!(get_all_teachers, "users");
+create_get_endpoint!(get_all_terms, "academicSessions");
+create_get_endpoint!(get_all_line_items, "lineItems");
+create_get_endpoint!(get_all_categories, "categories");
+create_get_endpoint!(get_all_results, "results");
+create_get_endpoint!(get_all_resources, "resources");
+create_get_endpoint!(get_all_demographics, "demographics");
 
 macro_rules! create_get_endpoint_by_id {
     ($name:ident) => {
@@ -84,55 +86,177 @@ create_get_endpoint_by_id!(get_course);
 create_get_endpoint_by_id!(get_grading_period);
 create_get_endpoint_by_id!(get_enrollment);
 create_get_endpoint_by_id!(get_org);
+create_get_endpoint_by_id!(get_period);
 create_get_endpoint_by_id!(get_school);
 create_get_endpoint_by_id!(get_student);
+create_get_endpoint_by_id!(get_subject);
 create_get_endpoint_by_id!(get_teacher);
 create_get_endpoint_by_id!(get_term);
 create_get_endpoint_by_id!(get_user);
+create_get_endpoint_by_id!(get_line_item);
+create_get_endpoint_by_id!(get_category);
+create_get_endpoint_by_id!(get_result);
+create_get_endpoint_by_id!(get_resource);
+create_get_endpoint_by_id!(get_demographics);
 
 macro_rules! create_get_collection_endpoint_by_id {
-    ($name:ident, $object:ident, $wrapper:literal) => {
+    ($name:ident, $wrapper:literal) => {
         async fn $name(req: Request<State>) -> tide::Result {
             let id = req.param("id")?;
-            let params = req.query()?;
-            let data = db::$name(&req.state().db, &id).await?;
-            let links = params::link_header_builder(&req, &params, data.$object.len()).await;
-            let (output, total) =
-                params::apply_parameters(&json!(data).to_string(), &params, $wrapper).await?;
-            Ok(tide::Response::builder(200)
-                .header("link", links)
-                .header("x-total-count", total.trim())
-                .content_type(mime::JSON)
-                .body(output)
-                .build())
+            let params = params::query(&req)?;
+            let collection = db::$name(&req.state().db, id, &params).await?;
+            stream::respond(&req, &params, collection, $wrapper, V1P1, convert::identity).await
         }
     };
 }
 
-create_get_collection_endpoint_by_id!(get_classes_for_school, classes, "classes");
-create_get_collection_endpoint_by_id!(get_students_for_school, users, "users");
-create_get_collection_endpoint_by_id!(get_teachers_for_school, users, "users");
-create_get_collection_endpoint_by_id!(get_enrollments_for_school, enrollments, "enrollments");
+create_get_collection_endpoint_by_id!(get_classes_for_school, "classes");
+create_get_collection_endpoint_by_id!(get_students_for_school, "users");
+create_get_collection_endpoint_by_id!(get_teachers_for_school, "users");
+create_get_collection_endpoint_by_id!(get_enrollments_for_school, "enrollments");
+create_get_collection_endpoint_by_id!(get_students_for_class, "users");
+create_get_collection_endpoint_by_id!(get_teachers_for_class, "users");
+create_get_collection_endpoint_by_id!(get_classes_for_user, "classes");
+create_get_collection_endpoint_by_id!(get_classes_for_student, "classes");
+create_get_collection_endpoint_by_id!(get_classes_for_teacher, "classes");
+create_get_collection_endpoint_by_id!(get_classes_for_term, "classes");
+create_get_collection_endpoint_by_id!(get_grading_periods_for_term, "academicSessions");
+create_get_collection_endpoint_by_id!(get_classes_for_course, "classes");
+create_get_collection_endpoint_by_id!(get_courses_for_school, "courses");
+create_get_collection_endpoint_by_id!(get_terms_for_school, "academicSessions");
+create_get_collection_endpoint_by_id!(get_line_items_for_class, "lineItems");
+create_get_collection_endpoint_by_id!(get_results_for_class, "results");
+create_get_collection_endpoint_by_id!(get_resources_for_class, "resources");
+create_get_collection_endpoint_by_id!(get_resources_for_course, "resources");
 
+/// Creates a GET endpoint function for a collection nested under two parent objects
+/// the parent and child sourcedIds are taken from the :id and :sub_id route parameters
+macro_rules! create_get_collection_endpoint_by_ids {
+    ($name:ident, $wrapper:literal) => {
+        async fn $name(req: Request<State>) -> tide::Result {
+            let id = req.param("id")?;
+            let sub_id = req.param("sub_id")?;
+            let params = params::query(&req)?;
+            let collection = db::$name(&req.state().db, id, sub_id, &params).await?;
+            stream::respond(&req, &params, collection, $wrapper, V1P1, convert::identity).await
+        }
+    };
+}
+
+create_get_collection_endpoint_by_ids!(get_enrollments_for_class_in_school, "enrollments");
+create_get_collection_endpoint_by_ids!(get_students_for_class_in_school, "users");
+create_get_collection_endpoint_by_ids!(get_results_for_line_item_for_class, "results");
+create_get_collection_endpoint_by_ids!(get_results_for_student_for_class, "results");
+
+/// Creates a PUT endpoint which upserts a collection as its body is read, objects which
+/// are rejected are listed in a statusInfoSet while the rest are written
+/// $data is the model each object is validated against and read into
+/// $wrapper is the top level json object name of the collection { "myObject": [{}] }
 macro_rules! create_put_endpoint {
-    ($i:ident) => {
+    ($i:ident, $data:ty, $wrapper:literal) => {
         async fn $i(mut req: Request<State>) -> tide::Result {
-            let json = to_vec(&mut req).await?;
+            let objects = body::objects::<$data>(&mut req, $wrapper)?;
+            let written = db::$i(objects, &req.state().db).await?;
+            put_response(written)
+        }
+    };
+}
+
+create_put_endpoint!(
+    put_academic_sessions,
+    model::AcademicSession,
+    "academicSessions"
+);
+create_put_endpoint!(put_periods, model::Period, "periods");
+create_put_endpoint!(put_orgs, model::Org, "orgs");
+create_put_endpoint!(put_users, model::User, "users");
+create_put_endpoint!(put_subjects, model::Subject, "subjects");
+create_put_endpoint!(put_courses, model::Course, "courses");
+create_put_endpoint!(put_classes, model::Class, "classes");
+create_put_endpoint!(put_enrollments, model::Enrollment, "enrollments");
+create_put_endpoint!(put_line_items, model::LineItem, "lineItems");
+create_put_endpoint!(put_categories, model::Category, "categories");
+create_put_endpoint!(put_results, model::GradebookResult, "results");
+create_put_endpoint!(put_resources, model::Resource, "resources");
+create_put_endpoint!(put_demographics, model::Demographic, "demographics");
+
+/// Creates a PUT endpoint which upserts a single object from its wrapper e.g. { "user": {} }
+/// $data is the single object wrapper type, $object is the json object contained in it, its sourcedId must match the :id param
+macro_rules! create_put_endpoint_by_id {
+    ($i:ident, $data:ty, $object:ident) => {
+        async fn $i(mut req: Request<State>) -> tide::Result {
+            let json: $data = to_vec(&mut req).await?;
             log::debug!("put request for: {:?}", json);
+            if json.$object.sourced_id != req.param("id")? {
+                return Err(ServerError::SourcedIdMismatch.into());
+            }
             db::$i(json, &req.state().db).await?;
             Ok(tide::Response::builder(200).build())
         }
     };
 }
 
-create_put_endpoint!(put_academic_sessions);
-create_put_endpoint!(put_periods);
-create_put_endpoint!(put_orgs);
-create_put_endpoint!(put_users);
-create_put_endpoint!(put_subjects);
-create_put_endpoint!(put_courses);
-create_put_endpoint!(put_classes);
-create_put_endpoint!(put_enrollments);
+create_put_endpoint_by_id!(
+    put_academic_session,
+    model::AcademicSessionSingle,
+    academic_session
+);
+create_put_endpoint_by_id!(put_period, model::PeriodSingle, period);
+create_put_endpoint_by_id!(put_subject, model::SubjectSingle, subject);
+create_put_endpoint_by_id!(put_class, model::ClassSingle, class);
+create_put_endpoint_by_id!(put_course, model::CourseSingle, course);
+create_put_endpoint_by_id!(put_org, model::OrgSingle, org);
+create_put_endpoint_by_id!(put_user, model::UserSingle, user);
+create_put_endpoint_by_id!(put_enrollment, model::EnrollmentSingle, enrollment);
+create_put_endpoint_by_id!(put_demographic, model::DemographicSingle, demographics);
+create_put_endpoint_by_id!(put_line_item, model::LineItemSingle, line_item);
+create_put_endpoint_by_id!(put_category, model::CategorySingle, category);
+create_put_endpoint_by_id!(put_result, model::GradebookResultSingle, result);
+create_put_endpoint_by_id!(put_resource, model::ResourceSingle, resource);
+
+macro_rules! create_delete_endpoint {
+    ($i:ident) => {
+        async fn $i(req: Request<State>) -> tide::Result {
+            let id = req.param("id")?;
+            log::debug!("delete request for: {}", id);
+            db::$i(&req.state().db, id).await?;
+            Ok(tide::Response::builder(200).build())
+        }
+    };
+}
+
+create_delete_endpoint!(delete_line_item);
+create_delete_endpoint!(delete_category);
+create_delete_endpoint!(delete_result);
+create_delete_endpoint!(delete_academic_session);
+create_delete_endpoint!(delete_period);
+create_delete_endpoint!(delete_subject);
+create_delete_endpoint!(delete_class);
+create_delete_endpoint!(delete_course);
+create_delete_endpoint!(delete_org);
+create_delete_endpoint!(delete_user);
+create_delete_endpoint!(delete_enrollment);
+create_delete_endpoint!(delete_demographic);
+create_delete_endpoint!(delete_resource);
+create_delete_endpoint!(delete_grading_period);
+create_delete_endpoint!(delete_term);
+create_delete_endpoint!(delete_school);
+create_delete_endpoint!(delete_student);
+create_delete_endpoint!(delete_teacher);
+
+create_delete_endpoint!(purge_academic_session);
+create_delete_endpoint!(purge_period);
+create_delete_endpoint!(purge_subject);
+create_delete_endpoint!(purge_class);
+create_delete_endpoint!(purge_course);
+create_delete_endpoint!(purge_org);
+create_delete_endpoint!(purge_user);
+create_delete_endpoint!(purge_enrollment);
+create_delete_endpoint!(purge_demographic);
+create_delete_endpoint!(purge_line_item);
+create_delete_endpoint!(purge_category);
+create_delete_endpoint!(purge_result);
+create_delete_endpoint!(purge_resource);
 
 pub struct Config {
     pub database: String,
@@ -142,98 +266,301 @@ pub struct Config {
     pub decode_key: jsonwebtoken::DecodingKey,
     pub web_public_key: String,
     pub web_private_key: String,
+    pub external_url: Option<String>,
+    /// use the X-Forwarded-* headers set by a reverse proxy in pagination links
+    pub trust_proxy: bool,
+    /// largest request body accepted in bytes, after any content encoding is decoded
+    pub max_body_size: u64,
+    /// largest page size a client may request with the limit parameter
+    pub max_limit: u32,
+    /// largest number of connections held to the database
+    pub db_max_connections: u32,
+    /// how long a request waits for a free database connection before failing
+    pub db_acquire_timeout: std::time::Duration,
+}
+
+/// Applies any pending schema migrations to an existing database without starting the server
+pub async fn migrate(database: &str) -> Result<()> {
+    let db = db::init(database, false, db::Pool::default()).await?;
+    db.close().await;
+    log::info!("database is up to date");
+    Ok(())
 }
 
-pub async fn run(config: Config) -> tide::Result<()> {
+pub async fn run(config: Config) -> Result<()> {
     log::info!("starting server...");
     //log::debug!("configuration: {:?}", config);
 
-    let path = "sqlite:".to_owned() + &config.database;
-    let pool = match db::init(&path, config.init).await {
-        Ok(pool) => pool,
-        Err(e) => {
-            log::error!("Error: could not start server: {}", e);
-            return Ok(());
-        }
+    let pool = db::Pool {
+        max_connections: config.db_max_connections,
+        acquire_timeout: config.db_acquire_timeout,
     };
+    let db = db::init(&config.database, config.init, pool).await?;
 
     let state = State {
-        db: pool,
+        db,
         encode_key: config.encode_key,
         decode_key: config.decode_key,
+        external_url: config
+            .external_url
+            .map(|u| u.trim_end_matches('/').to_string()),
+        trust_proxy: config.trust_proxy,
+        max_body_size: config.max_body_size,
+        max_limit: config.max_limit,
     };
     let mut srv = tide::with_state(state);
 
     srv.with(After(errors::middleware::ApiError::new()));
+    srv.with(compression::Compression::new());
+    srv.with(conditional::ConditionalGet::new());
     log::info!("ready on: {}", &config.socket_address);
     srv.at("/").get(|_| async { Ok("oneroster ui\n") });
     srv.at("/auth/login").post(login);
     srv.at("/auth/check_token").get(check_token);
     // oneroster
     let mut authsrv = tide::with_state(srv.state().clone());
-    authsrv.with(auth::middleware::Jwt::new(vec![
+    let mut roster = authsrv.at("");
+    roster.with(auth::middleware::Jwt::new(vec![
         "roster-core".to_string(),
         "roster".to_string(),
     ]));
-    authsrv
+    roster
         .at("/")
         .get(|_| async { Ok("hello protected world\n") });
-    authsrv.at("/orgs").get(get_all_orgs).put(put_orgs);
-    authsrv.at("/orgs/:id").get(get_org);
-    authsrv.at("/schools").get(get_all_schools);
-    authsrv.at("/schools/:id").get(get_school);
-    authsrv
+    roster.at("/orgs").get(get_all_orgs).put(put_orgs);
+    roster
+        .at("/orgs/:id")
+        .get(get_org)
+        .put(put_org)
+        .delete(delete_org);
+    roster.at("/schools").get(get_all_schools);
+    roster
+        .at("/schools/:id")
+        .get(get_school)
+        .delete(delete_school);
+    roster
         .at("/schools/:id/classes")
         .get(get_classes_for_school);
-    authsrv
+    roster
         .at("/schools/:id/students")
         .get(get_students_for_school);
-    authsrv
+    roster
         .at("/schools/:id/teachers")
         .get(get_teachers_for_school);
-    authsrv
+    roster
         .at("/schools/:id/enrollments")
         .get(get_enrollments_for_school);
-    authsrv.at("/classes").get(get_all_classes).put(put_classes);
-    authsrv.at("/classes/:id").get(get_class);
-    authsrv
+    roster
+        .at("/schools/:id/classes/:sub_id/enrollments")
+        .get(get_enrollments_for_class_in_school);
+    roster
+        .at("/schools/:id/classes/:sub_id/students")
+        .get(get_students_for_class_in_school);
+    roster
+        .at("/schools/:id/courses")
+        .get(get_courses_for_school);
+    roster.at("/schools/:id/terms").get(get_terms_for_school);
+    roster.at("/classes").get(get_all_classes).put(put_classes);
+    roster
+        .at("/classes/:id")
+        .get(get_class)
+        .put(put_class)
+        .delete(delete_class);
+    roster
+        .at("/classes/:id/students")
+        .get(get_students_for_class);
+    roster
+        .at("/classes/:id/teachers")
+        .get(get_teachers_for_class);
+    roster
         .at("/academicSessions")
         .get(get_all_academic_sessions)
         .put(put_academic_sessions);
-    authsrv
+    roster
         .at("/academicSessions/:id")
-        .get(get_academic_session);
-    authsrv.at("/gradingPeriods").get(get_all_grading_periods);
-    authsrv.at("/gradingPeriods/:id").get(get_grading_period);
-    authsrv.at("/periods").get(get_all_periods).put(put_periods);
-    authsrv
+        .get(get_academic_session)
+        .put(put_academic_session)
+        .delete(delete_academic_session);
+    roster.at("/gradingPeriods").get(get_all_grading_periods);
+    roster
+        .at("/gradingPeriods/:id")
+        .get(get_grading_period)
+        .delete(delete_grading_period);
+    roster.at("/periods").get(get_all_periods).put(put_periods);
+    roster
+        .at("/periods/:id")
+        .get(get_period)
+        .put(put_period)
+        .delete(delete_period);
+    roster
         .at("/subjects")
         .get(get_all_subjects)
         .put(put_subjects);
-    authsrv.at("/courses").get(get_all_courses).put(put_courses);
-    authsrv.at("/courses/:id").get(get_course);
-    authsrv.at("/users").get(get_all_users).put(put_users);
-    authsrv.at("/users/:id").get(get_user);
-    authsrv.at("/students").get(get_all_students);
-    authsrv.at("/students/:id").get(get_student);
-    authsrv.at("/teachers").get(get_all_teachers);
-    authsrv.at("/teachers/:id").get(get_teacher);
-    authsrv.at("/terms").get(get_all_terms);
-    authsrv.at("/terms/:id").get(get_term);
-    authsrv
+    roster
+        .at("/subjects/:id")
+        .get(get_subject)
+        .put(put_subject)
+        .delete(delete_subject);
+    roster.at("/courses").get(get_all_courses).put(put_courses);
+    roster
+        .at("/courses/:id")
+        .get(get_course)
+        .put(put_course)
+        .delete(delete_course);
+    roster
+        .at("/courses/:id/classes")
+        .get(get_classes_for_course);
+    roster.at("/users").get(get_all_users).put(put_users);
+    roster
+        .at("/users/:id")
+        .get(get_user)
+        .put(put_user)
+        .delete(delete_user);
+    roster.at("/users/:id/classes").get(get_classes_for_user);
+    roster.at("/students").get(get_all_students);
+    roster
+        .at("/students/:id")
+        .get(get_student)
+        .delete(delete_student);
+    roster
+        .at("/students/:id/classes")
+        .get(get_classes_for_student);
+    roster.at("/teachers").get(get_all_teachers);
+    roster
+        .at("/teachers/:id")
+        .get(get_teacher)
+        .delete(delete_teacher);
+    roster
+        .at("/teachers/:id/classes")
+        .get(get_classes_for_teacher);
+    roster.at("/terms").get(get_all_terms);
+    roster.at("/terms/:id").get(get_term).delete(delete_term);
+    roster.at("/terms/:id/classes").get(get_classes_for_term);
+    roster
+        .at("/terms/:id/gradingPeriods")
+        .get(get_grading_periods_for_term);
+    roster
         .at("/enrollments")
         .get(get_all_enrollments)
         .put(put_enrollments);
-    authsrv.at("/enrollments/:id").get(get_enrollment);
+    roster
+        .at("/enrollments/:id")
+        .get(get_enrollment)
+        .put(put_enrollment)
+        .delete(delete_enrollment);
+    // oneroster demographics
+    let mut demographics = authsrv.at("");
+    demographics.with(auth::middleware::Jwt::new(vec![
+        "roster-demographics".to_string()
+    ]));
+    demographics
+        .at("/demographics")
+        .get(get_all_demographics)
+        .put(put_demographics);
+    demographics
+        .at("/demographics/:id")
+        .get(get_demographics)
+        .put(put_demographic)
+        .delete(delete_demographic);
+    // oneroster gradebook
+    let mut gradebook = authsrv.at("");
+    gradebook.with(auth::middleware::Jwt::new(vec!["gradebook".to_string()]));
+    gradebook
+        .at("/categories")
+        .get(get_all_categories)
+        .put(put_categories);
+    gradebook
+        .at("/categories/:id")
+        .get(get_category)
+        .put(put_category)
+        .delete(delete_category);
+    gradebook
+        .at("/lineItems")
+        .get(get_all_line_items)
+        .put(put_line_items);
+    gradebook
+        .at("/lineItems/:id")
+        .get(get_line_item)
+        .put(put_line_item)
+        .delete(delete_line_item);
+    gradebook
+        .at("/results")
+        .get(get_all_results)
+        .put(put_results);
+    gradebook
+        .at("/results/:id")
+        .get(get_result)
+        .put(put_result)
+        .delete(delete_result);
+    gradebook
+        .at("/classes/:id/lineItems")
+        .get(get_line_items_for_class);
+    gradebook
+        .at("/classes/:id/results")
+        .get(get_results_for_class);
+    gradebook
+        .at("/classes/:id/lineItems/:sub_id/results")
+        .get(get_results_for_line_item_for_class);
+    gradebook
+        .at("/classes/:id/students/:sub_id/results")
+        .get(get_results_for_student_for_class);
+    // oneroster resources
+    let mut resources = authsrv.at("");
+    resources.with(auth::middleware::Jwt::new(vec!["resource".to_string()]));
+    resources
+        .at("/resources")
+        .get(get_all_resources)
+        .put(put_resources);
+    resources
+        .at("/resources/:id")
+        .get(get_resource)
+        .put(put_resource)
+        .delete(delete_resource);
+    resources
+        .at("/classes/:id/resources")
+        .get(get_resources_for_class);
+    resources
+        .at("/courses/:id/resources")
+        .get(get_resources_for_course);
+    // cross collection delta feed, limited to the objects the token can read
+    let mut changes = authsrv.at("");
+    changes.with(auth::middleware::Jwt::new(
+        CHANGE_GROUPS.iter().map(|g| g.to_string()).collect(),
+    ));
+    changes.at("/changes").get(get_changes);
+    // schemas of the PUT bodies, public so payloads can be checked before they are sent
+    authsrv.at("/schemas").get(schema::get_schemas);
+    authsrv.at("/schemas/:name").get(schema::get_schema);
     // user management
     let mut adminsrv = tide::with_state(srv.state().clone());
     adminsrv.with(auth::middleware::Jwt::new(vec!["admin".to_string()]));
     adminsrv.at("/users").get(get_api_users);
     adminsrv.at("/user").post(create_api_user);
     adminsrv.at("/user/:uuid").delete(delete_api_user);
+    adminsrv
+        .at("/purge/academicSessions/:id")
+        .delete(purge_academic_session);
+    adminsrv.at("/purge/periods/:id").delete(purge_period);
+    adminsrv.at("/purge/subjects/:id").delete(purge_subject);
+    adminsrv.at("/purge/classes/:id").delete(purge_class);
+    adminsrv.at("/purge/courses/:id").delete(purge_course);
+    adminsrv.at("/purge/orgs/:id").delete(purge_org);
+    adminsrv.at("/purge/users/:id").delete(purge_user);
+    adminsrv
+        .at("/purge/enrollments/:id")
+        .delete(purge_enrollment);
+    adminsrv
+        .at("/purge/demographics/:id")
+        .delete(purge_demographic);
+    adminsrv.at("/purge/lineItems/:id").delete(purge_line_item);
+    adminsrv.at("/purge/categories/:id").delete(purge_category);
+    adminsrv.at("/purge/results/:id").delete(purge_result);
+    adminsrv.at("/purge/resources/:id").delete(purge_resource);
 
     srv.at("/admin").nest(adminsrv);
-    srv.at("/ims/oneroster/v1p1").nest(authsrv);
+    srv.at(V1P1).nest(authsrv);
+    let rostering = v1p2::rostering(srv.state().clone());
+    srv.at(V1P2).nest(rostering);
     srv.listen(
         TlsListener::build()
             .addrs(config.socket_address)
@@ -253,14 +580,41 @@ pub struct Creds {
     scope: String,
 }
 
-//async fn to_vec<T>(req: &mut Request<State>) -> Result<Vec<T>>
+/// Lists the objects rejected from a collection PUT, which fails only if objects were sent
+/// and every one was rejected as nothing has been written. An empty collection succeeds
+fn put_response(put: db::Written) -> tide::Result {
+    let sent = put.written as usize + put.rejected.len();
+    let status = if sent > 0 && put.written == 0 {
+        400
+    } else {
+        200
+    };
+    if put.rejected.is_empty() {
+        return Ok(tide::Response::builder(status).build());
+    }
+    let status_info_set: Vec<errors::ErrorPayload> = put
+        .rejected
+        .into_iter()
+        .map(|r| {
+            let description = match r.sourced_id {
+                Some(id) => format!("'{}' rejected: {}", id, r.reason),
+                None => format!("rejected: {}", r.reason),
+            };
+            errors::ErrorPayload::failure(errors::CodeMinor::InvalidData, Some(description))
+        })
+        .collect();
+    log::warn!("{} objects rejected from PUT", status_info_set.len());
+    Ok(tide::Response::builder(status)
+        .content_type(mime::JSON)
+        .body(json!({ "statusInfoSet": status_info_set }))
+        .build())
+}
+
 async fn to_vec<T>(req: &mut Request<State>) -> Result<T>
 where
-    for<'a> T: Deserialize<'a>,
+    T: serde::de::DeserializeOwned + schemars::JsonSchema + 'static,
 {
-    let s = req.body_string().await.unwrap();
-    let v = serde_json::from_str(&s)?;
-    Ok(v)
+    body::read(req).await
 }
 
 async fn login(mut req: tide::Request<State>) -> tide::Result {
@@ -288,6 +642,55 @@ async fn get_api_users(req: tide::Request<State>) -> tide::Result {
     Ok(tide::Response::builder(200).body(json!(res)).build())
 }
 
+/// scope groups which can read the changes feed, roster is an alias of roster-core
+const CHANGE_GROUPS: [&str; 5] = [
+    "roster-core",
+    "roster",
+    "roster-demographics",
+    "gradebook",
+    "resource",
+];
+
+/// the groups of the changes feed a token's scope can read
+fn change_groups(scope: &str) -> Vec<&'static str> {
+    CHANGE_GROUPS
+        .iter()
+        .filter(|g| auth::middleware::has_scope(scope, &format!("{}.readonly", g)))
+        .map(|g| if *g == "roster" { "roster-core" } else { *g })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct Since {
+    since: Option<String>,
+}
+
+/// Lists the objects modified since a timestamp across every collection the token can read
+async fn get_changes(req: Request<State>) -> tide::Result {
+    let params = params::query(&req)?;
+    // without a timestamp the feed would be every object in the database
+    let since = req
+        .query::<Since>()?
+        .since
+        .and_then(|since| params::timestamp(&since))
+        .ok_or(ServerError::InvalidParameters)?;
+    let scope = match req.ext::<auth::jwt::Claims>() {
+        Some(claims) => &claims.scope,
+        None => return Err(ServerError::NoPermission.into()),
+    };
+    let groups = change_groups(scope);
+    let collection = db::get_changes(&req.state().db, &since, &groups, &params).await?;
+    stream::respond(
+        &req,
+        &params,
+        collection,
+        "changes",
+        V1P1,
+        convert::identity,
+    )
+    .await
+}
+
 async fn check_token(req: tide::Request<State>) -> tide::Result<String> {
     let token = auth::middleware::parse_auth_header(&req).await?;
     if auth::jwt::validate_token(token, &req.state().decode_key).await {
@@ -314,15 +717,684 @@ pub fn read_public_key(path: &str) -> Result<jsonwebtoken::DecodingKey> {
     Ok(public_key)
 }
 
+/// server state over a scratch database, for testing middleware and endpoints
+#[cfg(test)]
+pub(crate) async fn test_state(name: &str) -> State {
+    State {
+        db: db::scratch(name).await,
+        encode_key: jsonwebtoken::EncodingKey::from_secret(b"test"),
+        decode_key: jsonwebtoken::DecodingKey::from_secret(b"test"),
+        external_url: None,
+        trust_proxy: false,
+        max_body_size: 4096,
+        max_limit: 100,
+    }
+}
+
 // tests
 #[cfg(test)]
 #[async_std::test]
 async fn db() -> Result<()> {
-    let path = "sqlite:./db/rust_test.db";
-    db::init(path, true).await?;
-    let pool = db::connect(path).await?;
+    let db = db::init("./db/rust_test.db", true, db::Pool::default()).await?;
     let content = async_std::fs::read_to_string("./sample/academicSessions.json").await?;
-    let json = serde_json::from_str(&content)?;
-    db::put_academic_sessions(json, &pool).await?;
+    let json: model::AcademicSessions = serde_json::from_str(&content)?;
+    let (sender, objects) = async_std::channel::unbounded();
+    for object in json.academic_sessions {
+        let object = db::PutObject {
+            object: serde_json::to_value(object)?,
+            violations: Vec::new(),
+        };
+        let _ = sender.send(Ok(object)).await;
+    }
+    drop(sender);
+    let written = db::put_academic_sessions(objects, &db).await?;
+    assert!(written.rejected.is_empty());
     Ok(())
 }
+
+/// runs against the scratch database given in `OR_TEST_POSTGRES`, which is dropped first,
+/// e.g. `postgres://postgres@localhost/oneroster_test`. Skipped when it is not set
+#[cfg(test)]
+#[async_std::test]
+async fn postgres() -> Result<()> {
+    use sqlx::migrate::MigrateDatabase;
+    let Ok(url) = std::env::var("OR_TEST_POSTGRES") else {
+        return Ok(());
+    };
+    if sqlx::Postgres::database_exists(&url).await? {
+        sqlx::Postgres::drop_database(&url).await?;
+    }
+    let db = db::init(&url, true, db::Pool::default()).await?;
+    // migrating again finds nothing pending
+    db.migrate().await?;
+
+    let content = async_std::fs::read_to_string("./sample/orgs.json").await?;
+    let json: model::Orgs = serde_json::from_str(&content)?;
+    let (sender, objects) = async_std::channel::unbounded();
+    for object in json.orgs {
+        let object = db::PutObject {
+            object: serde_json::to_value(object)?,
+            violations: Vec::new(),
+        };
+        let _ = sender.send(Ok(object)).await;
+    }
+    drop(sender);
+    let written = db::put_orgs(objects, &db).await?;
+    assert!(written.rejected.is_empty());
+
+    let params = params::Parameters {
+        filter: Some("status='active'".to_string()),
+        ..Default::default()
+    };
+    let collection = db::get_all_orgs(&db, &params).await?;
+    assert_eq!(collection.total, 2);
+    let rows = collection.rows();
+    let mut orgs = Vec::new();
+    while let Ok(org) = rows.recv().await {
+        orgs.push(org?.sourced_id);
+    }
+    assert_eq!(orgs, ["017", "018"]);
+
+    db::delete_org(&db, "018").await?;
+    db::purge_org(&db, "018").await?;
+    let changes = db::get_changes(&db, "2000-01-01", &["roster-core"], &Default::default()).await?;
+    let rows = changes.rows();
+    let mut tombstone = None;
+    while let Ok(change) = rows.recv().await {
+        let change = change?;
+        if change["sourcedId"] == "018" {
+            tombstone = Some(change);
+        }
+    }
+    let tombstone = tombstone.expect("no change for the purged org");
+    assert_eq!(tombstone["status"], "tobedeleted");
+    assert!(tombstone["object"].is_null());
+    assert!(
+        db::get_changes(&db, "2000-01-01", &["roster-core"], &params)
+            .await
+            .is_err()
+    );
+    db.close().await;
+    Ok(())
+}
+
+#[cfg(test)]
+#[test]
+fn put_fails_only_when_every_object_sent_is_rejected() {
+    let put = |written, rejected: usize| {
+        let rejected = (0..rejected)
+            .map(|_| db::Rejected {
+                sourced_id: None,
+                reason: "unknown org".to_string(),
+            })
+            .collect();
+        let res = put_response(db::Written { written, rejected }).unwrap();
+        u16::from(res.status())
+    };
+    assert_eq!(put(0, 0), 200);
+    assert_eq!(put(2, 0), 200);
+    assert_eq!(put(2, 1), 200);
+    assert_eq!(put(0, 1), 400);
+}
+
+#[cfg(test)]
+#[test]
+fn changes_are_read_by_whole_scopes() {
+    assert_eq!(
+        change_groups("roster.readonly gradebook.readonly roster-core.createput"),
+        ["roster-core", "gradebook"]
+    );
+    assert!(change_groups("xroster-core.readonly roster-demographics.readonlyx").is_empty());
+}
+
+#[cfg(test)]
+#[async_std::test]
+async fn changes_require_a_since_timestamp() -> Result<()> {
+    let mut app = tide::with_state(test_state("changes_since").await);
+    app.with(After(errors::middleware::ApiError::new()));
+    app.at("/changes").get(get_changes);
+    for (query, status) in [
+        ("", 400),
+        ("?since=", 400),
+        ("?since=yesterday", 400),
+        // read without the claims of a token
+        ("?since=2000-01-01", 403),
+    ] {
+        let url = format!("http://localhost/changes{}", query);
+        let res: tide::http::Response = app
+            .respond(http_types::Request::get(url.as_str()))
+            .await
+            .unwrap();
+        assert_eq!(u16::from(res.status()), status, "{}", query);
+    }
+    Ok(())
+}
+
+/// an app over a scratch database with the collection PUTs of the rostering samples and
+/// without tokens, to which a test adds the routes it reads
+#[cfg(test)]
+async fn test_app(name: &str) -> tide::Server<State> {
+    let mut app = tide::with_state(test_state(name).await);
+    app.with(After(errors::middleware::ApiError::new()));
+    app.at("/subjects").put(put_subjects);
+    app.at("/orgs").put(put_orgs);
+    app.at("/academicSessions").put(put_academic_sessions);
+    app.at("/periods").put(put_periods);
+    app.at("/courses").put(put_courses);
+    app.at("/classes").put(put_classes);
+    app.at("/users").put(put_users);
+    app.at("/enrollments").put(put_enrollments);
+    app
+}
+
+/// the status and json body of a response, null when there is no body
+#[cfg(test)]
+async fn respond(app: &tide::Server<State>, req: http_types::Request) -> (u16, serde_json::Value) {
+    let mut res: http_types::Response = app.respond(req).await.unwrap();
+    let body = res.body_string().await.unwrap();
+    let json = match body.is_empty() {
+        true => serde_json::Value::Null,
+        false => serde_json::from_str(&body).unwrap(),
+    };
+    (res.status().into(), json)
+}
+
+/// a GET of `path`
+#[cfg(test)]
+fn get(path: &str) -> http_types::Request {
+    http_types::Request::get(format!("http://localhost{}", path).as_str())
+}
+
+/// a PUT of `body` to `path`
+#[cfg(test)]
+fn put(path: &str, body: &serde_json::Value) -> http_types::Request {
+    let mut req = http_types::Request::put(format!("http://localhost{}", path).as_str());
+    req.set_body(body.to_string());
+    req
+}
+
+/// a sample collection with its wrapper e.g. { "orgs": [] }
+#[cfg(test)]
+fn sample(name: &str) -> serde_json::Value {
+    let content = std::fs::read_to_string(format!("./sample/{}.json", name)).unwrap();
+    serde_json::from_str(&content).unwrap()
+}
+
+/// writes the rostering samples in the order they refer to each other
+#[cfg(test)]
+async fn put_samples(app: &tide::Server<State>, change: impl Fn(&str, &mut serde_json::Value)) {
+    for name in [
+        "subjects",
+        "orgs",
+        "academicSessions",
+        "periods",
+        "courses",
+        "classes",
+        "users",
+        "enrollments",
+    ] {
+        let mut body = sample(name);
+        change(name, &mut body);
+        let path = format!("/{}", name);
+        assert_eq!(
+            respond(app, put(&path, &body)).await,
+            (200, json!(null)),
+            "{}",
+            name
+        );
+    }
+}
+
+/// the sourcedIds of the objects in a collection read from `path`
+#[cfg(test)]
+async fn ids(app: &tide::Server<State>, path: &str, wrapper: &str) -> Vec<String> {
+    let (status, json) = respond(app, get(path)).await;
+    assert_eq!(status, 200, "{}: {}", path, json);
+    json[wrapper]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|o| o["sourcedId"].as_str().unwrap().to_string())
+        .collect()
+}
+
+/// an object as it is read back, without the dateLastModified set when it was written
+#[cfg(test)]
+fn written(mut object: serde_json::Value) -> serde_json::Value {
+    object.as_object_mut().unwrap().remove("dateLastModified");
+    object
+}
+
+/// a reference to the object `id` of a collection
+#[cfg(test)]
+fn guid_ref(collection: &str, id: &str, kind: &str) -> serde_json::Value {
+    let href = format!("{}/{}", collection, id);
+    json!({ "href": href, "sourcedId": id, "type": kind })
+}
+
+#[cfg(test)]
+#[async_std::test]
+async fn round_trips_gradebook_objects() {
+    let mut app = test_app("gradebook").await;
+    app.at("/categories")
+        .get(get_all_categories)
+        .put(put_categories);
+    app.at("/categories/:id").get(get_category);
+    app.at("/lineItems")
+        .get(get_all_line_items)
+        .put(put_line_items);
+    app.at("/lineItems/:id").get(get_line_item);
+    app.at("/results").get(get_all_results).put(put_results);
+    app.at("/results/:id").get(get_result);
+    app.at("/classes/:id/lineItems")
+        .get(get_line_items_for_class);
+    app.at("/classes/:id/results").get(get_results_for_class);
+    app.at("/classes/:id/lineItems/:sub_id/results")
+        .get(get_results_for_line_item_for_class);
+    app.at("/classes/:id/students/:sub_id/results")
+        .get(get_results_for_student_for_class);
+    put_samples(&app, |_, _| {}).await;
+
+    let modified = "2012-04-23T18:25:43.511Z";
+    let category = json!({
+        "sourcedId": "c1",
+        "status": "active",
+        "dateLastModified": modified,
+        "title": "homework",
+    });
+    let line_item = |id: &str, class: &str| {
+        json!({
+            "sourcedId": id,
+            "status": "active",
+            "dateLastModified": modified,
+            "title": format!("test {}", id),
+            "description": "end of unit",
+            "assignDate": "2021-01-04",
+            "dueDate": "2021-01-11",
+            "class": guid_ref("classes", class, "class"),
+            "category": guid_ref("categories", "c1", "category"),
+            "gradingPeriod": guid_ref("academicSessions", "001", "academicSession"),
+            "resultValueMin": 0.0,
+            "resultValueMax": 20.0,
+        })
+    };
+    let result = |id: &str, line_item: &str, student: &str| {
+        json!({
+            "sourcedId": id,
+            "status": "active",
+            "dateLastModified": modified,
+            "lineItem": guid_ref("lineItems", line_item, "lineItem"),
+            "student": guid_ref("users", student, "user"),
+            "scoreStatus": "fully graded",
+            "score": 17.5,
+            "scoreDate": "2021-01-12",
+            "comment": "good",
+        })
+    };
+    let line_items = [line_item("l1", "01"), line_item("l2", "02")];
+    let results = [
+        result("r1", "l1", "001"),
+        result("r2", "l2", "001"),
+        result("r3", "l1", "002"),
+    ];
+    for (path, body) in [
+        ("/categories", json!({ "categories": [category] })),
+        ("/lineItems", json!({ "lineItems": line_items })),
+        ("/results", json!({ "results": results })),
+    ] {
+        assert_eq!(
+            respond(&app, put(path, &body)).await,
+            (200, json!(null)),
+            "{}",
+            path
+        );
+    }
+
+    let (status, json) = respond(&app, get("/categories/c1")).await;
+    assert_eq!(status, 200);
+    assert_eq!(written(json["category"].clone()), written(category));
+    let (status, json) = respond(&app, get("/lineItems/l1")).await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        written(json["lineItem"].clone()),
+        written(line_items[0].clone())
+    );
+    let (status, json) = respond(&app, get("/results/r3")).await;
+    assert_eq!(status, 200);
+    assert_eq!(written(json["result"].clone()), written(results[2].clone()));
+    assert_eq!(respond(&app, get("/results/r4")).await.0, 404);
+
+    assert_eq!(ids(&app, "/categories", "categories").await, ["c1"]);
+    assert_eq!(ids(&app, "/lineItems", "lineItems").await, ["l1", "l2"]);
+    assert_eq!(ids(&app, "/results", "results").await, ["r1", "r2", "r3"]);
+    assert_eq!(
+        ids(&app, "/classes/01/lineItems", "lineItems").await,
+        ["l1"]
+    );
+    assert_eq!(
+        ids(&app, "/classes/01/results", "results").await,
+        ["r1", "r3"]
+    );
+    assert_eq!(
+        ids(&app, "/classes/02/lineItems/l2/results", "results").await,
+        ["r2"]
+    );
+    assert!(ids(&app, "/classes/02/lineItems/l1/results", "results")
+        .await
+        .is_empty());
+    assert_eq!(
+        ids(&app, "/classes/01/students/002/results", "results").await,
+        ["r3"]
+    );
+}
+
+#[cfg(test)]
+#[async_std::test]
+async fn round_trips_resources_of_classes_and_courses() {
+    let mut app = test_app("resources").await;
+    app.at("/resources")
+        .get(get_all_resources)
+        .put(put_resources);
+    app.at("/resources/:id").get(get_resource);
+    app.at("/classes/:id/resources")
+        .get(get_resources_for_class);
+    app.at("/courses/:id/resources")
+        .get(get_resources_for_course);
+    put_samples(&app, |_, _| {}).await;
+
+    let resource = |id: &str| {
+        json!({
+            "sourcedId": id,
+            "status": "active",
+            "dateLastModified": "2012-04-23T18:25:43.511Z",
+            "title": format!("textbook {}", id),
+            "roles": ["student", "teacher"],
+            "importance": "primary",
+            "vendorResourceId": format!("vendor-{}", id),
+            "vendorId": "vendor",
+            "applicationId": "reader",
+        })
+    };
+    let resources = [resource("e1"), resource("e2")];
+    let body = json!({ "resources": resources });
+    assert_eq!(
+        respond(&app, put("/resources", &body)).await,
+        (200, json!(null))
+    );
+    let (status, json) = respond(&app, get("/resources/e2")).await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        written(json["resource"].clone()),
+        written(resources[1].clone())
+    );
+    assert_eq!(ids(&app, "/resources", "resources").await, ["e1", "e2"]);
+
+    // class 01 is licensed for both, its course 01 only for e2
+    let mut classes = sample("classes");
+    classes["classes"][0]["resources"] = json!([
+        guid_ref("resources", "e1", "resource"),
+        guid_ref("resources", "e2", "resource"),
+    ]);
+    let mut courses = sample("courses");
+    courses["courses"][0]["resources"] = json!([guid_ref("resources", "e2", "resource")]);
+    assert_eq!(
+        respond(&app, put("/classes", &classes)).await,
+        (200, json!(null))
+    );
+    assert_eq!(
+        respond(&app, put("/courses", &courses)).await,
+        (200, json!(null))
+    );
+    assert_eq!(
+        ids(&app, "/classes/01/resources", "resources").await,
+        ["e1", "e2"]
+    );
+    assert!(ids(&app, "/classes/02/resources", "resources")
+        .await
+        .is_empty());
+    assert_eq!(
+        ids(&app, "/courses/01/resources", "resources").await,
+        ["e2"]
+    );
+}
+
+#[cfg(test)]
+#[async_std::test]
+async fn round_trips_demographics() {
+    let mut app = test_app("demographics").await;
+    app.at("/demographics")
+        .get(get_all_demographics)
+        .put(put_demographics);
+    app.at("/demographics/:id").get(get_demographics);
+    put_samples(&app, |_, _| {}).await;
+
+    let demographics = [
+        json!({
+            "sourcedId": "001",
+            "status": "active",
+            "dateLastModified": "2012-04-23T18:25:43.511Z",
+            "birthDate": "2008-02-29",
+            "sex": "female",
+            "white": "true",
+            "hispanicOrLatinoEthnicity": "false",
+            "countryOfBirthCode": "GB",
+            "cityOfBirth": "Glasgow",
+        }),
+        json!({
+            "sourcedId": "003",
+            "status": "active",
+            "dateLastModified": "2012-04-23T18:25:43.511Z",
+            "sex": "male",
+        }),
+    ];
+    let body = json!({ "demographics": demographics });
+    assert_eq!(
+        respond(&app, put("/demographics", &body)).await,
+        (200, json!(null))
+    );
+    for (id, sent) in [("001", &demographics[0]), ("003", &demographics[1])] {
+        let (status, json) = respond(&app, get(&format!("/demographics/{}", id))).await;
+        assert_eq!(status, 200);
+        assert_eq!(written(json["demographics"].clone()), written(sent.clone()));
+    }
+    assert_eq!(respond(&app, get("/demographics/002")).await.0, 404);
+    assert_eq!(
+        ids(&app, "/demographics", "demographics").await,
+        ["001", "003"]
+    );
+}
+
+#[cfg(test)]
+#[async_std::test]
+async fn lists_the_members_of_a_class() {
+    let mut app = test_app("class_members").await;
+    app.at("/classes/:id/students").get(get_students_for_class);
+    app.at("/classes/:id/teachers").get(get_teachers_for_class);
+    app.at("/schools/:id/classes/:sub_id/enrollments")
+        .get(get_enrollments_for_class_in_school);
+    put_samples(&app, |_, _| {}).await;
+
+    // 001 is enrolled in class 01 as a student and 003 as its teacher, both through 017
+    assert_eq!(ids(&app, "/classes/01/students", "users").await, ["001"]);
+    assert_eq!(ids(&app, "/classes/01/teachers", "users").await, ["003"]);
+    assert!(ids(&app, "/classes/02/students", "users").await.is_empty());
+    assert_eq!(
+        ids(&app, "/schools/017/classes/01/enrollments", "enrollments").await,
+        ["01", "02"]
+    );
+    assert!(
+        ids(&app, "/schools/016/classes/01/enrollments", "enrollments")
+            .await
+            .is_empty()
+    );
+    // the collection parameters apply to the members
+    let filtered = "/classes/01/students?filter=status%3D'tobedeleted'";
+    assert!(ids(&app, filtered, "users").await.is_empty());
+    let fields = "/classes/01/teachers?fields=sourcedId,role";
+    assert_eq!(
+        respond(&app, get(fields)).await,
+        (
+            200,
+            json!({ "users": [{ "sourcedId": "003", "role": "teacher" }] })
+        )
+    );
+}
+
+#[cfg(test)]
+#[async_std::test]
+async fn lists_the_classes_of_a_user_by_role() {
+    let mut app = test_app("user_classes").await;
+    app.at("/users/:id/classes").get(get_classes_for_user);
+    app.at("/students/:id/classes").get(get_classes_for_student);
+    app.at("/teachers/:id/classes").get(get_classes_for_teacher);
+    // 003 teaches class 01 and is also a student of class 02
+    put_samples(&app, |name, body| {
+        if name == "enrollments" {
+            let mut enrollment = body["enrollments"][0].clone();
+            enrollment["sourcedId"] = json!("03");
+            enrollment["user"]["sourcedId"] = json!("003");
+            enrollment["class"]["sourcedId"] = json!("02");
+            body["enrollments"].as_array_mut().unwrap().push(enrollment);
+        }
+    })
+    .await;
+
+    assert_eq!(
+        ids(&app, "/users/003/classes", "classes").await,
+        ["01", "02"]
+    );
+    assert_eq!(ids(&app, "/students/003/classes", "classes").await, ["02"]);
+    assert_eq!(ids(&app, "/teachers/003/classes", "classes").await, ["01"]);
+    assert_eq!(ids(&app, "/students/001/classes", "classes").await, ["01"]);
+    assert!(ids(&app, "/teachers/001/classes", "classes")
+        .await
+        .is_empty());
+    assert!(ids(&app, "/users/002/classes", "classes").await.is_empty());
+    let sorted = "/users/003/classes?sort=title&orderBy=desc";
+    assert_eq!(ids(&app, sorted, "classes").await, ["02", "01"]);
+}
+
+#[cfg(test)]
+#[async_std::test]
+async fn lists_the_objects_of_terms_courses_and_schools() {
+    let mut app = test_app("hierarchy").await;
+    app.at("/terms/:id/classes").get(get_classes_for_term);
+    app.at("/terms/:id/gradingPeriods")
+        .get(get_grading_periods_for_term);
+    app.at("/courses/:id/classes").get(get_classes_for_course);
+    app.at("/schools/:id/courses").get(get_courses_for_school);
+    app.at("/schools/:id/terms").get(get_terms_for_school);
+    app.at("/schools/:id/classes/:sub_id/students")
+        .get(get_students_for_class_in_school);
+    // 004 is a grading period of the term 001, in which class 02 is taught, and course 02
+    // moves to the district 018
+    put_samples(&app, |name, body| match name {
+        "academicSessions" => {
+            let mut period = body["academicSessions"][0].clone();
+            period["sourcedId"] = json!("004");
+            period["type"] = json!("gradingPeriod");
+            period["parent"] = guid_ref("academicSessions", "001", "academicSession");
+            body["academicSessions"]
+                .as_array_mut()
+                .unwrap()
+                .push(period);
+        }
+        "classes" => {
+            body["classes"][1]["terms"] =
+                json!([guid_ref("academicSessions", "001", "academicSession")]);
+        }
+        "courses" => body["courses"][1]["org"] = guid_ref("orgs", "018", "org"),
+        _ => (),
+    })
+    .await;
+
+    assert_eq!(ids(&app, "/terms/001/classes", "classes").await, ["02"]);
+    assert_eq!(
+        ids(&app, "/terms/002/classes", "classes").await,
+        ["01", "03"]
+    );
+    assert_eq!(
+        ids(&app, "/terms/001/gradingPeriods", "academicSessions").await,
+        ["004"]
+    );
+    assert!(ids(&app, "/terms/002/gradingPeriods", "academicSessions")
+        .await
+        .is_empty());
+    assert_eq!(
+        ids(&app, "/courses/01/classes", "classes").await,
+        ["01", "02", "03"]
+    );
+    assert!(ids(&app, "/courses/02/classes", "classes").await.is_empty());
+    assert_eq!(ids(&app, "/schools/016/courses", "courses").await, ["01"]);
+    assert_eq!(ids(&app, "/schools/018/courses", "courses").await, ["02"]);
+    // the school year 002 of classes 01 and 03 is not a term
+    assert_eq!(
+        ids(&app, "/schools/016/terms", "academicSessions").await,
+        ["001"]
+    );
+    assert_eq!(
+        ids(&app, "/schools/017/classes/01/students", "users").await,
+        ["001"]
+    );
+    assert!(ids(&app, "/schools/017/classes/02/students", "users")
+        .await
+        .is_empty());
+}
+
+#[cfg(test)]
+#[async_std::test]
+async fn reads_single_subjects_and_periods() {
+    let mut app = test_app("subjects_periods").await;
+    app.at("/subjects/:id").get(get_subject);
+    app.at("/periods/:id").get(get_period);
+    put_samples(&app, |_, _| {}).await;
+
+    for (path, wrapper, collection, index) in [
+        ("/subjects/02", "subject", "subjects", 1),
+        ("/periods/02", "period", "periods", 1),
+    ] {
+        let (status, json) = respond(&app, get(path)).await;
+        assert_eq!(status, 200, "{}", path);
+        let sent = sample(collection)[collection][index].clone();
+        assert_eq!(written(json[wrapper].clone()), written(sent), "{}", path);
+    }
+    assert_eq!(respond(&app, get("/subjects/03")).await.0, 404);
+    assert_eq!(respond(&app, get("/periods/03")).await.0, 404);
+}
+
+#[cfg(test)]
+#[async_std::test]
+async fn puts_single_objects_only_at_their_sourced_id() {
+    let mut app = test_app("single_put").await;
+    app.at("/users/:id").get(get_user).put(put_user);
+    app.at("/classes/:id").get(get_class).put(put_class);
+    put_samples(&app, |_, _| {}).await;
+
+    let mut user = sample("users")["users"][0].clone();
+    user["givenName"] = json!("renamed");
+    let (status, json) = respond(&app, put("/users/002", &json!({ "user": user }))).await;
+    assert_eq!(status, 400);
+    assert!(
+        json.to_string().contains("sourcedId does not match"),
+        "{}",
+        json
+    );
+    let (_, json) = respond(&app, get("/users/002")).await;
+    assert_eq!(json["user"]["sourcedId"], "002");
+    let (_, json) = respond(&app, get("/users/001")).await;
+    assert_ne!(json["user"]["givenName"], "renamed");
+
+    let body = json!({ "user": user });
+    assert_eq!(
+        respond(&app, put("/users/001", &body)).await,
+        (200, json!(null))
+    );
+    let (_, json) = respond(&app, get("/users/001")).await;
+    assert_eq!(json["user"]["givenName"], "renamed");
+
+    let class = sample("classes")["classes"][2].clone();
+    let body = json!({ "class": class });
+    assert_eq!(respond(&app, put("/classes/01", &body)).await.0, 400);
+    let (_, json) = respond(&app, get("/classes/01")).await;
+    assert_eq!(json["class"]["title"], "S1Maths");
+}
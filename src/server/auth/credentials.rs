@@ -12,7 +12,7 @@ pub(crate) struct NewCreds {
 // TODO: write tests in rust
 pub(crate) async fn login(
     creds: server::Creds,
-    db: &sqlx::SqlitePool,
+    db: &db::Database,
     key: &jsonwebtoken::EncodingKey,
 ) -> Result<jwt::TokenReturn> {
     let compare = db::get_api_creds(&creds.client_id, db).await;
@@ -52,7 +52,7 @@ pub(crate) async fn verify_scopes(current: &String, requested: &String) -> Resul
         }
     }
     log::debug!("allowed scopes: {:?}", matches);
-    if matches.len() >= 1 {
+    if !matches.is_empty() {
         let m = matches.join(" ");
         return Ok(m);
     }
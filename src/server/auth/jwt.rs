@@ -36,7 +36,7 @@ pub(crate) async fn create_token(
         sub: id,
         scope: scope.clone(),
     };
-    let token = jsonwebtoken::encode(&header, &claims, &encode_key)?;
+    let token = jsonwebtoken::encode(&header, &claims, encode_key)?;
     log::debug!("creating token:\n{}", &token);
     let result = TokenReturn {
         access_token: token,
@@ -14,11 +14,17 @@ impl Jwt {
 
 #[tide::utils::async_trait]
 impl tide::Middleware<State> for Jwt {
-    async fn handle(&self, req: tide::Request<State>, next: tide::Next<'_, State>) -> tide::Result {
+    async fn handle(
+        &self,
+        mut req: tide::Request<State>,
+        next: tide::Next<'_, State>,
+    ) -> tide::Result {
         let token = parse_auth_header(&req)
             .and_then(|t| async { auth::jwt::decode_token(t, &req.state().decode_key).await })
             .await?;
         parse_permission(&self.scope, req.method(), &token.claims.scope).await?;
+        // made available to endpoints which vary their output by scope
+        req.set_ext(token.claims);
         Ok(next.run(req).await)
     }
 }
@@ -30,7 +36,7 @@ async fn parse_permission(
     target: &String,
 ) -> Result<()> {
     if let Some(method) = parse_method_permission(method).await {
-        parse_scope_permission(scopes, method, &target).await?;
+        parse_scope_permission(scopes, method, target).await?;
         log::debug!(
             "scope: {:?} does not meet requirements: {:?}, {:?}",
             target,
@@ -44,21 +50,25 @@ async fn parse_permission(
 
 /// converts endpoint methods into their relevant scope CRUD action
 async fn parse_method_permission<'a>(method: http_types::Method) -> Option<&'a str> {
-    let result = match method {
+    match method {
         Method::Get => Some("readonly"),
         Method::Put => Some("createput"),
         Method::Delete => Some("delete"),
         Method::Post => Some("create"),
         _ => None,
-    };
-    result
+    }
+}
+
+/// whether the space separated scope string holds the whole scope `permission`
+pub(crate) fn has_scope(scope: &str, permission: &str) -> bool {
+    scope.split_whitespace().any(|s| s == permission)
 }
 
 /// iterates a list of scopes to check if matching exists in scope string
 async fn parse_scope_permission(scopes: &Vec<String>, method: &str, target: &String) -> Result<()> {
     for scope in scopes {
         let permission = scope.clone() + "." + method;
-        if target.contains(&permission) {
+        if has_scope(target, &permission) {
             log::debug!("{:?} contains {:?}", target, &permission);
             return Ok(());
         }
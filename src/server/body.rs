@@ -0,0 +1,260 @@
+//! Request body reading
+//!
+//! Bodies are limited to the configured maximum size, counted after any content encoding is
+//! decoded, and validated against the schema of their model. Collection bodies such as
+//! { "users": [{}] } are parsed on a blocking thread as they arrive, passing each object on
+//! once read so an upload is never held in memory at once
+use crate::server::db::PutObject;
+use crate::server::{schema, Result, ServerError, State};
+use async_std::channel::{Receiver, Sender};
+use futures::{AsyncRead, AsyncReadExt};
+use jsonschema::Validator;
+use schemars::JsonSchema;
+use serde::de::{self, DeserializeOwned, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::{fmt, io};
+
+/// objects parsed ahead of the database
+const OBJECT_BUFFER: usize = 1000;
+
+/// rejects a body declaring a length over the limit before any of it is read
+fn check_length(req: &tide::Request<State>) -> Result<u64> {
+    let limit = req.state().max_body_size;
+    match req.len() {
+        Some(length) if length as u64 > limit => Err(ServerError::PayloadTooLarge(limit)),
+        _ => Ok(limit),
+    }
+}
+
+/// reads and parses a whole json body, rejecting it with every violation of its schema
+pub(super) async fn read<T>(req: &mut tide::Request<State>) -> Result<T>
+where
+    T: DeserializeOwned + JsonSchema + 'static,
+{
+    let limit = check_length(req)?;
+    let mut body = Vec::new();
+    req.take_body()
+        .take(limit + 1)
+        .read_to_end(&mut body)
+        .await
+        .map_err(|e| ServerError::InvalidBody(e.to_string()))?;
+    if body.len() as u64 > limit {
+        return Err(ServerError::PayloadTooLarge(limit));
+    }
+    let json: serde_json::Value = serde_json::from_slice(&body)?;
+    let violations = schema::violations(&*schema::validator::<T>()?, &json, "");
+    if !violations.is_empty() {
+        return Err(ServerError::SchemaViolations(violations));
+    }
+    Ok(serde_json::from_value(json)?)
+}
+
+/// parses the objects in the `wrapper` array of a collection body as it is read, each with
+/// the violations of the schema of `T`. Reading stops if the receiver is dropped, and any
+/// error is sent as the last item
+pub(super) fn objects<T: JsonSchema + 'static>(
+    req: &mut tide::Request<State>,
+    wrapper: &'static str,
+) -> Result<Receiver<Result<PutObject>>> {
+    let limit = check_length(req)?;
+    let validator = schema::validator::<T>()?;
+    let reader = LimitedReader {
+        inner: req.take_body(),
+        read: 0,
+        limit,
+    };
+    let (sender, receiver) = async_std::channel::bounded(OBJECT_BUFFER);
+    async_std::task::spawn_blocking(move || {
+        let mut reader = io::BufReader::new(reader);
+        let mut json = serde_json::Deserializer::from_reader(&mut reader);
+        let parsed = de::Deserializer::deserialize_map(
+            &mut json,
+            CollectionVisitor {
+                wrapper,
+                validator: &validator,
+                sender: &sender,
+            },
+        )
+        .and_then(|_| json.end());
+        if let Err(e) = parsed {
+            let error = if reader.get_ref().read > limit {
+                ServerError::PayloadTooLarge(limit)
+            } else if e.is_io() {
+                ServerError::InvalidBody(e.to_string())
+            } else {
+                ServerError::Json(e)
+            };
+            let _ = async_std::task::block_on(sender.send(Err(error)));
+        }
+    });
+    Ok(receiver)
+}
+
+/// Blocking reader over the async body for serde_json, which stops once over the limit
+struct LimitedReader<R> {
+    inner: R,
+    read: u64,
+    limit: u64,
+}
+
+impl<R: AsyncRead + Unpin> io::Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = async_std::task::block_on(self.inner.read(buf))?;
+        self.read += read as u64;
+        if self.read > self.limit {
+            return Err(io::Error::other("request body too large"));
+        }
+        Ok(read)
+    }
+}
+
+/// Visits the top level object of a collection body, other keys are ignored
+struct CollectionVisitor<'a> {
+    wrapper: &'static str,
+    validator: &'a Validator,
+    sender: &'a Sender<Result<PutObject>>,
+}
+
+impl<'de> Visitor<'de> for CollectionVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an object with a {} array", self.wrapper)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<(), A::Error> {
+        let mut found = false;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == self.wrapper && !found {
+                map.next_value_seed(ObjectsVisitor {
+                    wrapper: self.wrapper,
+                    validator: self.validator,
+                    sender: self.sender,
+                })?;
+                found = true;
+            } else if key == self.wrapper {
+                return Err(de::Error::duplicate_field(self.wrapper));
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        match found {
+            true => Ok(()),
+            false => Err(de::Error::missing_field(self.wrapper)),
+        }
+    }
+}
+
+/// Visits the array of objects, sending each as soon as it is parsed and validated
+struct ObjectsVisitor<'a> {
+    wrapper: &'static str,
+    validator: &'a Validator,
+    sender: &'a Sender<Result<PutObject>>,
+}
+
+impl<'de> de::DeserializeSeed<'de> for ObjectsVisitor<'_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ObjectsVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of objects")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        let mut index = 0;
+        while let Some(object) = seq.next_element::<serde_json::Value>()? {
+            let pointer = format!("/{}/{}", self.wrapper, index);
+            let violations = schema::violations(self.validator, &object, &pointer);
+            let object = PutObject { object, violations };
+            index += 1;
+            if async_std::task::block_on(self.sender.send(Ok(object))).is_err() {
+                // the upload was abandoned, e.g. a database error
+                return Err(de::Error::custom("upload cancelled"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model;
+    use std::io::Read;
+
+    fn limited(length: usize, limit: u64) -> io::Result<usize> {
+        let mut reader = LimitedReader {
+            inner: futures::io::Cursor::new(vec![b'x'; length]),
+            read: 0,
+            limit,
+        };
+        reader.read_to_end(&mut Vec::new())
+    }
+
+    #[test]
+    fn reads_up_to_the_limit() {
+        assert_eq!(limited(10, 10).unwrap(), 10);
+        assert!(limited(11, 10).is_err());
+        assert_eq!(limited(0, 0).unwrap(), 0);
+    }
+
+    /// counts the objects of a PUT orgs body, the test state allows 4096 bytes
+    async fn app() -> tide::Server<State> {
+        let mut app = tide::with_state(crate::server::test_state("body").await);
+        app.with(tide::utils::After(
+            crate::server::errors::middleware::ApiError::new(),
+        ));
+        app.at("/orgs")
+            .put(|mut req: tide::Request<State>| async move {
+                let objects = objects::<model::Org>(&mut req, "orgs")?;
+                let mut count = 0;
+                while let Ok(object) = objects.recv().await {
+                    object?;
+                    count += 1;
+                }
+                Ok(count.to_string())
+            });
+        app
+    }
+
+    fn orgs(count: usize) -> String {
+        let content = std::fs::read_to_string("./sample/orgs.json").unwrap();
+        let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        let org = &json["orgs"][2];
+        serde_json::json!({ "orgs": vec![org; count] }).to_string()
+    }
+
+    #[async_std::test]
+    async fn limits_the_body_read() {
+        let app = app().await;
+        let put = |body: String, length: bool| {
+            let mut req = http_types::Request::put("https://localhost/orgs");
+            let reader = futures::io::Cursor::new(body.clone().into_bytes());
+            let length = length.then_some(body.len());
+            req.set_body(http_types::Body::from_reader(reader, length));
+            req
+        };
+        let fits = orgs(4);
+        assert!(fits.len() <= 4096);
+        let mut res: http_types::Response = app.respond(put(fits, false)).await.unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.body_string().await.unwrap(), "4");
+
+        let large = orgs(40);
+        assert!(large.len() > 4096);
+        // rejected from its length before being read, or once over the limit when not given
+        for length in [true, false] {
+            let res: http_types::Response = app.respond(put(large.clone(), length)).await.unwrap();
+            assert_eq!(res.status(), 413);
+        }
+    }
+}
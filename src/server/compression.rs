@@ -0,0 +1,228 @@
+//! Content encoding of request and response bodies
+//!
+//! Request bodies sent with a gzip or brotli Content-Encoding are decoded before reaching
+//! the endpoints. Responses are encoded with the best encoding offered in Accept-Encoding,
+//! streaming through the encoder rather than buffering the compressed body
+use crate::server::{ServerError, State};
+use async_compression::futures::bufread::{BrotliDecoder, BrotliEncoder, GzipDecoder, GzipEncoder};
+use async_compression::Level;
+use futures::io::BufReader;
+use http_types::conditional::ETag;
+use http_types::content::{ContentEncoding, Encoding};
+use http_types::headers::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, VARY};
+use http_types::{Body, Method};
+
+/// bodies smaller than this are not worth the encoding overhead
+const MIN_LENGTH: usize = 1024;
+/// brotli's default quality is too slow for large collections
+const BROTLI_QUALITY: i32 = 4;
+
+pub(crate) struct Compression {}
+
+impl Compression {
+    pub(crate) fn new() -> Self {
+        Self {}
+    }
+}
+
+#[tide::utils::async_trait]
+impl tide::Middleware<State> for Compression {
+    async fn handle(
+        &self,
+        mut req: tide::Request<State>,
+        next: tide::Next<'_, State>,
+    ) -> tide::Result {
+        decode_request(&mut req)?;
+        let encoding = negotiate(&req);
+        let head = req.method() == Method::Head;
+
+        let mut res = next.run(req).await;
+        res.append_header(VARY, "Accept-Encoding");
+        let skip = head
+            || res.error().is_some()
+            || matches!(res.status() as u16, 204 | 304)
+            || res.header(CONTENT_ENCODING).is_some()
+            || res.len().is_some_and(|l| l < MIN_LENGTH);
+        let (body, encoding) = match encoding {
+            Some(Encoding::Brotli) if !skip => {
+                let encoder =
+                    BrotliEncoder::with_quality(res.take_body(), Level::Precise(BROTLI_QUALITY));
+                (
+                    Body::from_reader(BufReader::new(encoder), None),
+                    Encoding::Brotli,
+                )
+            }
+            Some(Encoding::Gzip) if !skip => {
+                let encoder = GzipEncoder::new(res.take_body());
+                (
+                    Body::from_reader(BufReader::new(encoder), None),
+                    Encoding::Gzip,
+                )
+            }
+            _ => return Ok(res),
+        };
+        res.set_body(body);
+        res.remove_header(CONTENT_LENGTH);
+        ContentEncoding::new(encoding).apply(&mut res);
+        // the encoded body differs byte for byte so is only weakly equivalent
+        if let Ok(Some(ETag::Strong(tag))) = ETag::from_headers(&res) {
+            ETag::new_weak(tag).apply(&mut res);
+        }
+        Ok(res)
+    }
+}
+
+/// picks the supported encoding with the highest q value in Accept-Encoding, preferring
+/// brotli on a tie. Encodings not listed take the q value of any wildcard
+fn negotiate(req: &tide::Request<State>) -> Option<Encoding> {
+    let header = req.header(ACCEPT_ENCODING)?;
+    let proposals: Vec<(String, f32)> = header
+        .iter()
+        .flat_map(|v| v.as_str().split(','))
+        .map(|proposal| {
+            let mut parts = proposal.split(';');
+            let name = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (name, q)
+        })
+        .collect();
+    let q = |names: &[&str]| {
+        let find = |names: &[&str]| {
+            proposals
+                .iter()
+                .find(|(n, _)| names.contains(&n.as_str()))
+                .map(|(_, q)| *q)
+        };
+        find(names).or_else(|| find(&["*"])).unwrap_or(0.0)
+    };
+    let (brotli, gzip) = (q(&["br"]), q(&["gzip", "x-gzip"]));
+    if brotli > 0.0 && brotli >= gzip {
+        Some(Encoding::Brotli)
+    } else if gzip > 0.0 {
+        Some(Encoding::Gzip)
+    } else {
+        None
+    }
+}
+
+/// replaces an encoded request body with a decoding reader
+fn decode_request(req: &mut tide::Request<State>) -> Result<(), ServerError> {
+    let encoding = match req.header(CONTENT_ENCODING) {
+        Some(e) => e.as_str().trim().to_ascii_lowercase(),
+        None => return Ok(()),
+    };
+    let decoded = match encoding.as_str() {
+        "identity" => return Ok(()),
+        "gzip" | "x-gzip" => {
+            Body::from_reader(BufReader::new(GzipDecoder::new(req.take_body())), None)
+        }
+        "br" => Body::from_reader(BufReader::new(BrotliDecoder::new(req.take_body())), None),
+        _ => return Err(ServerError::UnsupportedContentEncoding(encoding)),
+    };
+    req.set_body(decoded);
+    req.remove_header(CONTENT_ENCODING);
+    req.remove_header(CONTENT_LENGTH);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::AsyncReadExt;
+
+    async fn app(name: &str) -> tide::Server<State> {
+        let mut app = tide::with_state(crate::server::test_state(name).await);
+        app.with(tide::utils::After(
+            crate::server::errors::middleware::ApiError::new(),
+        ));
+        app.with(Compression::new());
+        app.at("/large").get(|_| async {
+            let mut res = tide::Response::new(200);
+            res.set_body("x".repeat(MIN_LENGTH));
+            ETag::new("large".to_string()).apply(&mut res);
+            Ok(res)
+        });
+        app.at("/small").get(|_| async { Ok("x") });
+        app.at("/echo")
+            .put(|mut req: tide::Request<State>| async move { req.body_string().await });
+        app
+    }
+
+    async fn encoding(app: &tide::Server<State>, path: &str, accept: &str) -> Option<String> {
+        let mut req = http_types::Request::get(format!("https://localhost{}", path).as_str());
+        req.insert_header(ACCEPT_ENCODING, accept);
+        let res: http_types::Response = app.respond(req).await.unwrap();
+        res.header(CONTENT_ENCODING).map(|e| e.as_str().to_string())
+    }
+
+    #[async_std::test]
+    async fn negotiates_the_preferred_encoding() {
+        let app = app("negotiate").await;
+        let large = |accept| encoding(&app, "/large", accept);
+        assert_eq!(large("gzip").await.as_deref(), Some("gzip"));
+        assert_eq!(large("gzip, br").await.as_deref(), Some("br"));
+        assert_eq!(large("br;q=0.5, gzip").await.as_deref(), Some("gzip"));
+        assert_eq!(large("gzip;q=0, *;q=0.1").await.as_deref(), Some("br"));
+        assert_eq!(large("br;q=0, x-gzip").await.as_deref(), Some("gzip"));
+        assert_eq!(large("identity").await, None);
+        assert_eq!(large("*;q=0").await, None);
+        assert_eq!(encoding(&app, "/small", "gzip").await, None);
+    }
+
+    #[async_std::test]
+    async fn encodes_responses_with_a_weak_etag() {
+        let app = app("encode").await;
+        let mut req = http_types::Request::get("https://localhost/large");
+        req.insert_header(ACCEPT_ENCODING, "gzip");
+        let mut res: http_types::Response = app.respond(req).await.unwrap();
+        assert_eq!(
+            ETag::from_headers(&res).unwrap(),
+            Some(ETag::new_weak("large".into()))
+        );
+        assert!(res.header(CONTENT_LENGTH).is_none());
+        let encoded = res.take_body().into_bytes().await.unwrap();
+        let mut decoded = String::new();
+        GzipDecoder::new(encoded.as_slice())
+            .read_to_string(&mut decoded)
+            .await
+            .unwrap();
+        assert_eq!(decoded, "x".repeat(MIN_LENGTH));
+    }
+
+    #[async_std::test]
+    async fn decodes_request_bodies() {
+        let app = app("decode").await;
+        let sent = "{\"users\":[]}".repeat(100);
+        let mut gzip = Vec::new();
+        GzipEncoder::new(sent.as_bytes())
+            .read_to_end(&mut gzip)
+            .await
+            .unwrap();
+        let mut brotli = Vec::new();
+        BrotliEncoder::new(sent.as_bytes())
+            .read_to_end(&mut brotli)
+            .await
+            .unwrap();
+        for (encoding, body) in [
+            ("gzip", gzip),
+            ("br", brotli),
+            ("identity", sent.clone().into()),
+        ] {
+            let mut req = http_types::Request::put("https://localhost/echo");
+            req.insert_header(CONTENT_ENCODING, encoding);
+            req.set_body(body);
+            let mut res: http_types::Response = app.respond(req).await.unwrap();
+            assert_eq!(res.status(), 200, "{}", encoding);
+            assert_eq!(res.body_string().await.unwrap(), sent, "{}", encoding);
+        }
+
+        let mut req = http_types::Request::put("https://localhost/echo");
+        req.insert_header(CONTENT_ENCODING, "compress");
+        req.set_body(sent);
+        let res: http_types::Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), 415);
+    }
+}
@@ -0,0 +1,253 @@
+//! Conditional GET support
+//!
+//! Successful GET responses are given a strong ETag from a hash of the body and a
+//! Last-Modified from the newest dateLastModified of the objects returned, requests
+//! with a matching If-None-Match or If-Modified-Since receive a 304 with no body.
+//! Streamed collections set their own validators, see [`super::stream`]
+use crate::server::State;
+use http_types::conditional::{ETag, IfModifiedSince, IfNoneMatch, LastModified};
+use http_types::Method;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub(crate) struct ConditionalGet {}
+
+impl ConditionalGet {
+    pub(crate) fn new() -> Self {
+        Self {}
+    }
+}
+
+#[tide::utils::async_trait]
+impl tide::Middleware<State> for ConditionalGet {
+    async fn handle(&self, req: tide::Request<State>, next: tide::Next<'_, State>) -> tide::Result {
+        if req.method() != Method::Get {
+            return Ok(next.run(req).await);
+        }
+        let conditions = Conditions::from_request(&req);
+
+        let mut res = next.run(req).await;
+        // streamed bodies are not buffered to be hashed
+        let streamed = res.len().is_none() || res.header("ETag").is_some();
+        if res.status() != 200 || res.error().is_some() || streamed {
+            return Ok(res);
+        }
+        let body = res.take_body().into_bytes().await?;
+        let mut builder = ValidatorBuilder::new();
+        builder.update(&body);
+        for date in dates(&body) {
+            builder.modified(&date);
+        }
+        let validators = builder.finish();
+
+        if validators.not_modified(&conditions) {
+            res = tide::Response::new(304);
+        } else {
+            res.set_body(body);
+        }
+        validators.apply(&mut res);
+        Ok(res)
+    }
+}
+
+/// The conditional headers of a request, malformed headers are ignored as if they were
+/// not sent
+pub(super) struct Conditions {
+    if_none_match: Option<IfNoneMatch>,
+    if_modified_since: Option<IfModifiedSince>,
+}
+
+impl Conditions {
+    pub(super) fn from_request(req: &tide::Request<State>) -> Self {
+        Self {
+            if_none_match: IfNoneMatch::from_headers(req).ok().flatten(),
+            if_modified_since: IfModifiedSince::from_headers(req).ok().flatten(),
+        }
+    }
+}
+
+/// Builds the validators of a response body given in one or more parts
+pub(super) struct ValidatorBuilder {
+    hasher: openssl::sha::Sha256,
+    last_modified: Option<SystemTime>,
+}
+
+impl ValidatorBuilder {
+    pub(super) fn new() -> Self {
+        Self {
+            hasher: openssl::sha::Sha256::new(),
+            last_modified: None,
+        }
+    }
+
+    /// adds the next part of the body to the hash
+    pub(super) fn update(&mut self, part: &[u8]) {
+        self.hasher.update(part);
+    }
+
+    /// records the dateLastModified of an object in the body, truncated to whole seconds
+    /// as used by HTTP dates
+    pub(super) fn modified(&mut self, date: &str) {
+        if let Ok(date) = chrono::DateTime::parse_from_rfc3339(date) {
+            let date = UNIX_EPOCH + Duration::from_secs(date.timestamp().max(0) as u64);
+            self.last_modified = self.last_modified.max(Some(date));
+        }
+    }
+
+    pub(super) fn finish(self) -> Validators {
+        let hash = self.hasher.finish();
+        let hex: String = hash[..16].iter().map(|b| format!("{:02x}", b)).collect();
+        Validators {
+            etag: ETag::new(hex),
+            last_modified: self.last_modified,
+        }
+    }
+}
+
+/// The ETag and Last-Modified of a response body
+pub(super) struct Validators {
+    etag: ETag,
+    last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    /// whether the client's copy is current, If-Modified-Since is only used without
+    /// If-None-Match, which uses the weak comparison
+    pub(super) fn not_modified(&self, conditions: &Conditions) -> bool {
+        match (
+            &conditions.if_none_match,
+            &conditions.if_modified_since,
+            self.last_modified,
+        ) {
+            (Some(inm), _, _) => inm.wildcard() || inm.iter().any(|e| matches(e, &self.etag)),
+            (None, Some(ims), Some(modified)) => modified <= ims.modified(),
+            _ => false,
+        }
+    }
+
+    pub(super) fn apply(&self, res: &mut tide::Response) {
+        self.etag.apply(&mut *res);
+        if let Some(modified) = self.last_modified {
+            LastModified::new(modified).apply(res);
+        }
+    }
+}
+
+/// If-None-Match uses the weak comparison, ignoring whether either tag is weak
+fn matches(tag: &ETag, etag: &ETag) -> bool {
+    let opaque = |t: &ETag| match t {
+        ETag::Strong(s) | ETag::Weak(s) => s.clone(),
+    };
+    opaque(tag) == opaque(etag)
+}
+
+/// the dateLastModified of the objects in a collection or single object response
+fn dates(body: &[u8]) -> Vec<String> {
+    let json: serde_json::Value = match serde_json::from_slice(body) {
+        Ok(json) => json,
+        Err(_) => return Vec::new(),
+    };
+    let objects = match json.as_object() {
+        Some(objects) => objects,
+        None => return Vec::new(),
+    };
+    objects
+        .values()
+        .flat_map(|v| match v {
+            serde_json::Value::Array(objects) => objects.iter().collect(),
+            object => vec![object],
+        })
+        .filter_map(|o| o.get("dateLastModified")?.as_str())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validators(body: &str, modified: Option<&str>) -> Validators {
+        let mut builder = ValidatorBuilder::new();
+        builder.update(body.as_bytes());
+        if let Some(date) = modified {
+            builder.modified(date);
+        }
+        builder.finish()
+    }
+
+    fn conditions(etags: Vec<ETag>, since: Option<SystemTime>) -> Conditions {
+        let mut if_none_match = IfNoneMatch::new();
+        let sent = !etags.is_empty();
+        for etag in etags {
+            if_none_match.push(etag);
+        }
+        Conditions {
+            if_none_match: sent.then_some(if_none_match),
+            if_modified_since: since.map(IfModifiedSince::new),
+        }
+    }
+
+    fn opaque(etag: &ETag) -> String {
+        match etag {
+            ETag::Strong(s) | ETag::Weak(s) => s.clone(),
+        }
+    }
+
+    #[test]
+    fn matches_etags_weakly() {
+        let current = validators("{}", None);
+        let weak = ETag::new_weak(opaque(&current.etag));
+        assert!(current.not_modified(&conditions(vec![current.etag.clone()], None)));
+        assert!(current.not_modified(&conditions(vec![weak], None)));
+        let other = validators("[]", None).etag;
+        assert!(!current.not_modified(&conditions(vec![other.clone()], None)));
+        assert!(current.not_modified(&conditions(vec![other, current.etag.clone()], None)));
+        assert!(!current.not_modified(&conditions(vec![], None)));
+    }
+
+    #[test]
+    fn only_uses_if_modified_since_without_if_none_match() {
+        let current = validators("{}", Some("2021-01-01T00:00:00.900Z"));
+        let modified = UNIX_EPOCH + Duration::from_secs(1609459200);
+        let earlier = modified - Duration::from_secs(1);
+        // dates are truncated to whole seconds
+        assert!(current.not_modified(&conditions(vec![], Some(modified))));
+        assert!(!current.not_modified(&conditions(vec![], Some(earlier))));
+        let other = validators("[]", None).etag;
+        assert!(!current.not_modified(&conditions(vec![other], Some(modified))));
+        let undated = validators("{}", None);
+        assert!(!undated.not_modified(&conditions(vec![], Some(modified))));
+    }
+
+    #[async_std::test]
+    async fn responds_not_modified_without_a_body() {
+        let mut app = tide::with_state(crate::server::test_state("conditional").await);
+        app.with(ConditionalGet::new());
+        app.at("/").get(|_| async {
+            tide::Body::from_json(&serde_json::json!({
+                "user": { "dateLastModified": "2021-01-01T00:00:00.000Z" }
+            }))
+        });
+        let get = || http_types::Request::get("https://localhost/");
+
+        let res: http_types::Response = app.respond(get()).await.unwrap();
+        assert_eq!(res.status(), 200);
+        let etag = ETag::from_headers(&res).unwrap().unwrap();
+        assert!(etag.is_strong());
+        assert_eq!(
+            res.header("Last-Modified").unwrap(),
+            "Fri, 01 Jan 2021 00:00:00 GMT"
+        );
+
+        let mut req = get();
+        req.insert_header("If-None-Match", etag.value());
+        let mut res: http_types::Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), 304);
+        assert!(res.body_string().await.unwrap().is_empty());
+        assert_eq!(ETag::from_headers(&res).unwrap(), Some(etag));
+
+        let mut req = get();
+        req.insert_header("If-Modified-Since", "Thu, 31 Dec 2020 23:59:59 GMT");
+        let res: http_types::Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), 200);
+    }
+}
@@ -1,9 +1,75 @@
+mod postgres;
+mod sqlite;
+
 use crate::model;
+use crate::server::params::{self, Bind, Dialect, Parameters};
 use crate::server::{auth, Result, ServerError};
-use sqlite::SqlitePoolOptions;
-use sqlx::{migrate::MigrateDatabase, sqlite};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use sqlx::Arguments;
 use tide::prelude::*;
 
+/// The database shared by every request, SQLite or PostgreSQL
+pub(crate) type Database = std::sync::Arc<dyn Store>;
+
+/// Persistence behind the API. The collection, by id, delete and purge queries are shared and
+/// written against the json views and entity tables both schemas define, with `?` parameters
+/// and any differences given by the [`Dialect`]. Writing through the views is left to each
+/// database as the staging of a PUT differs
+#[tide::utils::async_trait]
+pub(crate) trait Store: Send + Sync {
+    fn dialect(&self) -> Dialect;
+
+    /// applies each pending schema migration in order
+    async fn migrate(&self) -> Result<()>;
+
+    async fn close(&self);
+
+    /// the hashed secret and space separated scopes of an API user
+    async fn api_creds(&self, client_id: &str) -> Result<Option<super::Creds>>;
+
+    async fn api_users(&self) -> Result<Vec<UserList>>;
+
+    /// adds an API user with the scopes which exist, within a transaction
+    async fn insert_api_user(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        tag: &str,
+        scopes: &[&str],
+    ) -> Result<()>;
+
+    /// the number of API users deleted
+    async fn delete_api_user(&self, client_id: &str) -> Result<u64>;
+
+    /// runs a query returning at most one json object as text
+    async fn object(&self, query: &str, binds: &[Bind]) -> Result<Option<String>>;
+
+    /// begins a read only transaction which sees the database as it is at its first query
+    async fn snapshot(&self) -> Result<Box<dyn Snapshot>>;
+
+    /// runs a statement returning the number of rows affected
+    async fn execute(&self, query: &str, binds: &[Bind]) -> Result<u64>;
+
+    /// runs each statement in order within a transaction, returning the rows each affected.
+    /// When the `guard` count query run first in the transaction counts any rows, nothing is
+    /// run and None is returned
+    async fn transaction(
+        &self,
+        guard: Option<&(String, Vec<Bind>)>,
+        statements: &[(String, Vec<Bind>)],
+    ) -> Result<Option<Vec<u64>>>;
+
+    /// stages batches of `{ sourcedId, data }` objects, or `{ sourcedId, reason }` for those
+    /// already rejected, as they are received. Their references are then checked and the
+    /// valid objects upserted through the json view trigger within a single transaction
+    async fn put(
+        &self,
+        batches: BoxStream<'_, Result<Vec<serde_json::Value>>>,
+        target: &PutTarget,
+    ) -> Result<Written>;
+}
+
 #[derive(Serialize)]
 pub(super) struct UserList {
     tag: String,
@@ -11,59 +77,15 @@ pub(super) struct UserList {
     scope: String,
 }
 
-pub(super) async fn get_api_creds(
-    client_id: &String,
-    db: &sqlx::SqlitePool,
-) -> Result<super::Creds> {
-    let res = sqlx::query_as!(
-        super::Creds,
-        r#"
-        SELECT
-            c.client_id
-            , c.client_secret
-            , group_concat(s.scope,' ') AS "scope!: String"
-        FROM
-            credentials c
-            INNER JOIN credential_scopes cs ON c.id = cs.credential_id
-            INNER JOIN scopes s ON cs.scope_id = s.id
-        WHERE
-            c.client_id = ?
-            AND scope IS NOT NULL
-        GROUP BY 
-            c.client_id
-        "#,
-        client_id
-    )
-    .fetch_optional(db)
-    .await?;
-    if let Some(user) = res {
-        return Ok(user);
-    }
-    Err(ServerError::InvalidLogin)
-}
-
-pub(super) async fn get_api_users(db: &sqlx::SqlitePool) -> Result<Vec<UserList>> {
-    let rows = sqlx::query_as!(
-        UserList,
-        r#"
-        SELECT
-            c.client_id
-            , c.tag
-            , group_concat(s.scope,' ') AS "scope!: String"
-        FROM
-            credentials c
-            INNER JOIN credential_scopes cs ON c.id = cs.credential_id
-            INNER JOIN scopes s ON cs.scope_id = s.id
-        WHERE
-            scope IS NOT NULL
-        GROUP BY 
-            c.client_id
-        "#,
-    )
-    .fetch_all(db)
-    .await?;
+pub(super) async fn get_api_creds(client_id: &str, db: &Database) -> Result<super::Creds> {
+    match db.api_creds(client_id).await? {
+        Some(user) => Ok(user),
+        None => Err(ServerError::InvalidLogin),
+    }
+}
 
-    Ok(rows)
+pub(super) async fn get_api_users(db: &Database) -> Result<Vec<UserList>> {
+    db.api_users().await
 }
 
 #[derive(Deserialize)]
@@ -72,33 +94,11 @@ pub(super) struct CreateApiUser {
     scope: String,
 }
 
-pub(super) async fn create_api_user(
-    user: CreateApiUser,
-    db: &sqlx::SqlitePool,
-) -> Result<super::Creds> {
+pub(super) async fn create_api_user(user: CreateApiUser, db: &Database) -> Result<super::Creds> {
     let new = auth::credentials::generate_credentials().await?;
-    let mut t = db.begin().await?;
-    sqlx::query!(
-        "INSERT INTO credentials(client_id, client_secret, tag) VALUES (?, ?, ?)",
-        new.creds.client_id,
-        new.encrypt,
-        user.tag,
-    )
-    .execute(&mut *t)
-    .await?;
-    for scope in user.scope.split(' ') {
-        sqlx::query!(
-            "INSERT OR IGNORE INTO credential_scopes (credential_id, scope_id) VALUES (
-            (SELECT id FROM credentials WHERE client_id = ?),
-            (SELECT id FROM scopes WHERE scope = ?)
-            )",
-            new.creds.client_id,
-            scope,
-        )
-        .execute(&mut *t)
+    let scopes: Vec<&str> = user.scope.split(' ').collect();
+    db.insert_api_user(&new.creds.client_id, &new.encrypt, &user.tag, &scopes)
         .await?;
-    }
-    t.commit().await?;
     let authscopes = get_api_creds(&new.creds.client_id, db).await?;
     let out = super::Creds {
         client_id: new.creds.client_id.clone(),
@@ -108,11 +108,8 @@ pub(super) async fn create_api_user(
     Ok(out)
 }
 
-pub(super) async fn delete_api_user(uuid: &str, db: &sqlx::SqlitePool) -> Result<()> {
-    let deleted = sqlx::query!("DELETE FROM credentials WHERE client_id = ?", uuid)
-        .execute(db)
-        .await?
-        .rows_affected();
+pub(super) async fn delete_api_user(uuid: &str, db: &Database) -> Result<()> {
+    let deleted = db.delete_api_user(uuid).await?;
 
     if deleted > 0 {
         return Ok(());
@@ -120,116 +117,479 @@ pub(super) async fn delete_api_user(uuid: &str, db: &sqlx::SqlitePool) -> Result
     Err(ServerError::NoRecordDeleted)
 }
 
-/// Creates a database call function to the relevant json array object view
+/// Binds the parameters of a shared query as the arguments of either database
+fn arguments<'q, DB>(binds: &'q [Bind]) -> Result<<DB as sqlx::Database>::Arguments<'q>>
+where
+    DB: sqlx::Database,
+    &'q str: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    f64: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    i64: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+{
+    let mut arguments = <DB as sqlx::Database>::Arguments::default();
+    for bind in binds {
+        match bind {
+            Bind::Text(text) => arguments.add(text.as_str()),
+            Bind::Number(number) => arguments.add(*number),
+            Bind::Integer(integer) => arguments.add(*integer),
+        }
+        .map_err(sqlx::Error::Encode)?;
+    }
+    Ok(arguments)
+}
+
+/// A read of the database as it was at its first query, ended when dropped. A page is
+/// read through one so the objects sent are those counted, whatever is written meanwhile
+#[tide::utils::async_trait]
+pub(crate) trait Snapshot: Send {
+    /// runs a query returning a single count
+    async fn count(&mut self, query: &str, binds: &[Bind]) -> Result<i64>;
+
+    /// runs a query returning the `key`, dateLastModified and total count of each row of a
+    /// page, in order
+    async fn keys(&mut self, query: &str, binds: &[Bind]) -> Result<Vec<Key>>;
+
+    /// runs a query returning each json object as text alongside its key
+    async fn objects(&mut self, query: &str, binds: &[Bind]) -> Result<Vec<(String, String)>>;
+}
+
+/// rows read ahead of the client when streaming a collection
+const ROW_BUFFER: usize = 64;
+
+/// objects read at a time when streaming a page
+const CHUNK: usize = 256;
+
+/// A row of a page, read before its object. The total is the count of every row matching
+/// the query, counted in the same statement
+pub(crate) type Key = (String, Option<String>, i64);
+
+/// A page of a collection and the total matching count. The keys and dateLastModified of
+/// the page are read up front in one statement, the objects are only read by key in
+/// chunks as they are sent, so a page is never held in memory at once. Both are read in
+/// one snapshot, so the objects sent are those the total and ETag were taken from
+pub(crate) struct Collection<T> {
+    pub(crate) total: u32,
+    snapshot: Box<dyn Snapshot>,
+    keys: Vec<(String, Option<String>)>,
+    /// query returning the `key` and `object` of rows, ending where the keys to read are
+    /// given as `IN (..)`
+    lookup: String,
+    binds: Vec<Bind>,
+    object: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: serde::de::DeserializeOwned + Send + 'static> Collection<T> {
+    /// Reads the keys of a page with `keys` then, if it is past the end of the results,
+    /// the total with `count`. Both take `binds`, `keys` followed by `page_binds`. The
+    /// objects are later read with `lookup` and `lookup_binds` followed by their keys
+    async fn read(
+        db: &Database,
+        keys: &str,
+        count: &str,
+        binds: &[Bind],
+        page_binds: Vec<Bind>,
+        lookup: String,
+        lookup_binds: Vec<Bind>,
+    ) -> Result<Self> {
+        let mut snapshot = db.snapshot().await?;
+        let page: Vec<Bind> = binds.iter().cloned().chain(page_binds).collect();
+        let page = snapshot.keys(keys, &page).await?;
+        let total = match page.first() {
+            Some((.., total)) => *total,
+            None => snapshot.count(count, binds).await?,
+        };
+        Ok(Collection {
+            total: total as u32,
+            snapshot,
+            keys: page.into_iter().map(|(key, date, _)| (key, date)).collect(),
+            lookup,
+            binds: lookup_binds,
+            object: std::marker::PhantomData,
+        })
+    }
+
+    /// the key and dateLastModified of each object of the page, in order
+    pub(crate) fn keys(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.keys
+            .iter()
+            .map(|(key, date)| (key.as_str(), date.as_deref()))
+    }
+
+    /// reads the objects of the page in the background, sending each as it is read. The
+    /// channel is bounded so reading is held back while the reader is slower, the snapshot
+    /// is held until the page has been read
+    pub(crate) fn rows(self) -> async_std::channel::Receiver<Result<T>> {
+        let (sender, receiver) = async_std::channel::bounded(ROW_BUFFER);
+        let mut snapshot = self.snapshot;
+        async_std::task::spawn(async move {
+            for chunk in self.keys.chunks(CHUNK) {
+                let query = format!("{} ({})", self.lookup, vec!["?"; chunk.len()].join(", "));
+                let mut binds = self.binds.clone();
+                binds.extend(chunk.iter().map(|(key, _)| Bind::Text(key.clone())));
+                let mut objects: std::collections::HashMap<String, String> =
+                    match snapshot.objects(&query, &binds).await {
+                        Ok(objects) => objects.into_iter().collect(),
+                        Err(e) => {
+                            let _ = sender.send(Err(e)).await;
+                            return;
+                        }
+                    };
+                for (key, _) in chunk {
+                    let Some(data) = objects.remove(key) else {
+                        continue;
+                    };
+                    let object = serde_json::from_str(&data).map_err(ServerError::from);
+                    // the reader has gone, e.g. the client disconnected
+                    if sender.send(object).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        receiver
+    }
+}
+
+/// Reads a page of a collection with the request parameters applied
+/// $source is a query returning one json object per row in a `data` column alongside its
+/// `sourcedId`, its parameters are bound from ids in order. The entity table is named
+/// after the wrapper e.g. `users` is `Users`. Returns the total matching count with the
+/// objects of the page ready to be read
+async fn get_collection<T: serde::de::DeserializeOwned + Send + 'static>(
+    db: &Database,
+    source: &str,
+    wrapper: &str,
+    ids: &[&str],
+    params: &Parameters,
+) -> Result<Collection<T>> {
+    let table = format!("{}{}", wrapper[..1].to_uppercase(), &wrapper[1..]);
+    let dialect = db.dialect();
+    let clauses = params::sql_clauses::<T>(params, &table, dialect).await?;
+
+    let count = format!(
+        "SELECT count(*) FROM ({}) AS source WHERE {}",
+        source, clauses.filter
+    );
+    let keys = format!(
+        "SELECT sourcedId, {}, count(*) OVER () FROM ({}) AS source WHERE {} \
+        ORDER BY {} LIMIT ? OFFSET ?",
+        dialect.date_last_modified(),
+        source,
+        clauses.filter,
+        clauses.order
+    );
+    let lookup = format!(
+        "SELECT sourcedId, {} FROM ({}) AS source WHERE sourcedId IN",
+        dialect.text("data"),
+        source,
+    );
+    let ids: Vec<Bind> = ids.iter().map(|id| Bind::Text(id.to_string())).collect();
+    let mut binds = ids.clone();
+    binds.extend(clauses.filter_binds);
+    let mut page_binds = clauses.order_binds;
+    page_binds.push(Bind::Integer(params.limit.into()));
+    page_binds.push(Bind::Integer(params.offset.into()));
+    Collection::read(db, &keys, &count, &binds, page_binds, lookup, ids).await
+}
+
+/// Creates a database call function for a collection with the request parameters applied
 /// $name is the name of the function mirroring the HTTP API get request
-/// $data is the json array struct to serialize to
-/// $query is the SQL query to the relevant view
-/// $object is the json object contained in the $data struct
+/// $data is the struct each object of the collection is read into
+/// $wrapper is the top level json object name of the collection
+/// $query returns one json object per row in a `data` column alongside its `sourcedId`,
+/// taking each $id as a parameter
 macro_rules! create_get_db {
-    ($name:ident, $data:ty, $query:literal, $object:ident) => {
-        pub(crate) async fn $name(db: &sqlx::SqlitePool) -> Result<$data> {
-            let row = sqlx::query!($query).fetch_one(db).await?;
-            if let Some(data) = row.$object {
-                let output: $data = serde_json::from_str(&data)?;
-                if output.$object.len() >= 1 {
-                    return Ok(output);
-                }
+    ($name:ident, $data:ty, $wrapper:literal, $query:expr $(, $id:ident)*) => {
+        pub(crate) async fn $name(
+            db: &Database,
+            $($id: &str,)*
+            params: &Parameters,
+        ) -> Result<Collection<$data>> {
+            let ids: &[&str] = &[$($id),*];
+            let collection = get_collection(db, $query, $wrapper, ids, params).await?;
+            // an empty top level collection has no content, as opposed to
+            // a filter or parent object which matched nothing
+            if collection.total == 0 && ids.is_empty() && params.filter.is_none() {
+                return Err(ServerError::NoContent);
             }
-            Err(ServerError::NoContent)
+            Ok(collection)
         }
     };
 }
 
-create_get_db!(
-    get_all_classes,
-    model::Classes,
-    "SELECT classes FROM ClassesJsonArray",
-    classes
-);
+// The json column of each view is named after its object type and quoted, as `user` is
+// reserved in postgres. Kinds of object and relationships are found through the entity
+// and link tables rather than the json, so the same queries run on either database
+
+const ACADEMIC_SESSIONS_JSON: &str =
+    r#"SELECT "academicSession" AS data, sourcedId FROM AcademicSessionsJson"#;
+const GRADING_PERIODS_JSON: &str = r#"
+    SELECT "academicSession" AS data, sourcedId
+    FROM AcademicSessionsJson
+    WHERE sourcedId IN (
+        SELECT AcademicSessions.sourcedId
+        FROM AcademicSessions
+            INNER JOIN SessionType ON AcademicSessions.sessionTypeId = SessionType.id
+        WHERE SessionType.token = 'gradingPeriod'
+    )
+    "#;
+const TERMS_JSON: &str = r#"
+    SELECT "academicSession" AS data, sourcedId
+    FROM AcademicSessionsJson
+    WHERE sourcedId IN (
+        SELECT AcademicSessions.sourcedId
+        FROM AcademicSessions
+            INNER JOIN SessionType ON AcademicSessions.sessionTypeId = SessionType.id
+        WHERE SessionType.token = 'term'
+    )
+    "#;
+const CLASSES_JSON: &str = r#"SELECT "class" AS data, sourcedId FROM ClassesJson"#;
+const COURSES_JSON: &str = r#"SELECT "course" AS data, sourcedId FROM CoursesJson"#;
+const ENROLLMENTS_JSON: &str = r#"SELECT "enrollment" AS data, sourcedId FROM EnrollmentsJson"#;
+const ORGS_JSON: &str = r#"SELECT "org" AS data, sourcedId FROM OrgsJson"#;
+const SCHOOLS_JSON: &str = r#"
+    SELECT "org" AS data, sourcedId
+    FROM OrgsJson
+    WHERE sourcedId IN (
+        SELECT Orgs.sourcedId
+        FROM Orgs
+            INNER JOIN OrgType ON Orgs.orgTypeId = OrgType.id
+        WHERE OrgType.token = 'school'
+    )
+    "#;
+const PERIODS_JSON: &str = r#"SELECT "period" AS data, sourcedId FROM PeriodsJson"#;
+const SUBJECTS_JSON: &str = r#"SELECT "subject" AS data, sourcedId FROM SubjectsJson"#;
+const USERS_JSON: &str = r#"SELECT "user" AS data, sourcedId FROM UsersJson"#;
+const STUDENTS_JSON: &str = r#"
+    SELECT "user" AS data, sourcedId
+    FROM UsersJson
+    WHERE sourcedId IN (
+        SELECT Users.sourcedId
+        FROM Users
+            INNER JOIN RoleType ON Users.roleTypeId = RoleType.id
+        WHERE RoleType.token = 'student'
+    )
+    "#;
+const TEACHERS_JSON: &str = r#"
+    SELECT "user" AS data, sourcedId
+    FROM UsersJson
+    WHERE sourcedId IN (
+        SELECT Users.sourcedId
+        FROM Users
+            INNER JOIN RoleType ON Users.roleTypeId = RoleType.id
+        WHERE RoleType.token = 'teacher'
+    )
+    "#;
+const LINE_ITEMS_JSON: &str = r#"SELECT "lineItem" AS data, sourcedId FROM LineItemsJson"#;
+const CATEGORIES_JSON: &str = r#"SELECT "category" AS data, sourcedId FROM CategoriesJson"#;
+const RESULTS_JSON: &str = r#"SELECT "result" AS data, sourcedId FROM ResultsJson"#;
+const RESOURCES_JSON: &str = r#"SELECT "resource" AS data, sourcedId FROM ResourcesJson"#;
+const DEMOGRAPHICS_JSON: &str = r#"SELECT "demographics" AS data, sourcedId FROM DemographicsJson"#;
+
+create_get_db!(get_all_classes, model::Class, "classes", CLASSES_JSON);
 create_get_db!(
     get_all_academic_sessions,
-    model::AcademicSessions,
-    "SELECT academicSessions AS academic_sessions FROM AcademicSessionsJsonArray",
-    academic_sessions
-);
-create_get_db!(
-    get_all_periods,
-    model::Periods,
-    "SELECT periods FROM PeriodsJsonArray",
-    periods
-);
-create_get_db!(
-    get_all_orgs,
-    model::Orgs,
-    "SELECT orgs FROM OrgsJsonArray",
-    orgs
+    model::AcademicSession,
+    "academicSessions",
+    ACADEMIC_SESSIONS_JSON
 );
+create_get_db!(get_all_periods, model::Period, "periods", PERIODS_JSON);
+create_get_db!(get_all_orgs, model::Org, "orgs", ORGS_JSON);
+create_get_db!(get_all_users, model::User, "users", USERS_JSON);
+create_get_db!(get_all_subjects, model::Subject, "subjects", SUBJECTS_JSON);
+create_get_db!(get_all_courses, model::Course, "courses", COURSES_JSON);
 create_get_db!(
-    get_all_users,
-    model::Users,
-    "SELECT users FROM UsersJsonArray",
-    users
-);
-create_get_db!(
-    get_all_subjects,
-    model::Subjects,
-    "SELECT subjects FROM SubjectsJsonArray",
-    subjects
+    get_all_enrollments,
+    model::Enrollment,
+    "enrollments",
+    ENROLLMENTS_JSON
 );
 create_get_db!(
-    get_all_courses,
-    model::Courses,
-    "SELECT courses FROM CoursesJsonArray",
-    courses
+    get_all_grading_periods,
+    model::AcademicSession,
+    "academicSessions",
+    GRADING_PERIODS_JSON
 );
+create_get_db!(get_all_schools, model::Org, "orgs", SCHOOLS_JSON);
+create_get_db!(get_all_students, model::User, "users", STUDENTS_JSON);
+create_get_db!(get_all_teachers, model::User, "users", TEACHERS_JSON);
 create_get_db!(
-    get_all_enrollments,
-    model::Enrollments,
-    "SELECT enrollments FROM EnrollmentsJsonArray",
-    enrollments
+    get_all_terms,
+    model::AcademicSession,
+    "academicSessions",
+    TERMS_JSON
 );
 create_get_db!(
-    get_all_grading_periods,
-    model::AcademicSessions,
-    "SELECT academicSessions AS academic_sessions FROM VwORGetAllGradingPeriods",
-    academic_sessions
+    get_all_line_items,
+    model::LineItem,
+    "lineItems",
+    LINE_ITEMS_JSON
 );
 create_get_db!(
-    get_all_schools,
-    model::Orgs,
-    "SELECT orgs FROM VwORGetAllSchools",
-    orgs
+    get_all_categories,
+    model::Category,
+    "categories",
+    CATEGORIES_JSON
 );
 create_get_db!(
-    get_all_students,
-    model::Users,
-    "SELECT users FROM VwORGetAllStudents",
-    users
+    get_all_results,
+    model::GradebookResult,
+    "results",
+    RESULTS_JSON
 );
 create_get_db!(
-    get_all_teachers,
-    model::Users,
-    "SELECT users FROM VwORGetAllTeachers",
-    users
+    get_all_resources,
+    model::Resource,
+    "resources",
+    RESOURCES_JSON
 );
 create_get_db!(
-    get_all_terms,
-    model::AcademicSessions,
-    "SELECT academicSessions AS academic_sessions FROM VwOrGetAllTerms",
-    academic_sessions
+    get_all_demographics,
+    model::Demographic,
+    "demographics",
+    DEMOGRAPHICS_JSON
 );
 
+/// The object type, json view, entity table and scope group of each object in the changes
+/// feed, the json column of each view is named after the object type
+const CHANGE_SOURCES: [(&str, &str, &str, &str); 13] = [
+    (
+        "academicSession",
+        "AcademicSessionsJson",
+        "AcademicSessions",
+        "roster-core",
+    ),
+    ("class", "ClassesJson", "Classes", "roster-core"),
+    ("course", "CoursesJson", "Courses", "roster-core"),
+    (
+        "enrollment",
+        "EnrollmentsJson",
+        "Enrollments",
+        "roster-core",
+    ),
+    ("org", "OrgsJson", "Orgs", "roster-core"),
+    ("period", "PeriodsJson", "Periods", "roster-core"),
+    ("subject", "SubjectsJson", "Subjects", "roster-core"),
+    ("user", "UsersJson", "Users", "roster-core"),
+    (
+        "demographics",
+        "DemographicsJson",
+        "Demographics",
+        "roster-demographics",
+    ),
+    ("category", "CategoriesJson", "Categories", "gradebook"),
+    ("lineItem", "LineItemsJson", "LineItems", "gradebook"),
+    ("result", "ResultsJson", "Results", "gradebook"),
+    ("resource", "ResourcesJson", "Resources", "resource"),
+];
+
+/// Lists every object modified after `since` with its type, oldest first, including
+/// tombstones for purged objects. Only objects belonging to `groups` are included. The
+/// feed is always in this order and is paged rather than filtered, so a filter or sort
+/// is rejected
+pub(crate) async fn get_changes(
+    db: &Database,
+    since: &str,
+    groups: &[&str],
+    params: &Parameters,
+) -> Result<Collection<serde_json::Value>> {
+    if params.filter.is_some() || params.sort.is_some() || params.order_by.is_some() {
+        return Err(ServerError::InvalidParameters);
+    }
+    let sources: Vec<_> = CHANGE_SOURCES
+        .iter()
+        .filter(|(.., group)| groups.contains(group))
+        .collect();
+    let mut changes: Vec<String> = sources
+        .iter()
+        .map(|(object, view, table, _)| {
+            format!(
+                r#"SELECT '{object}' AS type, t.sourcedId, t.dateLastModified
+                    , StatusType.token AS status, v."{object}" AS data
+                FROM {table} AS t
+                    INNER JOIN {view} AS v ON t.sourcedId = v.sourcedId
+                    INNER JOIN StatusType ON t.statusTypeId = StatusType.id
+                WHERE t.dateLastModified > ?"#
+            )
+        })
+        .collect();
+    let types: Vec<String> = sources.iter().map(|(o, ..)| format!("'{}'", o)).collect();
+    changes.push(format!(
+        "SELECT type, sourcedId, dateLastModified, 'tobedeleted' AS status, NULL AS data
+        FROM Tombstones
+        WHERE dateLastModified > ? AND type IN ({})",
+        types.join(", ")
+    ));
+    let changes = changes.join(" UNION ALL ");
+    let binds = vec![Bind::Text(since.to_string()); sources.len() + 1];
+
+    // an object is keyed by its type and sourcedId, as a sourcedId is only unique to a type
+    let count = format!("SELECT count(*) FROM ({}) AS changes", changes);
+    let keys = match db.dialect() {
+        Dialect::Sqlite => format!(
+            "SELECT type || ' ' || sourcedId, dateLastModified, count(*) OVER ()
+            FROM ({}) AS changes
+            ORDER BY dateLastModified, type, sourcedId LIMIT ? OFFSET ?",
+            changes
+        ),
+        Dialect::Postgres => format!(
+            r#"SELECT type || ' ' || sourcedId, dateLastModified, count(*) OVER ()
+            FROM ({}) AS changes
+            ORDER BY dateLastModified COLLATE "C", type COLLATE "C", sourcedId COLLATE "C"
+            LIMIT ? OFFSET ?"#,
+            changes
+        ),
+    };
+    let lookup = match db.dialect() {
+        Dialect::Sqlite => format!(
+            "SELECT changeKey, object FROM (
+                SELECT type || ' ' || sourcedId AS changeKey, json_object(
+                    'type', type
+                    , 'sourcedId', sourcedId
+                    , 'status', status
+                    , 'dateLastModified', dateLastModified
+                    , 'object', json(data)
+                ) AS object FROM ({}) AS changes
+            ) AS changes WHERE changeKey IN",
+            changes
+        ),
+        Dialect::Postgres => format!(
+            "SELECT changeKey, object FROM (
+                SELECT type || ' ' || sourcedId AS changeKey, CAST(jsonb_build_object(
+                    'type', type
+                    , 'sourcedId', sourcedId
+                    , 'status', status
+                    , 'dateLastModified', dateLastModified
+                    , 'object', data
+                ) AS text) AS object FROM ({}) AS changes
+            ) AS changes WHERE changeKey IN",
+            changes
+        ),
+    };
+    let page_binds = vec![
+        Bind::Integer(params.limit.into()),
+        Bind::Integer(params.offset.into()),
+    ];
+    Collection::read(db, &keys, &count, &binds, page_binds, lookup, binds.clone()).await
+}
+
+/// Creates a database call function for a single object
+/// $data is the single object wrapper struct e.g. { "user": {} }
+/// $object is the json object name within the wrapper
+/// $query is a collection query the object is found in by its sourcedId
 macro_rules! create_get_db_by_id {
-    ($name:ident, $data:ty, $query:literal, $object:ident) => {
-        pub(crate) async fn $name(db: &sqlx::SqlitePool, id: &str) -> Result<$data> {
-            let row = sqlx::query!($query, id).fetch_optional(db).await?;
-            if let Some(r) = row {
-                if let Some(data) = r.$object {
-                    let output: $data = serde_json::from_str(&data)?;
-                    return Ok(output);
-                }
+    ($name:ident, $data:ty, $object:literal, $query:expr) => {
+        pub(crate) async fn $name(db: &Database, id: &str) -> Result<$data> {
+            let query = format!(
+                "SELECT {} FROM ({}) AS source WHERE sourcedId = ?",
+                db.dialect().text("data"),
+                $query
+            );
+            if let Some(data) = db.object(&query, &[Bind::Text(id.to_string())]).await? {
+                let object: serde_json::Value = serde_json::from_str(&data)?;
+                let output: $data = serde_json::from_value(json!({ $object: object }))?;
+                return Ok(output);
             }
-            Err(ServerError::NoContent)
+            Err(ServerError::UnknownObject(id.to_string()))
         }
     };
 }
@@ -237,214 +597,1173 @@ macro_rules! create_get_db_by_id {
 create_get_db_by_id!(
     get_academic_session,
     model::AcademicSessionSingle,
-    r#"SELECT academicSession AS "academic_session: String" FROM VwORGetAcademicSession WHERE json_extract(academicSession, '$.academicSession.sourcedId') = ?"#,
-    academic_session
-);
-create_get_db_by_id!(
-    get_class,
-    model::ClassSingle,
-    r#"SELECT class AS "class: String" FROM VwORGetClass WHERE json_extract(class, '$.class.sourcedId') = ?"#,
-    class
-);
-create_get_db_by_id!(
-    get_course,
-    model::CourseSingle,
-    r#"SELECT course AS "course: String" FROM VwORGetCourse WHERE json_extract(course, '$.course.sourcedId') = ?"#,
-    course
+    "academicSession",
+    ACADEMIC_SESSIONS_JSON
 );
+create_get_db_by_id!(get_class, model::ClassSingle, "class", CLASSES_JSON);
+create_get_db_by_id!(get_course, model::CourseSingle, "course", COURSES_JSON);
 create_get_db_by_id!(
     get_grading_period,
     model::AcademicSessionSingle,
-    r#"SELECT academicSession AS "academic_session: String" FROM VwORGetGradingPeriod WHERE json_extract(academicSession, '$.academicSession.sourcedId') = ?"#,
-    academic_session
+    "academicSession",
+    GRADING_PERIODS_JSON
 );
 create_get_db_by_id!(
     get_enrollment,
     model::EnrollmentSingle,
-    r#"SELECT enrollment AS "enrollment: String" FROM VwORGetEnrollment WHERE json_extract(enrollment, '$.enrollment.sourcedId') = ?"#,
-    enrollment
+    "enrollment",
+    ENROLLMENTS_JSON
 );
+create_get_db_by_id!(get_org, model::OrgSingle, "org", ORGS_JSON);
+create_get_db_by_id!(get_school, model::OrgSingle, "org", SCHOOLS_JSON);
+create_get_db_by_id!(get_student, model::UserSingle, "user", STUDENTS_JSON);
+create_get_db_by_id!(get_teacher, model::UserSingle, "user", TEACHERS_JSON);
 create_get_db_by_id!(
-    get_org,
-    model::OrgSingle,
-    r#"SELECT org AS "org: String" FROM VwORGetOrg WHERE json_extract(org, '$.org.sourcedId') = ?"#,
-    org
+    get_term,
+    model::AcademicSessionSingle,
+    "academicSession",
+    TERMS_JSON
 );
-create_get_db_by_id!(
-    get_school,
-    model::OrgSingle,
-    r#"SELECT org AS "org: String" FROM VwORGetSchool WHERE json_extract(org, '$.org.sourcedId') = ?"#,
-    org
+create_get_db_by_id!(get_period, model::PeriodSingle, "period", PERIODS_JSON);
+create_get_db_by_id!(get_subject, model::SubjectSingle, "subject", SUBJECTS_JSON);
+create_get_db_by_id!(get_user, model::UserSingle, "user", USERS_JSON);
+create_get_db!(
+    get_classes_for_school,
+    model::Class,
+    "classes",
+    r#"
+    SELECT "class" AS data, sourcedId
+    FROM ClassesJson
+    WHERE sourcedId IN (SELECT sourcedId FROM Classes WHERE orgSourcedId = ?)
+    "#,
+    id
 );
-create_get_db_by_id!(
-    get_student,
-    model::UserSingle,
-    r#"SELECT user AS "user: String" FROM VwORGetStudent WHERE json_extract(user, '$.user.sourcedId') = ?"#,
-    user
+create_get_db!(
+    get_students_for_school,
+    model::User,
+    "users",
+    r#"
+    SELECT "user" AS data, sourcedId
+    FROM UsersJson
+    WHERE sourcedId IN (
+        SELECT UserOrgs.userSourcedId
+        FROM UserOrgs
+            INNER JOIN Users ON UserOrgs.userSourcedId = Users.sourcedId
+            INNER JOIN RoleType ON Users.roleTypeId = RoleType.id
+        WHERE UserOrgs.orgSourcedId = ?
+            AND UserOrgs.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            AND RoleType.token = 'student'
+    )
+    "#,
+    id
 );
-create_get_db_by_id!(
-    get_teacher,
-    model::UserSingle,
-    r#"SELECT user AS "user: String" FROM VwORGetTeacher WHERE json_extract(user, '$.user.sourcedId') = ?"#,
-    user
+create_get_db!(
+    get_teachers_for_school,
+    model::User,
+    "users",
+    r#"
+    SELECT "user" AS data, sourcedId
+    FROM UsersJson
+    WHERE sourcedId IN (
+        SELECT UserOrgs.userSourcedId
+        FROM UserOrgs
+            INNER JOIN Users ON UserOrgs.userSourcedId = Users.sourcedId
+            INNER JOIN RoleType ON Users.roleTypeId = RoleType.id
+        WHERE UserOrgs.orgSourcedId = ?
+            AND UserOrgs.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+            AND RoleType.token = 'teacher'
+    )
+    "#,
+    id
 );
-create_get_db_by_id!(
-    get_term,
-    model::AcademicSessionSingle,
-    r#"SELECT academicSession AS "academic_session: String" FROM VwORGetTerm WHERE json_extract(academicSession, '$.academicSession.sourcedId') = ?"#,
-    academic_session
+create_get_db!(
+    get_enrollments_for_school,
+    model::Enrollment,
+    "enrollments",
+    r#"
+    SELECT "enrollment" AS data, sourcedId
+    FROM EnrollmentsJson
+    WHERE sourcedId IN (SELECT sourcedId FROM Enrollments WHERE orgSourcedId = ?)
+    "#,
+    id
+);
+create_get_db!(
+    get_students_for_class,
+    model::User,
+    "users",
+    r#"
+    SELECT "user" AS data, sourcedId
+    FROM UsersJson
+    WHERE sourcedId IN (
+        SELECT Enrollments.userSourcedId
+        FROM Enrollments
+            INNER JOIN RoleType ON Enrollments.roleTypeId = RoleType.id
+        WHERE Enrollments.classSourcedId = ?
+            AND RoleType.token = 'student'
+    )
+    "#,
+    id
+);
+create_get_db!(
+    get_teachers_for_class,
+    model::User,
+    "users",
+    r#"
+    SELECT "user" AS data, sourcedId
+    FROM UsersJson
+    WHERE sourcedId IN (
+        SELECT Enrollments.userSourcedId
+        FROM Enrollments
+            INNER JOIN RoleType ON Enrollments.roleTypeId = RoleType.id
+        WHERE Enrollments.classSourcedId = ?
+            AND RoleType.token = 'teacher'
+    )
+    "#,
+    id
+);
+create_get_db!(
+    get_classes_for_user,
+    model::Class,
+    "classes",
+    r#"
+    SELECT "class" AS data, sourcedId
+    FROM ClassesJson
+    WHERE sourcedId IN (
+        SELECT Enrollments.classSourcedId
+        FROM Enrollments
+            INNER JOIN Classes ON Enrollments.classSourcedId = Classes.sourcedId
+        WHERE Enrollments.userSourcedId = ?
+    )
+    "#,
+    id
+);
+create_get_db!(
+    get_classes_for_student,
+    model::Class,
+    "classes",
+    r#"
+    SELECT "class" AS data, sourcedId
+    FROM ClassesJson
+    WHERE sourcedId IN (
+        SELECT Enrollments.classSourcedId
+        FROM Enrollments
+            INNER JOIN Classes ON Enrollments.classSourcedId = Classes.sourcedId
+            INNER JOIN RoleType ON Enrollments.roleTypeId = RoleType.id
+        WHERE Enrollments.userSourcedId = ?
+            AND RoleType.token = 'student'
+    )
+    "#,
+    id
+);
+create_get_db!(
+    get_classes_for_teacher,
+    model::Class,
+    "classes",
+    r#"
+    SELECT "class" AS data, sourcedId
+    FROM ClassesJson
+    WHERE sourcedId IN (
+        SELECT Enrollments.classSourcedId
+        FROM Enrollments
+            INNER JOIN Classes ON Enrollments.classSourcedId = Classes.sourcedId
+            INNER JOIN RoleType ON Enrollments.roleTypeId = RoleType.id
+        WHERE Enrollments.userSourcedId = ?
+            AND RoleType.token = 'teacher'
+    )
+    "#,
+    id
+);
+create_get_db!(
+    get_classes_for_term,
+    model::Class,
+    "classes",
+    r#"
+    SELECT "class" AS data, sourcedId
+    FROM ClassesJson
+    WHERE sourcedId IN (
+        SELECT classSourcedId
+        FROM ClassAcademicSessions
+        WHERE academicSessionSourcedId = ?
+            AND statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+    )
+    "#,
+    id
+);
+create_get_db!(
+    get_grading_periods_for_term,
+    model::AcademicSession,
+    "academicSessions",
+    r#"
+    SELECT "academicSession" AS data, sourcedId
+    FROM AcademicSessionsJson
+    WHERE sourcedId IN (
+        SELECT AcademicSessions.sourcedId
+        FROM AcademicSessions
+            INNER JOIN SessionType ON AcademicSessions.sessionTypeId = SessionType.id
+        WHERE AcademicSessions.parentSourcedId = ?
+            AND SessionType.token = 'gradingPeriod'
+    )
+    "#,
+    id
+);
+create_get_db!(
+    get_classes_for_course,
+    model::Class,
+    "classes",
+    r#"
+    SELECT "class" AS data, sourcedId
+    FROM ClassesJson
+    WHERE sourcedId IN (SELECT sourcedId FROM Classes WHERE courseSourcedId = ?)
+    "#,
+    id
+);
+create_get_db!(
+    get_courses_for_school,
+    model::Course,
+    "courses",
+    r#"
+    SELECT "course" AS data, sourcedId
+    FROM CoursesJson
+    WHERE sourcedId IN (SELECT sourcedId FROM Courses WHERE orgSourcedId = ?)
+    "#,
+    id
+);
+create_get_db!(
+    get_terms_for_school,
+    model::AcademicSession,
+    "academicSessions",
+    r#"
+    SELECT "academicSession" AS data, sourcedId
+    FROM AcademicSessionsJson
+    WHERE sourcedId IN (
+        SELECT ClassAcademicSessions.academicSessionSourcedId
+        FROM ClassAcademicSessions
+            INNER JOIN Classes ON ClassAcademicSessions.classSourcedId = Classes.sourcedId
+        WHERE Classes.orgSourcedId = ?
+            AND ClassAcademicSessions.statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+    )
+        AND sourcedId IN (
+            SELECT AcademicSessions.sourcedId
+            FROM AcademicSessions
+                INNER JOIN SessionType ON AcademicSessions.sessionTypeId = SessionType.id
+            WHERE SessionType.token = 'term'
+        )
+    "#,
+    id
 );
 create_get_db_by_id!(
-    get_user,
-    model::UserSingle,
-    r#"SELECT user AS "user: String" FROM VwORGetUser WHERE json_extract(user, '$.user.sourcedId') = ?"#,
-    user
+    get_line_item,
+    model::LineItemSingle,
+    "lineItem",
+    LINE_ITEMS_JSON
 );
 create_get_db_by_id!(
-    get_classes_for_school,
-    model::Classes,
-    r#"SELECT json_object('classes', json_group_array(json(class))) AS 'classes'
-    FROM ClassesJson
-    WHERE json_extract(class, '$.school.sourcedId') = ?"#,
-    classes
+    get_category,
+    model::CategorySingle,
+    "category",
+    CATEGORIES_JSON
 );
 create_get_db_by_id!(
-    get_students_for_school,
-    model::Users,
+    get_result,
+    model::GradebookResultSingle,
+    "result",
+    RESULTS_JSON
+);
+create_get_db!(
+    get_line_items_for_class,
+    model::LineItem,
+    "lineItems",
+    r#"
+    SELECT "lineItem" AS data, sourcedId
+    FROM LineItemsJson
+    WHERE sourcedId IN (SELECT sourcedId FROM LineItems WHERE classSourcedId = ?)
+    "#,
+    id
+);
+create_get_db!(
+    get_results_for_class,
+    model::GradebookResult,
+    "results",
     r#"
-    SELECT json_object('users', json_group_array(json(user))) AS 'users'
-    FROM UsersJson, json_each(json_extract(user, '$.orgs'))
-    WHERE json_extract(json_each.value, '$.sourcedId') = ?
-        AND json_extract(user, '$.role') = "student"
+    SELECT "result" AS data, sourcedId
+    FROM ResultsJson
+    WHERE sourcedId IN (
+        SELECT Results.sourcedId
+        FROM Results
+            INNER JOIN LineItems ON Results.lineItemSourcedId = LineItems.sourcedId
+        WHERE LineItems.classSourcedId = ?
+    )
     "#,
-    users
+    id
 );
 create_get_db_by_id!(
-    get_teachers_for_school,
-    model::Users,
+    get_resource,
+    model::ResourceSingle,
+    "resource",
+    RESOURCES_JSON
+);
+create_get_db!(
+    get_resources_for_class,
+    model::Resource,
+    "resources",
     r#"
-    SELECT json_object('users', json_group_array(json(user))) AS 'users'
-    FROM UsersJson, json_each(json_extract(user, '$.orgs'))
-    WHERE json_extract(json_each.value, '$.sourcedId') = ?
-        AND json_extract(user, '$.role') = "teacher"
+    SELECT "resource" AS data, sourcedId
+    FROM ResourcesJson
+    WHERE sourcedId IN (
+        SELECT resourceSourcedId
+        FROM ClassResources
+        WHERE classSourcedId = ?
+            AND statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+    )
     "#,
-    users
+    id
+);
+create_get_db!(
+    get_resources_for_course,
+    model::Resource,
+    "resources",
+    r#"
+    SELECT "resource" AS data, sourcedId
+    FROM ResourcesJson
+    WHERE sourcedId IN (
+        SELECT resourceSourcedId
+        FROM CourseResources
+        WHERE courseSourcedId = ?
+            AND statusTypeId = ( SELECT id FROM StatusType WHERE token = 'active' )
+    )
+    "#,
+    id
 );
 create_get_db_by_id!(
-    get_enrollments_for_school,
-    model::Enrollments,
+    get_demographics,
+    model::DemographicSingle,
+    "demographics",
+    DEMOGRAPHICS_JSON
+);
+
+create_get_db!(
+    get_enrollments_for_class_in_school,
+    model::Enrollment,
+    "enrollments",
     r#"
-    SELECT json_object('enrollments', json_group_array(json(enrollment))) AS 'enrollments'
+    SELECT "enrollment" AS data, sourcedId
     FROM EnrollmentsJson
-    WHERE json_extract(enrollment, '$.school.sourcedId') = ?
+    WHERE sourcedId IN (
+        SELECT sourcedId
+        FROM Enrollments
+        WHERE orgSourcedId = ?
+            AND classSourcedId = ?
+    )
     "#,
-    enrollments
+    id,
+    sub_id
+);
+create_get_db!(
+    get_students_for_class_in_school,
+    model::User,
+    "users",
+    r#"
+    SELECT "user" AS data, sourcedId
+    FROM UsersJson
+    WHERE sourcedId IN (
+        SELECT Enrollments.userSourcedId
+        FROM Enrollments
+            INNER JOIN RoleType ON Enrollments.roleTypeId = RoleType.id
+        WHERE Enrollments.orgSourcedId = ?
+            AND Enrollments.classSourcedId = ?
+            AND RoleType.token = 'student'
+    )
+    "#,
+    id,
+    sub_id
+);
+create_get_db!(
+    get_results_for_line_item_for_class,
+    model::GradebookResult,
+    "results",
+    r#"
+    SELECT "result" AS data, sourcedId
+    FROM ResultsJson
+    WHERE sourcedId IN (
+        SELECT Results.sourcedId
+        FROM Results
+            INNER JOIN LineItems ON Results.lineItemSourcedId = LineItems.sourcedId
+        WHERE LineItems.classSourcedId = ?
+            AND LineItems.sourcedId = ?
+    )
+    "#,
+    id,
+    sub_id
+);
+create_get_db!(
+    get_results_for_student_for_class,
+    model::GradebookResult,
+    "results",
+    r#"
+    SELECT "result" AS data, sourcedId
+    FROM ResultsJson
+    WHERE sourcedId IN (
+        SELECT Results.sourcedId
+        FROM Results
+            INNER JOIN LineItems ON Results.lineItemSourcedId = LineItems.sourcedId
+        WHERE LineItems.classSourcedId = ?
+            AND Results.studentSourcedId = ?
+    )
+    "#,
+    id,
+    sub_id
 );
 
-macro_rules! create_put_db {
-    ($name:ident, $data:ty, $query:literal, $object:ident) => {
-        pub(crate) async fn $name(data: $data, db: &sqlx::SqlitePool) -> Result<()> {
-            let mut transaction = db.begin().await?;
-            for i in data.$object.iter() {
-                let json = serde_json::to_string(i)?;
-                sqlx::query!($query, json).execute(&mut *transaction).await?;
+/// objects staged or upserted per statement when writing a collection
+const PUT_BATCH: usize = 500;
+
+/// A value which must exist before an object is written, such as a GUIDRef or enum token,
+/// found at `path` in the object or in each element of the `list` array. Missing values are
+/// left to the model and table constraints
+pub(crate) struct Reference {
+    list: Option<&'static str>,
+    path: Option<&'static str>,
+    table: &'static str,
+    column: &'static str,
+}
+
+impl Reference {
+    /// a GUIDRef e.g. `$.class.sourcedId`
+    const fn object(path: &'static str, table: &'static str) -> Self {
+        Self {
+            list: None,
+            path: Some(path),
+            table,
+            column: "sourcedId",
+        }
+    }
+
+    /// an array of GUIDRefs
+    const fn objects(list: &'static str, table: &'static str) -> Self {
+        Self {
+            list: Some(list),
+            path: Some("$.sourcedId"),
+            table,
+            column: "sourcedId",
+        }
+    }
+
+    /// an enum token
+    const fn token(path: &'static str, table: &'static str) -> Self {
+        Self {
+            list: None,
+            path: Some(path),
+            table,
+            column: "token",
+        }
+    }
+
+    /// an array of enum tokens, or codes of other objects found in `column`
+    const fn tokens(list: &'static str, table: &'static str, column: &'static str) -> Self {
+        Self {
+            list: Some(list),
+            path: None,
+            table,
+            column,
+        }
+    }
+
+    /// the name of the field in rejection reasons e.g. `class` or `grades`
+    fn label(&self) -> &'static str {
+        let field = self.list.or(self.path).unwrap_or_default();
+        field
+            .trim_start_matches("$.")
+            .split('.')
+            .next()
+            .unwrap_or(field)
+    }
+}
+
+/// The json view and column a type is written through, its entity table, the references
+/// of its objects and the objects of other types whose json includes theirs
+pub(crate) struct PutTarget {
+    view: &'static str,
+    column: &'static str,
+    table: &'static str,
+    references: &'static [Reference],
+    dependents: &'static [Dependent],
+}
+
+/// Objects whose json includes values of the objects written e.g. the subject titles of a
+/// class, or their parent's children
+#[derive(Clone, Copy)]
+struct Dependent {
+    view: &'static str,
+    column: &'static str,
+    table: &'static str,
+    /// the sourcedIds of the objects depending on the valid objects in `PutStaging`
+    objects: &'static str,
+}
+
+/// the sourcedIds of the valid objects in `PutStaging`
+const STAGED: &str = "SELECT sourcedId FROM PutStaging WHERE reason IS NULL";
+
+impl PutTarget {
+    /// the objects written and those depending on them
+    fn renderings(&self) -> impl Iterator<Item = Dependent> + '_ {
+        let written = Dependent {
+            view: self.view,
+            column: self.column,
+            table: self.table,
+            objects: STAGED,
+        };
+        std::iter::once(written).chain(self.dependents.iter().copied())
+    }
+
+    /// SQL keeping the json of each object a write can change in `PutPrevious`, without its
+    /// dateLastModified, to compare once written. Run before the objects are written
+    pub(super) fn previous(&self, dialect: Dialect) -> Vec<String> {
+        self.renderings()
+            .map(|r| {
+                format!(
+                    r#"INSERT INTO PutPrevious (tbl, sourcedId, dateLastModified, object)
+                    SELECT '{table}', v.sourcedId
+                        , (SELECT dateLastModified FROM {table} WHERE sourcedId = v.sourcedId)
+                        , {object}
+                    FROM {view} AS v WHERE v.sourcedId IN ({objects})
+                    ON CONFLICT DO NOTHING"#,
+                    table = r.table,
+                    object = dialect.undated(&format!(r#"v."{}""#, r.column)),
+                    view = r.view,
+                    objects = r.objects,
+                )
+            })
+            .collect()
+    }
+
+    /// SQL setting the dateLastModified of each object whose json the write changed to the
+    /// time of the write and keeping the date of the others, whatever date was sent with
+    /// them, so it only changes when the object does. Run once the objects are written
+    pub(super) fn modified(&self, dialect: Dialect) -> Vec<String> {
+        let mut statements: Vec<String> = self
+            .renderings()
+            .map(|r| {
+                format!(
+                    r#"INSERT INTO PutCurrent (tbl, sourcedId, object)
+                    SELECT '{table}', v.sourcedId, {object}
+                    FROM {view} AS v WHERE v.sourcedId IN (
+                        {objects} UNION SELECT sourcedId FROM PutPrevious WHERE tbl = '{table}'
+                    )
+                    ON CONFLICT DO NOTHING"#,
+                    table = r.table,
+                    object = dialect.undated(&format!(r#"v."{}""#, r.column)),
+                    view = r.view,
+                    objects = r.objects,
+                )
+            })
+            .collect();
+        let mut tables: Vec<&str> = self.renderings().map(|r| r.table).collect();
+        tables.sort_unstable();
+        tables.dedup();
+        statements.extend(tables.into_iter().map(|table| {
+            format!(
+                "UPDATE {table} SET dateLastModified = coalesce((
+                    SELECT p.dateLastModified FROM PutPrevious AS p
+                        INNER JOIN PutCurrent AS c ON c.tbl = p.tbl AND c.sourcedId = p.sourcedId
+                    WHERE p.tbl = '{table}' AND p.sourcedId = {table}.sourcedId
+                        AND c.object = p.object
+                ), {now})
+                WHERE sourcedId IN (SELECT sourcedId FROM PutCurrent WHERE tbl = '{table}')",
+                now = dialect.now(),
+            )
+        }));
+        statements
+    }
+}
+
+const STATUS: Reference = Reference::token("$.status", "StatusType");
+
+const ACADEMIC_SESSIONS: PutTarget = PutTarget {
+    view: "AcademicSessionsJson",
+    column: "academicSession",
+    table: "AcademicSessions",
+    references: &[
+        STATUS,
+        Reference::token("$.type", "SessionType"),
+        Reference::object("$.parent.sourcedId", "AcademicSessions"),
+    ],
+    dependents: &[Dependent {
+        view: "AcademicSessionsJson",
+        column: "academicSession",
+        table: "AcademicSessions",
+        objects: "SELECT parentSourcedId FROM AcademicSessions \
+            WHERE sourcedId IN (SELECT sourcedId FROM PutStaging WHERE reason IS NULL)",
+    }],
+};
+const PERIODS: PutTarget = PutTarget {
+    view: "PeriodsJson",
+    column: "period",
+    table: "Periods",
+    references: &[STATUS, Reference::objects("$.orgs", "Orgs")],
+    dependents: &[Dependent {
+        view: "ClassesJson",
+        column: "class",
+        table: "Classes",
+        objects: "SELECT classSourcedId FROM ClassPeriods \
+            WHERE periodSourcedId IN (SELECT sourcedId FROM PutStaging WHERE reason IS NULL)",
+    }],
+};
+const SUBJECTS: PutTarget = PutTarget {
+    view: "SubjectsJson",
+    column: "subject",
+    table: "Subjects",
+    references: &[STATUS],
+    dependents: &[
+        Dependent {
+            view: "ClassesJson",
+            column: "class",
+            table: "Classes",
+            objects: "SELECT classSourcedId FROM ClassSubjects \
+                WHERE subjectSourcedId IN (SELECT sourcedId FROM PutStaging WHERE reason IS NULL)",
+        },
+        Dependent {
+            view: "CoursesJson",
+            column: "course",
+            table: "Courses",
+            objects: "SELECT courseSourcedId FROM CourseSubjects \
+                WHERE subjectSourcedId IN (SELECT sourcedId FROM PutStaging WHERE reason IS NULL)",
+        },
+    ],
+};
+const CLASSES: PutTarget = PutTarget {
+    view: "ClassesJson",
+    column: "class",
+    table: "Classes",
+    references: &[
+        STATUS,
+        Reference::token("$.classType", "ClassType"),
+        Reference::object("$.course.sourcedId", "Courses"),
+        Reference::object("$.school.sourcedId", "Orgs"),
+        Reference::tokens("$.grades", "GradeType", "token"),
+        Reference::tokens("$.subjectCodes", "Subjects", "subjectCode"),
+        Reference::objects("$.terms", "AcademicSessions"),
+        Reference::tokens("$.periods", "Periods", "periodCode"),
+        Reference::objects("$.resources", "Resources"),
+    ],
+    dependents: &[],
+};
+const COURSES: PutTarget = PutTarget {
+    view: "CoursesJson",
+    column: "course",
+    table: "Courses",
+    references: &[
+        STATUS,
+        Reference::object("$.schoolYear.sourcedId", "AcademicSessions"),
+        Reference::object("$.org.sourcedId", "Orgs"),
+        Reference::tokens("$.grades", "GradeType", "token"),
+        Reference::tokens("$.subjectCodes", "Subjects", "subjectCode"),
+        Reference::objects("$.resources", "Resources"),
+    ],
+    dependents: &[],
+};
+const ORGS: PutTarget = PutTarget {
+    view: "OrgsJson",
+    column: "org",
+    table: "Orgs",
+    references: &[
+        STATUS,
+        Reference::token("$.type", "OrgType"),
+        Reference::object("$.parent.sourcedId", "Orgs"),
+    ],
+    dependents: &[Dependent {
+        view: "OrgsJson",
+        column: "org",
+        table: "Orgs",
+        objects: "SELECT parentSourcedId FROM Orgs \
+            WHERE sourcedId IN (SELECT sourcedId FROM PutStaging WHERE reason IS NULL)",
+    }],
+};
+const USERS: PutTarget = PutTarget {
+    view: "UsersJson",
+    column: "user",
+    table: "Users",
+    references: &[
+        STATUS,
+        Reference::token("$.role", "RoleType"),
+        Reference::objects("$.agents", "Users"),
+        Reference::objects("$.orgs", "Orgs"),
+        Reference::tokens("$.grades", "GradeType", "token"),
+    ],
+    dependents: &[],
+};
+const ENROLLMENTS: PutTarget = PutTarget {
+    view: "EnrollmentsJson",
+    column: "enrollment",
+    table: "Enrollments",
+    references: &[
+        STATUS,
+        Reference::object("$.user.sourcedId", "Users"),
+        Reference::object("$.class.sourcedId", "Classes"),
+        Reference::object("$.school.sourcedId", "Orgs"),
+        Reference::token("$.role", "RoleType"),
+    ],
+    dependents: &[],
+};
+const LINE_ITEMS: PutTarget = PutTarget {
+    view: "LineItemsJson",
+    column: "lineItem",
+    table: "LineItems",
+    references: &[
+        STATUS,
+        Reference::object("$.class.sourcedId", "Classes"),
+        Reference::object("$.category.sourcedId", "Categories"),
+        Reference::object("$.gradingPeriod.sourcedId", "AcademicSessions"),
+    ],
+    dependents: &[],
+};
+const CATEGORIES: PutTarget = PutTarget {
+    view: "CategoriesJson",
+    column: "category",
+    table: "Categories",
+    references: &[STATUS],
+    dependents: &[],
+};
+const RESULTS: PutTarget = PutTarget {
+    view: "ResultsJson",
+    column: "result",
+    table: "Results",
+    references: &[
+        STATUS,
+        Reference::object("$.lineItem.sourcedId", "LineItems"),
+        Reference::object("$.student.sourcedId", "Users"),
+        Reference::token("$.scoreStatus", "ScoreStatusType"),
+    ],
+    dependents: &[],
+};
+const RESOURCES: PutTarget = PutTarget {
+    view: "ResourcesJson",
+    column: "resource",
+    table: "Resources",
+    references: &[
+        STATUS,
+        Reference::token("$.importance", "ImportanceType"),
+        Reference::tokens("$.roles", "RoleType", "token"),
+    ],
+    dependents: &[],
+};
+const DEMOGRAPHICS: PutTarget = PutTarget {
+    view: "DemographicsJson",
+    column: "demographics",
+    table: "Demographics",
+    references: &[
+        STATUS,
+        // demographics belong to the user with the same sourcedId
+        Reference::object("$.sourcedId", "Users"),
+        Reference::token("$.sex", "GenderType"),
+    ],
+    dependents: &[],
+};
+
+/// An object read from a PUT body with any violations of its schema
+pub(crate) struct PutObject {
+    pub(crate) object: serde_json::Value,
+    pub(crate) violations: Vec<String>,
+}
+
+/// An object rejected from a PUT and the reason
+pub(crate) struct Rejected {
+    pub(crate) sourced_id: Option<String>,
+    pub(crate) reason: String,
+}
+
+/// The reason an object is rejected when the database refuses to write it, given by the kind
+/// of failure as the database's own message names its tables and constraints and differs
+/// between databases. The message is logged instead
+pub(super) fn rejection(view: &str, e: &dyn sqlx::error::DatabaseError) -> String {
+    log::warn!("object rejected by {}: {}", view, e.message());
+    match e.kind() {
+        sqlx::error::ErrorKind::ForeignKeyViolation => "an object it refers to does not exist",
+        sqlx::error::ErrorKind::UniqueViolation => "a value it holds belongs to another object",
+        sqlx::error::ErrorKind::NotNullViolation => "a required value is missing or unknown",
+        sqlx::error::ErrorKind::CheckViolation => "a value it holds is not allowed",
+        _ => "it could not be written",
+    }
+    .to_string()
+}
+
+/// The outcome of a PUT, valid objects are written even when others are rejected
+pub(crate) struct Written {
+    pub(crate) written: u32,
+    pub(crate) rejected: Vec<Rejected>,
+}
+
+/// Writes a collection as its objects are read, rejecting objects which violate their schema
+/// or cannot be read into `T`, refer to GUIDRefs or enum tokens which do not exist or fail a table constraint.
+/// Objects are staged in a temporary table so references between objects of the upload are
+/// checked before any are written, the valid objects are then upserted in batches through
+/// the json view trigger within a single transaction. The server sets dateLastModified, to
+/// the time of the write on the objects whose json it changes, see [`PutTarget::modified`]
+async fn put_collection<T: serde::de::DeserializeOwned + serde::Serialize>(
+    objects: async_std::channel::Receiver<Result<PutObject>>,
+    db: &Database,
+    target: &PutTarget,
+) -> Result<Written> {
+    let batches = objects.chunks(PUT_BATCH).map(|batch| {
+        let mut staged = Vec::with_capacity(batch.len());
+        for object in batch {
+            let PutObject { object, violations } = object?;
+            let sourced_id = object.get("sourcedId").cloned();
+            if !violations.is_empty() {
+                staged.push(json!({ "sourcedId": sourced_id, "reason": violations.join("; ") }));
+                continue;
             }
-            transaction.commit().await?;
-            Ok(())
+            staged.push(match serde_json::from_value::<T>(object) {
+                Ok(object) => json!({ "sourcedId": sourced_id, "data": object }),
+                Err(e) => json!({ "sourcedId": sourced_id, "reason": e.to_string() }),
+            });
+        }
+        Ok(staged)
+    });
+    db.put(batches.boxed(), target).await
+}
+
+/// Creates a database call function to upsert a collection as its objects are read
+/// $data is the struct each object of the collection is read into
+/// $target is the [`PutTarget`] it is written to
+macro_rules! create_put_db {
+    ($name:ident, $data:ty, $target:ident) => {
+        pub(crate) async fn $name(
+            objects: async_std::channel::Receiver<Result<PutObject>>,
+            db: &Database,
+        ) -> Result<Written> {
+            put_collection::<$data>(objects, db, &$target).await
         }
     };
 }
 
 create_put_db!(
     put_academic_sessions,
-    model::AcademicSessions,
-    "INSERT INTO AcademicSessionsJson(academicSession) VALUES (json(?))",
-    academic_sessions
+    model::AcademicSession,
+    ACADEMIC_SESSIONS
 );
-create_put_db!(
-    put_periods,
-    model::Periods,
-    "INSERT INTO PeriodsJson(period) VALUES (json(?))",
-    periods
+create_put_db!(put_periods, model::Period, PERIODS);
+create_put_db!(put_subjects, model::Subject, SUBJECTS);
+create_put_db!(put_classes, model::Class, CLASSES);
+create_put_db!(put_courses, model::Course, COURSES);
+create_put_db!(put_orgs, model::Org, ORGS);
+create_put_db!(put_users, model::User, USERS);
+create_put_db!(put_enrollments, model::Enrollment, ENROLLMENTS);
+create_put_db!(put_line_items, model::LineItem, LINE_ITEMS);
+create_put_db!(put_categories, model::Category, CATEGORIES);
+create_put_db!(put_results, model::GradebookResult, RESULTS);
+create_put_db!(put_resources, model::Resource, RESOURCES);
+create_put_db!(put_demographics, model::Demographic, DEMOGRAPHICS);
+
+/// Creates a database call function to upsert a single object with the same checks as a
+/// collection, a rejected object is returned as an error
+/// $data is the single object wrapper struct e.g. { "user": {} }
+/// $object is the json object contained in the $data struct
+/// $target is the [`PutTarget`] it is written to
+macro_rules! create_put_db_single {
+    ($name:ident, $data:ty, $object:ident, $target:ident) => {
+        pub(crate) async fn $name(data: $data, db: &Database) -> Result<()> {
+            let (sender, objects) = async_std::channel::bounded(1);
+            sender
+                .send(Ok(PutObject {
+                    object: serde_json::to_value(data.$object)?,
+                    violations: Vec::new(),
+                }))
+                .await
+                .ok();
+            drop(sender);
+            let written = put_collection::<serde_json::Value>(objects, db, &$target).await?;
+            match written.rejected.into_iter().next() {
+                Some(rejected) => Err(ServerError::InvalidObject(rejected.reason)),
+                None => Ok(()),
+            }
+        }
+    };
+}
+
+create_put_db_single!(
+    put_academic_session,
+    model::AcademicSessionSingle,
+    academic_session,
+    ACADEMIC_SESSIONS
 );
-create_put_db!(
-    put_subjects,
-    model::Subjects,
-    "INSERT INTO SubjectsJson(subject) VALUES (json(?))",
-    subjects
+create_put_db_single!(put_period, model::PeriodSingle, period, PERIODS);
+create_put_db_single!(put_subject, model::SubjectSingle, subject, SUBJECTS);
+create_put_db_single!(put_class, model::ClassSingle, class, CLASSES);
+create_put_db_single!(put_course, model::CourseSingle, course, COURSES);
+create_put_db_single!(put_org, model::OrgSingle, org, ORGS);
+create_put_db_single!(put_user, model::UserSingle, user, USERS);
+create_put_db_single!(
+    put_enrollment,
+    model::EnrollmentSingle,
+    enrollment,
+    ENROLLMENTS
 );
-create_put_db!(
-    put_classes,
-    model::Classes,
-    "INSERT INTO ClassesJson(class) VALUES (json(?))",
-    classes
+create_put_db_single!(
+    put_demographic,
+    model::DemographicSingle,
+    demographics,
+    DEMOGRAPHICS
 );
-create_put_db!(
-    put_courses,
-    model::Courses,
-    "INSERT INTO CoursesJson(course) VALUES (json(?))",
-    courses
+create_put_db_single!(put_line_item, model::LineItemSingle, line_item, LINE_ITEMS);
+create_put_db_single!(put_category, model::CategorySingle, category, CATEGORIES);
+create_put_db_single!(put_result, model::GradebookResultSingle, result, RESULTS);
+create_put_db_single!(put_resource, model::ResourceSingle, resource, RESOURCES);
+
+/// Creates a database call function which soft deletes a record by setting its status to
+/// tobedeleted, bumping the dateLastModified so delta consumers pick up the change
+/// $table is the entity table of the record
+/// $query optionally limits the record to a kind of object, the collection query of its
+/// typed GET e.g. a student, so `/students/:id` cannot delete a teacher
+macro_rules! create_delete_db {
+    ($name:ident, $table:literal $(, $query:expr)?) => {
+        pub(crate) async fn $name(db: &Database, id: &str) -> Result<()> {
+            let kinds: &[&str] = &[$($query)?];
+            let mut query = format!(
+                "UPDATE {}
+                SET statusTypeId = ( SELECT id FROM StatusType WHERE token = 'tobedeleted' )
+                    , dateLastModified = {}
+                WHERE sourcedId = ?",
+                $table,
+                db.dialect().now()
+            );
+            for kind in kinds {
+                query += &format!(
+                    " AND sourcedId IN (SELECT sourcedId FROM ({}) AS source)",
+                    kind
+                );
+            }
+            let deleted = db.execute(&query, &[Bind::Text(id.to_string())]).await?;
+            if deleted > 0 {
+                return Ok(());
+            }
+            Err(ServerError::NoRecordDeleted)
+        }
+    };
+}
+
+create_delete_db!(delete_line_item, "LineItems");
+create_delete_db!(delete_category, "Categories");
+create_delete_db!(delete_result, "Results");
+create_delete_db!(delete_academic_session, "AcademicSessions");
+create_delete_db!(delete_period, "Periods");
+create_delete_db!(delete_subject, "Subjects");
+create_delete_db!(delete_class, "Classes");
+create_delete_db!(delete_course, "Courses");
+create_delete_db!(delete_org, "Orgs");
+create_delete_db!(delete_user, "Users");
+create_delete_db!(delete_enrollment, "Enrollments");
+create_delete_db!(delete_demographic, "Demographics");
+create_delete_db!(delete_resource, "Resources");
+create_delete_db!(
+    delete_grading_period,
+    "AcademicSessions",
+    GRADING_PERIODS_JSON
 );
-create_put_db!(
-    put_orgs,
-    model::Orgs,
-    "INSERT INTO OrgsJson(org) VALUES (json(?))",
-    orgs
+create_delete_db!(delete_term, "AcademicSessions", TERMS_JSON);
+create_delete_db!(delete_school, "Orgs", SCHOOLS_JSON);
+create_delete_db!(delete_student, "Users", STUDENTS_JSON);
+create_delete_db!(delete_teacher, "Users", TEACHERS_JSON);
+
+/// Creates a database call function which permanently removes a record and its link rows
+/// $type is the object type recorded in the tombstone for delta consumers
+/// $table is the entity table of the record
+/// $query is a list of DELETE statements of its link rows taking the sourcedId, run in
+/// order within a transaction before the record itself is deleted. The json of the objects
+/// holding those links changes, so they are preceded by statements setting their
+/// dateLastModified to `{now}`
+/// records still referenced by other objects e.g. a user with enrollments are rejected
+/// by the foreign key constraints and must have those objects purged first
+/// $refused is a count query taking the sourcedId of the objects which cannot lose the
+/// record, such as users with no other org, and a description of them. The record is kept
+/// while it counts any
+macro_rules! create_purge_db {
+    ($name:ident, $type:literal, $table:literal $(, $query:literal)*) => {
+        create_purge_db!($name, $type, $table, refused: None $(, $query)*);
+    };
+    ($name:ident, $type:literal, $table:literal, refused: $refused:expr $(, $query:literal)*) => {
+        pub(crate) async fn $name(db: &Database, id: &str) -> Result<()> {
+            let refused: Option<(&str, &str)> = $refused;
+            let id = Bind::Text(id.to_string());
+            let guard = refused.map(|(query, _)| {
+                (query.to_string(), vec![id.clone(); query.matches('?').count()])
+            });
+            let links: &[&str] = &[$($query),*];
+            let mut statements: Vec<(String, Vec<Bind>)> = links
+                .iter()
+                .map(|query| {
+                    let query = query.replace("{now}", db.dialect().now());
+                    let binds = vec![id.clone(); query.matches('?').count()];
+                    (query, binds)
+                })
+                .collect();
+            statements.push((
+                concat!(
+                    "INSERT INTO Tombstones (type, sourcedId) SELECT ?, sourcedId FROM ",
+                    $table,
+                    " WHERE sourcedId = ?"
+                )
+                .to_string(),
+                vec![Bind::Text($type.to_string()), id.clone()],
+            ));
+            statements.push((
+                concat!("DELETE FROM ", $table, " WHERE sourcedId = ?").to_string(),
+                vec![id],
+            ));
+            let deleted = match db.transaction(guard.as_ref(), &statements).await? {
+                Some(deleted) => deleted,
+                None => {
+                    let (_, by) = refused.unwrap_or_default();
+                    return Err(ServerError::StillReferenced(by.to_string()));
+                }
+            };
+            if deleted.last().is_some_and(|d| *d > 0) {
+                return Ok(());
+            }
+            Err(ServerError::NoRecordDeleted)
+        }
+    };
+}
+
+create_purge_db!(
+    purge_academic_session,
+    "academicSession",
+    "AcademicSessions",
+    "UPDATE AcademicSessions SET dateLastModified = {now}
+    WHERE sourcedId IN (SELECT parentSourcedId FROM AcademicSessions WHERE sourcedId = ?)",
+    "UPDATE Classes SET dateLastModified = {now} WHERE sourcedId IN
+    (SELECT classSourcedId FROM ClassAcademicSessions WHERE academicSessionSourcedId = ?)",
+    "DELETE FROM ClassAcademicSessions WHERE academicSessionSourcedId = ?"
 );
-create_put_db!(
-    put_users,
-    model::Users,
-    "INSERT INTO UsersJson(user) VALUES (json(?))",
-    users
+create_purge_db!(
+    purge_period,
+    "period",
+    "Periods",
+    "UPDATE Classes SET dateLastModified = {now} WHERE sourcedId IN
+    (SELECT classSourcedId FROM ClassPeriods WHERE periodSourcedId = ?)",
+    "DELETE FROM OrgPeriods WHERE periodSourcedId = ?",
+    "DELETE FROM ClassPeriods WHERE periodSourcedId = ?"
 );
-create_put_db!(
-    put_enrollments,
-    model::Enrollments,
-    "INSERT INTO EnrollmentsJson(enrollment) VALUES (json(?))",
-    enrollments
+create_purge_db!(
+    purge_subject,
+    "subject",
+    "Subjects",
+    "UPDATE Classes SET dateLastModified = {now} WHERE sourcedId IN
+    (SELECT classSourcedId FROM ClassSubjects WHERE subjectSourcedId = ?)",
+    "UPDATE Courses SET dateLastModified = {now} WHERE sourcedId IN
+    (SELECT courseSourcedId FROM CourseSubjects WHERE subjectSourcedId = ?)",
+    "DELETE FROM ClassSubjects WHERE subjectSourcedId = ?",
+    "DELETE FROM CourseSubjects WHERE subjectSourcedId = ?"
+);
+create_purge_db!(
+    purge_class,
+    "class",
+    "Classes",
+    "DELETE FROM ClassGrades WHERE classSourcedId = ?",
+    "DELETE FROM ClassSubjects WHERE classSourcedId = ?",
+    "DELETE FROM ClassAcademicSessions WHERE classSourcedId = ?",
+    "DELETE FROM ClassPeriods WHERE classSourcedId = ?",
+    "DELETE FROM ClassResources WHERE classSourcedId = ?"
+);
+create_purge_db!(
+    purge_course,
+    "course",
+    "Courses",
+    "DELETE FROM CourseGrades WHERE courseSourcedId = ?",
+    "DELETE FROM CourseSubjects WHERE courseSourcedId = ?",
+    "DELETE FROM CourseResources WHERE courseSourcedId = ?"
+);
+// a user must have an org, so an org is kept while it is the only org of a user
+create_purge_db!(
+    purge_org,
+    "org",
+    "Orgs",
+    refused: Some((
+        "SELECT COUNT(*) FROM UserOrgs uo WHERE uo.orgSourcedId = ? AND NOT EXISTS (
+        SELECT 1 FROM UserOrgs other
+        WHERE other.userSourcedId = uo.userSourcedId AND other.orgSourcedId <> uo.orgSourcedId)",
+        "users with no other org",
+    )),
+    "UPDATE Orgs SET dateLastModified = {now}
+    WHERE sourcedId IN (SELECT parentSourcedId FROM Orgs WHERE sourcedId = ?)",
+    "UPDATE Periods SET dateLastModified = {now} WHERE sourcedId IN
+    (SELECT periodSourcedId FROM OrgPeriods WHERE orgSourcedId = ?)",
+    "UPDATE Users SET dateLastModified = {now} WHERE sourcedId IN
+    (SELECT userSourcedId FROM UserOrgs WHERE orgSourcedId = ?)",
+    "DELETE FROM OrgPeriods WHERE orgSourcedId = ?",
+    "DELETE FROM UserOrgs WHERE orgSourcedId = ?"
+);
+create_purge_db!(
+    purge_user,
+    "user",
+    "Users",
+    "DELETE FROM UserIds WHERE userSourcedId = ?",
+    "DELETE FROM UserGrades WHERE userSourcedId = ?",
+    "UPDATE Users SET dateLastModified = {now} WHERE sourcedId IN
+    (SELECT userSourcedId FROM UserAgents WHERE agentUserSourcedId = ?)",
+    "DELETE FROM UserAgents WHERE userSourcedId = ? OR agentUserSourcedId = ?",
+    "DELETE FROM UserOrgs WHERE userSourcedId = ?"
+);
+create_purge_db!(purge_enrollment, "enrollment", "Enrollments");
+create_purge_db!(purge_demographic, "demographics", "Demographics");
+create_purge_db!(purge_line_item, "lineItem", "LineItems");
+create_purge_db!(purge_category, "category", "Categories");
+create_purge_db!(purge_result, "result", "Results");
+create_purge_db!(
+    purge_resource,
+    "resource",
+    "Resources",
+    "UPDATE Classes SET dateLastModified = {now} WHERE sourcedId IN
+    (SELECT classSourcedId FROM ClassResources WHERE resourceSourcedId = ?)",
+    "UPDATE Courses SET dateLastModified = {now} WHERE sourcedId IN
+    (SELECT courseSourcedId FROM CourseResources WHERE resourceSourcedId = ?)",
+    "DELETE FROM ResourceRoles WHERE resourceSourcedId = ?",
+    "DELETE FROM ClassResources WHERE resourceSourcedId = ?",
+    "DELETE FROM CourseResources WHERE resourceSourcedId = ?"
 );
 
-pub(super) async fn init(path: &str, create: bool) -> Result<sqlx::Pool<sqlx::Sqlite>> {
-    init_db(path, create).await?;
-    let pool = connect(path).await?;
-    if create {
-        init_schema(&pool).await?;
-        init_admin(&pool).await?;
-    }
-    Ok(pool)
+/// The connection pool each server holds to the database. A streamed collection holds its
+/// connection until its page has been read
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Pool {
+    pub(crate) max_connections: u32,
+    /// how long a request waits for a free connection before failing
+    pub(crate) acquire_timeout: std::time::Duration,
 }
 
-async fn init_db(path: &str, create: bool) -> Result<()> {
-    log::info!("seeking database...");
-    let exist = sqlx::Sqlite::database_exists(path).await?;
-    if exist {
-        log::info!("found existing database");
-    } else if create {
-        log::info!("no existing database, creating...");
-        sqlx::Sqlite::create_database(path).await?;
-    } else {
-        log::info!("no existing database found, exiting...");
-        return Err(ServerError::NoDatabaseFound);
+impl Default for Pool {
+    fn default() -> Self {
+        Self {
+            max_connections: 8,
+            acquire_timeout: std::time::Duration::from_secs(30),
+        }
     }
-    Ok(())
 }
 
-async fn init_schema(pool: &sqlx::SqlitePool) -> Result<()> {
-    let mut t = pool.begin().await?;
-    sqlx::query_file!("db/schema.sql").execute(&mut *t).await?;
-    sqlx::query_file!("db/init.sql").execute(&mut *t).await?;
-    t.commit().await?;
-    Ok(())
+/// Opens the database, a `postgres://` URL or otherwise the path of a SQLite file, and
+/// applies any pending migrations
+pub(super) async fn init(database: &str, create: bool, pool: Pool) -> Result<Database> {
+    let db: Database =
+        if database.starts_with("postgres://") || database.starts_with("postgresql://") {
+            std::sync::Arc::new(postgres::PostgresStore::open(database, create, pool).await?)
+        } else {
+            let path = "sqlite:".to_owned() + database;
+            std::sync::Arc::new(sqlite::SqliteStore::open(&path, create, pool).await?)
+        };
+    db.migrate().await?;
+    if create {
+        init_admin(&db).await?;
+    }
+    Ok(db)
 }
 
-async fn init_admin(pool: &sqlx::SqlitePool) -> Result<()> {
-    let exists = get_api_users(pool).await?.is_empty();
+async fn init_admin(db: &Database) -> Result<()> {
+    let exists = get_api_users(db).await?.is_empty();
     if exists {
         let user = CreateApiUser {
             tag: "root admin".to_string(),
             scope: "admin.readonly roster-core.readonly roster-core.createput".to_string(),
         };
-        let account = create_api_user(user, pool).await?;
+        let account = create_api_user(user, db).await?;
         println!(
             "The root admin account is shown below, \
             this will never be provided again so please store these details securely. \
@@ -457,11 +1776,419 @@ async fn init_admin(pool: &sqlx::SqlitePool) -> Result<()> {
     Ok(())
 }
 
-pub(super) async fn connect(path: &str) -> Result<sqlx::Pool<sqlx::Sqlite>> {
-    log::info!("connecting to database...");
-    let pool = SqlitePoolOptions::new()
-        .max_connections(1)
-        .connect(path)
-        .await?;
-    Ok(pool)
+/// A new SQLite database in the temporary directory, `name` keeps tests running at the
+/// same time apart
+#[cfg(test)]
+pub(crate) async fn scratch(name: &str) -> Database {
+    let path = std::env::temp_dir().join(format!("oneroster_{}_{}.db", name, std::process::id()));
+    let path = path.to_str().unwrap();
+    let _ = std::fs::remove_file(path);
+    init(path, true, Pool::default()).await.unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::channel::Receiver;
+
+    /// the objects of a PUT body as they are read from it
+    fn objects(objects: Vec<serde_json::Value>) -> Receiver<Result<PutObject>> {
+        let (sender, receiver) = async_std::channel::unbounded();
+        for object in objects {
+            let violations = Vec::new();
+            sender
+                .try_send(Ok(PutObject { object, violations }))
+                .unwrap();
+        }
+        receiver
+    }
+
+    /// the objects of a sample collection
+    fn sample(name: &str) -> Vec<serde_json::Value> {
+        let content = std::fs::read_to_string(format!("./sample/{}.json", name)).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        json[name].as_array().unwrap().clone()
+    }
+
+    /// every object of a page
+    async fn read<T: serde::de::DeserializeOwned + Send + 'static>(
+        collection: Collection<T>,
+    ) -> Vec<T> {
+        let rows = collection.rows();
+        let mut objects = Vec::new();
+        while let Ok(object) = rows.recv().await {
+            objects.push(object.unwrap());
+        }
+        objects
+    }
+
+    #[async_std::test]
+    async fn soft_deletes_only_objects_of_the_type() {
+        let db = scratch("soft_delete").await;
+        put_orgs(objects(sample("orgs")), &db).await.unwrap();
+
+        // 018 is a district rather than a school
+        let school = delete_school(&db, "018").await;
+        assert!(matches!(school, Err(ServerError::NoRecordDeleted)));
+        delete_org(&db, "018").await.unwrap();
+        let org = get_org(&db, "018").await.unwrap().org;
+        assert_eq!(json!(org.status), json!("tobedeleted"));
+        let sent: chrono::DateTime<chrono::Utc> = "2012-04-23T18:25:43.511Z".parse().unwrap();
+        assert!(org.date_last_modified > sent);
+
+        let unknown = delete_org(&db, "999").await;
+        assert!(matches!(unknown, Err(ServerError::NoRecordDeleted)));
+        db.close().await;
+    }
+
+    #[async_std::test]
+    async fn purges_only_objects_nothing_refers_to() {
+        let db = scratch("purge").await;
+        put_orgs(objects(sample("orgs")), &db).await.unwrap();
+
+        // 018 is a child of 017
+        assert!(purge_org(&db, "017").await.is_err());
+        assert!(get_org(&db, "017").await.is_ok());
+        purge_org(&db, "018").await.unwrap();
+        let purged = get_org(&db, "018").await;
+        assert!(matches!(purged, Err(ServerError::UnknownObject(_))));
+        let again = purge_org(&db, "018").await;
+        assert!(matches!(again, Err(ServerError::NoRecordDeleted)));
+        db.close().await;
+    }
+
+    #[async_std::test]
+    async fn keeps_the_only_org_of_a_user() {
+        let db = scratch("purge_user_org").await;
+        put_orgs(objects(sample("orgs")), &db).await.unwrap();
+        let org = |id| json!({ "href": format!("orgs/{}", id), "sourcedId": id, "type": "org" });
+        let mut users = sample("users");
+        users[2]["orgs"] = json!([org("018")]);
+        put_users(objects(users.clone()), &db).await.unwrap();
+
+        let purged = purge_org(&db, "018").await;
+        assert!(matches!(purged, Err(ServerError::StillReferenced(_))));
+        assert!(get_org(&db, "018").await.is_ok());
+        let user = get_user(&db, "003").await.unwrap().user;
+        assert_eq!(user.orgs.unwrap()[0].sourced_id, "018");
+
+        // once the user has another org the purge removes only the purged org
+        users[2]["orgs"] = json!([org("018"), org("017")]);
+        put_users(objects(users), &db).await.unwrap();
+        purge_org(&db, "018").await.unwrap();
+        let user = get_user(&db, "003").await.unwrap().user;
+        let orgs: Vec<String> = user
+            .orgs
+            .unwrap()
+            .into_iter()
+            .map(|o| o.sourced_id)
+            .collect();
+        assert_eq!(orgs, ["017"]);
+        db.close().await;
+    }
+
+    #[async_std::test]
+    async fn sets_date_last_modified_when_objects_change() {
+        let db = scratch("modified").await;
+        put_subjects(objects(sample("subjects")), &db)
+            .await
+            .unwrap();
+        put_orgs(objects(sample("orgs")), &db).await.unwrap();
+        put_academic_sessions(objects(sample("academicSessions")), &db)
+            .await
+            .unwrap();
+        put_periods(objects(sample("periods")), &db).await.unwrap();
+        put_courses(objects(sample("courses")), &db).await.unwrap();
+        put_classes(objects(sample("classes")), &db).await.unwrap();
+        let orgs = || async {
+            let mut dates = Vec::new();
+            for id in ["015", "016", "017", "018"] {
+                dates.push(get_org(&db, id).await.unwrap().org.date_last_modified);
+            }
+            dates
+        };
+        let classes = || async {
+            let mut dates = Vec::new();
+            for id in ["01", "02", "03"] {
+                dates.push(get_class(&db, id).await.unwrap().class.date_last_modified);
+            }
+            dates
+        };
+        // dates are kept to the millisecond
+        let later = || async_std::task::sleep(std::time::Duration::from_millis(5));
+
+        // the samples were sent as last modified in 2012
+        let sent: chrono::DateTime<chrono::Utc> = "2012-04-23T18:25:43.511Z".parse().unwrap();
+        let written = orgs().await;
+        assert!(written.iter().all(|date| *date > sent));
+        later().await;
+        put_orgs(objects(sample("orgs")), &db).await.unwrap();
+        assert_eq!(orgs().await, written);
+
+        // 018 is renamed and moved from 017 to 015, changing the children of both
+        let mut moved = sample("orgs")[3].clone();
+        moved["name"] = json!("moved");
+        moved["parent"]["sourcedId"] = json!("015");
+        later().await;
+        put_orgs(objects(vec![moved]), &db).await.unwrap();
+        let modified = orgs().await;
+        assert!(modified[0] > written[0]);
+        assert_eq!(modified[1], written[1]);
+        assert!(modified[2] > written[2]);
+        assert!(modified[3] > written[3]);
+
+        // 02 and 03 hold the title of the CS subject
+        let written = classes().await;
+        let mut subjects = sample("subjects");
+        subjects[1]["title"] = json!("Computer Science");
+        later().await;
+        put_subjects(objects(subjects), &db).await.unwrap();
+        let modified = classes().await;
+        assert_eq!(modified[0], written[0]);
+        assert!(modified[1] > written[1]);
+        assert!(modified[2] > written[2]);
+        db.close().await;
+    }
+
+    #[async_std::test]
+    async fn lists_changes_with_tombstones_in_order() {
+        let db = scratch("changes").await;
+        put_academic_sessions(objects(sample("academicSessions")), &db)
+            .await
+            .unwrap();
+        put_orgs(objects(sample("orgs")), &db).await.unwrap();
+        let written = get_org(&db, "015").await.unwrap().org.date_last_modified;
+        // dates are kept to the millisecond
+        async_std::task::sleep(std::time::Duration::from_millis(5)).await;
+        delete_org(&db, "018").await.unwrap();
+        purge_org(&db, "018").await.unwrap();
+
+        let params = Parameters::default();
+        let changes = get_changes(&db, "2000-01-01", &["roster-core"], &params)
+            .await
+            .unwrap();
+        assert_eq!(changes.total, 7);
+        let changes = read(changes).await;
+        let keys: Vec<_> = changes
+            .iter()
+            .map(|c| {
+                (
+                    c["type"].as_str().unwrap(),
+                    c["sourcedId"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(keys[0], ("academicSession", "001"));
+        assert_eq!(keys[3], ("org", "015"));
+        let last = changes.last().unwrap();
+        assert_eq!(keys.last().unwrap(), &("org", "018"));
+        assert_eq!(last["status"], "tobedeleted");
+        assert!(last["object"].is_null());
+
+        // purging 018 changes the children of its parent 017
+        let since = written.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        let since = get_changes(&db, &since, &["roster-core"], &params)
+            .await
+            .unwrap();
+        assert_eq!(since.total, 2);
+        let since: Vec<_> = read(since)
+            .await
+            .iter()
+            .map(|c| c["sourcedId"].clone())
+            .collect();
+        assert_eq!(since, [json!("017"), json!("018")]);
+
+        let gradebook = get_changes(&db, "2000-01-01", &["gradebook"], &params)
+            .await
+            .unwrap();
+        assert_eq!(gradebook.total, 0);
+        let paged = Parameters {
+            limit: 2,
+            offset: 5,
+            ..Default::default()
+        };
+        let page = get_changes(&db, "2000-01-01", &["roster-core"], &paged)
+            .await
+            .unwrap();
+        assert_eq!(page.total, 7);
+        assert_eq!(read(page).await.len(), 2);
+        db.close().await;
+    }
+
+    #[async_std::test]
+    async fn rejects_filters_and_sorts_of_changes() {
+        let db = scratch("changes_params").await;
+        for params in [
+            Parameters {
+                filter: Some("type='org'".to_string()),
+                ..Default::default()
+            },
+            Parameters {
+                sort: Some("type".to_string()),
+                ..Default::default()
+            },
+            Parameters {
+                order_by: Some("desc".to_string()),
+                ..Default::default()
+            },
+        ] {
+            let changes = get_changes(&db, "2000-01-01", &["roster-core"], &params).await;
+            assert!(matches!(changes, Err(ServerError::InvalidParameters)));
+        }
+        db.close().await;
+    }
+
+    #[async_std::test]
+    async fn streams_a_page_as_it_was_when_counted() {
+        let db = scratch("snapshot").await;
+        put_orgs(objects(sample("orgs")), &db).await.unwrap();
+        let page = get_all_orgs(&db, &Default::default()).await.unwrap();
+        assert_eq!(page.total, 4);
+
+        // written after the page was counted, while it is yet to be sent
+        purge_org(&db, "018").await.unwrap();
+        let mut renamed = sample("orgs")[0].clone();
+        renamed["name"] = json!("renamed");
+        put_orgs(objects(vec![renamed]), &db).await.unwrap();
+
+        let orgs = read(page).await;
+        let ids: Vec<&str> = orgs.iter().map(|o| o.sourced_id.as_str()).collect();
+        assert_eq!(ids, ["015", "016", "017", "018"]);
+        assert_eq!(orgs[0].name, "put05");
+        let now = get_all_orgs(&db, &Default::default()).await.unwrap();
+        assert_eq!(now.total, 3);
+        assert_eq!(read(now).await[0].name, "renamed");
+        db.close().await;
+    }
+
+    #[async_std::test]
+    async fn writing_a_purged_object_again_removes_its_tombstone() {
+        let db = scratch("tombstone").await;
+        put_orgs(objects(sample("orgs")), &db).await.unwrap();
+        purge_org(&db, "018").await.unwrap();
+        put_orgs(objects(sample("orgs")), &db).await.unwrap();
+
+        let params = Parameters::default();
+        let changes = get_changes(&db, "2000-01-01", &["roster-core"], &params)
+            .await
+            .unwrap();
+        let changes: Vec<_> = read(changes)
+            .await
+            .into_iter()
+            .filter(|c| c["sourcedId"] == "018")
+            .collect();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0]["status"], "active");
+        db.close().await;
+    }
+
+    #[async_std::test]
+    async fn rejects_objects_referring_to_what_does_not_exist() {
+        let db = scratch("references").await;
+        let mut orgs = sample("orgs");
+        // 018 refers to 017, which is rejected for its unknown parent
+        orgs[2]["parent"] = json!({ "href": "orgs/x", "sourcedId": "x", "type": "org" });
+        orgs[1]["type"] = json!("wizard");
+        let violated = PutObject {
+            object: json!({ "sourcedId": "019" }),
+            violations: vec!["/name: required".to_string()],
+        };
+        let (sender, receiver) = async_std::channel::unbounded();
+        for object in orgs {
+            let violations = Vec::new();
+            sender
+                .try_send(Ok(PutObject { object, violations }))
+                .unwrap();
+        }
+        sender.try_send(Ok(violated)).unwrap();
+        drop(sender);
+
+        let written = put_orgs(receiver, &db).await.unwrap();
+        assert_eq!(written.written, 1);
+        let rejected: Vec<_> = written
+            .rejected
+            .iter()
+            .map(|r| (r.sourced_id.as_deref().unwrap(), r.reason.as_str()))
+            .collect();
+        // an unknown enum value is rejected when the object is read into its model
+        assert_eq!(rejected[0].0, "016");
+        assert!(rejected[0].1.contains("`wizard`"));
+        assert_eq!(
+            rejected[1..],
+            [
+                ("017", "unknown parent 'x'"),
+                ("018", "unknown parent '017'"),
+                ("019", "/name: required"),
+            ]
+        );
+        assert!(get_org(&db, "015").await.is_ok());
+        assert!(get_org(&db, "018").await.is_err());
+
+        // an object is accepted once what it refers to is written
+        let written = put_orgs(objects(sample("orgs")), &db).await.unwrap();
+        assert!(written.rejected.is_empty());
+        db.close().await;
+    }
+
+    #[async_std::test]
+    async fn rejects_demographics_of_unknown_users() {
+        let db = scratch("demographics_references").await;
+        let demographics = || {
+            let demographic = |id| {
+                json!({ "sourcedId": id, "status": "active",
+                    "dateLastModified": "2021-01-01T00:00:00Z", "birthDate": "2010-01-01" })
+            };
+            objects(vec![demographic("001"), demographic("x")])
+        };
+        put_orgs(objects(sample("orgs")), &db).await.unwrap();
+        let written = put_demographics(demographics(), &db).await.unwrap();
+        assert_eq!(written.written, 0);
+        assert_eq!(written.rejected[0].reason, "unknown sourcedId '001'");
+
+        put_users(objects(sample("users")), &db).await.unwrap();
+        let written = put_demographics(demographics(), &db).await.unwrap();
+        assert_eq!(written.written, 1);
+        assert_eq!(written.rejected[0].sourced_id.as_deref(), Some("x"));
+        db.close().await;
+    }
+
+    #[async_std::test]
+    async fn describes_objects_the_database_rejects_by_the_failure() {
+        let db = scratch("database_rejections").await;
+        put_orgs(objects(sample("orgs")), &db).await.unwrap();
+        put_academic_sessions(objects(sample("academicSessions")), &db)
+            .await
+            .unwrap();
+        put_courses(objects(sample("courses")), &db).await.unwrap();
+        put_classes(objects(sample("classes")), &db).await.unwrap();
+        put_users(objects(sample("users")), &db).await.unwrap();
+
+        // 03 enrolls the same user in the same class at the same school as 01
+        let mut enrollments = sample("enrollments");
+        let mut duplicate = enrollments[0].clone();
+        duplicate["sourcedId"] = json!("03");
+        enrollments.push(duplicate);
+        let written = put_enrollments(objects(enrollments), &db).await.unwrap();
+        assert_eq!(written.written, 2);
+        assert_eq!(written.rejected.len(), 1);
+        assert_eq!(written.rejected[0].sourced_id.as_deref(), Some("03"));
+        assert_eq!(
+            written.rejected[0].reason,
+            "a value it holds belongs to another object"
+        );
+        db.close().await;
+    }
+
+    #[async_std::test]
+    async fn rejects_an_upload_referring_only_to_unknown_objects() {
+        let db = scratch("unknown_references").await;
+        let written = put_classes(objects(sample("classes")), &db).await.unwrap();
+        assert_eq!(written.written, 0);
+        assert_eq!(written.rejected.len(), 3);
+        assert!(written
+            .rejected
+            .iter()
+            .all(|r| r.reason.starts_with("unknown ")));
+        db.close().await;
+    }
 }
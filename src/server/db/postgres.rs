@@ -0,0 +1,474 @@
+use super::{
+    Bind, Dialect, Key, Pool, PutTarget, Reference, Rejected, Snapshot, Store, UserList, Written,
+};
+use crate::server::{Creds, Result, ServerError};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{migrate::MigrateDatabase, Acquire};
+
+/// schema migrations in version order, embedded from `db/migrations/postgres`
+static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("db/migrations/postgres");
+
+/// A PostgreSQL database which may be shared by several servers, the json views are built
+/// with jsonb and written through plpgsql triggers
+pub(super) struct PostgresStore {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresStore {
+    pub(super) async fn open(url: &str, create: bool, pool: Pool) -> Result<Self> {
+        log::info!("seeking database...");
+        let exist = sqlx::Postgres::database_exists(url).await?;
+        if exist {
+            log::info!("found existing database");
+        } else if create {
+            log::info!("no existing database, creating...");
+            sqlx::Postgres::create_database(url).await?;
+        } else {
+            log::info!("no existing database found, exiting...");
+            return Err(ServerError::NoDatabaseFound);
+        }
+        log::info!("connecting to database...");
+        // each server holds its own pool, so the connections of every replica count
+        // towards the database max_connections
+        let pool = PgPoolOptions::new()
+            .max_connections(pool.max_connections)
+            .acquire_timeout(pool.acquire_timeout)
+            .connect(url)
+            .await?;
+        Ok(Self { pool })
+    }
+}
+
+/// Numbers the `?` parameters of a shared query as `$1`, `$2`.. in order, leaving any
+/// within quoted literals and identifiers
+fn numbered(query: &str) -> String {
+    let mut numbered = String::with_capacity(query.len());
+    let mut quote = None;
+    let mut parameter = 0;
+    for c in query.chars() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '?') => {
+                parameter += 1;
+                numbered += &format!("${}", parameter);
+                continue;
+            }
+            _ => {}
+        }
+        numbered.push(c);
+    }
+    numbered
+}
+
+/// converts a json path e.g. `$.class.sourcedId` into a jsonb path e.g. `'{class,sourcedId}'`
+fn jsonb_path(path: &str) -> String {
+    let path = path.trim_start_matches('$').trim_start_matches('.');
+    format!("'{{{}}}'", path.replace('.', ","))
+}
+
+/// SQL rejecting each valid staged object with a value which does not exist in the
+/// database. References to the type being written may also be to other valid objects
+/// in the upload
+fn check(reference: &Reference, table: &str) -> String {
+    let label = reference.label();
+    let value = match (reference.list, reference.path) {
+        (Some(_), Some(path)) => format!("(e.value #>> {})", jsonb_path(path)),
+        (Some(_), None) => "(e.value #>> '{}')".to_string(),
+        (None, path) => format!("(s.data #>> {})", jsonb_path(path.unwrap_or("$"))),
+    };
+    let mut missing = format!(
+        "{value} IS NOT NULL AND NOT EXISTS (SELECT 1 FROM {} WHERE {} = {value})",
+        reference.table, reference.column
+    );
+    if reference.table == table {
+        missing += &format!(
+            " AND NOT EXISTS (SELECT 1 FROM PutStaging p \
+            WHERE p.reason IS NULL AND p.sourcedId = {value})"
+        );
+    }
+    match reference.list {
+        None => format!(
+            "UPDATE PutStaging AS s SET reason = 'unknown {label} ' || quote_literal({value})
+            WHERE s.reason IS NULL AND {missing}"
+        ),
+        Some(list) => {
+            // a list which is missing or not an array has no elements, as with json_each
+            let elements = format!(
+                "jsonb_array_elements(CASE jsonb_typeof(s.data #> {list}) \
+                WHEN 'array' THEN s.data #> {list} END) AS e",
+                list = jsonb_path(list)
+            );
+            format!(
+                "UPDATE PutStaging AS s SET reason = (
+                    SELECT 'unknown {label} ' || quote_literal({value})
+                    FROM {elements} WHERE {missing} LIMIT 1
+                )
+                WHERE s.reason IS NULL
+                AND EXISTS (SELECT 1 FROM {elements} WHERE {missing})"
+            )
+        }
+    }
+}
+
+#[tide::utils::async_trait]
+impl Store for PostgresStore {
+    fn dialect(&self) -> Dialect {
+        Dialect::Postgres
+    }
+
+    /// Applies each pending migration in order, servers starting together wait on the
+    /// migration lock. A database migrated by a newer release is refused
+    async fn migrate(&self) -> Result<()> {
+        let pool = &self.pool;
+        let versioned: bool =
+            sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+                .fetch_one(pool)
+                .await?;
+        if versioned {
+            let applied: Option<i64> =
+                sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations")
+                    .fetch_one(pool)
+                    .await?;
+            let latest = MIGRATOR.iter().map(|m| m.version).max().unwrap_or(0);
+            if let Some(applied) = applied.filter(|v| *v > latest) {
+                return Err(ServerError::NewerSchema(applied));
+            }
+        }
+        log::info!("applying pending migrations...");
+        MIGRATOR.run(pool).await?;
+        Ok(())
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    async fn api_creds(&self, client_id: &str) -> Result<Option<Creds>> {
+        let creds = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT
+                c.client_id
+                , c.client_secret
+                , string_agg(s.scope, ' ' ORDER BY cs.id)
+            FROM
+                credentials c
+                INNER JOIN credential_scopes cs ON c.id = cs.credential_id
+                INNER JOIN scopes s ON cs.scope_id = s.id
+            WHERE
+                c.client_id = $1
+            GROUP BY
+                c.client_id, c.client_secret",
+        )
+        .bind(client_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|(client_id, client_secret, scope)| Creds {
+            client_id,
+            client_secret,
+            scope,
+        });
+        Ok(creds)
+    }
+
+    async fn api_users(&self) -> Result<Vec<UserList>> {
+        let rows = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT
+                c.client_id
+                , c.tag
+                , string_agg(s.scope, ' ' ORDER BY cs.id)
+            FROM
+                credentials c
+                INNER JOIN credential_scopes cs ON c.id = cs.credential_id
+                INNER JOIN scopes s ON cs.scope_id = s.id
+            GROUP BY
+                c.client_id, c.tag
+            ORDER BY
+                c.client_id",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|(client_id, tag, scope)| UserList {
+            tag,
+            client_id,
+            scope,
+        })
+        .collect();
+
+        Ok(rows)
+    }
+
+    async fn insert_api_user(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        tag: &str,
+        scopes: &[&str],
+    ) -> Result<()> {
+        let mut t = self.pool.begin().await?;
+        sqlx::query("INSERT INTO credentials (client_id, client_secret, tag) VALUES ($1, $2, $3)")
+            .bind(client_id)
+            .bind(client_secret)
+            .bind(tag)
+            .execute(&mut *t)
+            .await?;
+        for scope in scopes {
+            // scopes which do not exist are left out
+            sqlx::query(
+                "INSERT INTO credential_scopes (credential_id, scope_id)
+                SELECT c.id, s.id FROM credentials c, scopes s
+                WHERE c.client_id = $1 AND s.scope = $2",
+            )
+            .bind(client_id)
+            .bind(scope)
+            .execute(&mut *t)
+            .await?;
+        }
+        t.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_api_user(&self, client_id: &str) -> Result<u64> {
+        let deleted = sqlx::query("DELETE FROM credentials WHERE client_id = $1")
+            .bind(client_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(deleted)
+    }
+
+    async fn object(&self, query: &str, binds: &[Bind]) -> Result<Option<String>> {
+        let query = numbered(query);
+        let arguments = super::arguments::<sqlx::Postgres>(binds)?;
+        Ok(sqlx::query_scalar_with(&query, arguments)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn snapshot(&self) -> Result<Box<dyn Snapshot>> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .execute(&mut *tx)
+            .await?;
+        Ok(Box::new(PostgresSnapshot(tx)))
+    }
+
+    async fn execute(&self, query: &str, binds: &[Bind]) -> Result<u64> {
+        let query = numbered(query);
+        let arguments = super::arguments::<sqlx::Postgres>(binds)?;
+        Ok(sqlx::query_with(&query, arguments)
+            .execute(&self.pool)
+            .await?
+            .rows_affected())
+    }
+
+    async fn transaction(
+        &self,
+        guard: Option<&(String, Vec<Bind>)>,
+        statements: &[(String, Vec<Bind>)],
+    ) -> Result<Option<Vec<u64>>> {
+        let mut tx = self.pool.begin().await?;
+        if let Some((query, binds)) = guard {
+            let query = numbered(query);
+            let arguments = super::arguments::<sqlx::Postgres>(binds)?;
+            let count: i64 = sqlx::query_scalar_with(&query, arguments)
+                .fetch_one(&mut *tx)
+                .await?;
+            if count > 0 {
+                return Ok(None);
+            }
+        }
+        let mut affected = Vec::with_capacity(statements.len());
+        for (query, binds) in statements {
+            let query = numbered(query);
+            let arguments = super::arguments::<sqlx::Postgres>(binds)?;
+            affected.push(
+                sqlx::query_with(&query, arguments)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected(),
+            );
+        }
+        tx.commit().await?;
+        Ok(Some(affected))
+    }
+
+    async fn put(
+        &self,
+        mut batches: BoxStream<'_, Result<Vec<serde_json::Value>>>,
+        target: &PutTarget,
+    ) -> Result<Written> {
+        // the upload is staged in a table of its connection as it is received, outside of a
+        // transaction so rows written by other uploads are not locked while a slow client
+        // sends it
+        let mut connection = self.pool.acquire().await?;
+        for staging in [
+            "CREATE TEMP TABLE IF NOT EXISTS PutStaging (
+                idx bigint GENERATED ALWAYS AS IDENTITY PRIMARY KEY
+                , sourcedId text, data jsonb, reason text
+            )",
+            "CREATE INDEX IF NOT EXISTS PutStagingSourcedIdIndex ON PutStaging (sourcedId)",
+            "CREATE TEMP TABLE IF NOT EXISTS PutPrevious (
+                tbl text, sourcedId text, dateLastModified text, object jsonb
+                , PRIMARY KEY (tbl, sourcedId)
+            )",
+            "CREATE TEMP TABLE IF NOT EXISTS PutCurrent (
+                tbl text, sourcedId text, object jsonb, PRIMARY KEY (tbl, sourcedId)
+            )",
+            "TRUNCATE PutStaging, PutPrevious, PutCurrent RESTART IDENTITY",
+        ] {
+            sqlx::query(staging).execute(&mut *connection).await?;
+        }
+
+        while let Some(staged) = batches.next().await {
+            sqlx::query(
+                "INSERT INTO PutStaging (sourcedId, data, reason)
+                SELECT value ->> 'sourcedId', value -> 'data', value ->> 'reason'
+                FROM jsonb_array_elements(CAST($1 AS jsonb)) WITH ORDINALITY AS e(value, n)
+                ORDER BY n",
+            )
+            .bind(serde_json::to_string(&staged?)?)
+            .execute(&mut *connection)
+            .await?;
+        }
+
+        let mut transaction = connection.begin().await?;
+        // rejecting an object can leave others in the upload referring to it, so references to
+        // the type being written are checked again until no more are rejected
+        let mut checks: Vec<String> = target
+            .references
+            .iter()
+            .map(|r| check(r, target.table))
+            .collect();
+        loop {
+            let mut rejected = 0;
+            for check in &checks {
+                rejected += sqlx::query(check)
+                    .execute(&mut *transaction)
+                    .await?
+                    .rows_affected();
+            }
+            if rejected == 0 {
+                break;
+            }
+            checks = target
+                .references
+                .iter()
+                .filter(|r| r.table == target.table)
+                .map(|r| check(r, target.table))
+                .collect();
+        }
+
+        for previous in target.previous(Dialect::Postgres) {
+            sqlx::query(&previous).execute(&mut *transaction).await?;
+        }
+        let insert = format!(
+            r#"INSERT INTO {} ("{}") SELECT data FROM PutStaging
+            WHERE reason IS NULL AND idx > $1 AND idx <= $2 ORDER BY idx"#,
+            target.view, target.column
+        );
+        let staged: i64 = sqlx::query_scalar("SELECT coalesce(max(idx), 0) FROM PutStaging")
+            .fetch_one(&mut *transaction)
+            .await?;
+        for start in (0..staged).step_by(super::PUT_BATCH) {
+            let end = staged.min(start + super::PUT_BATCH as i64);
+            // a failed statement aborts the transaction, so each batch is written within a
+            // savepoint and written one object at a time to find those to reject
+            let mut savepoint = transaction.begin().await?;
+            let batch = sqlx::query(&insert)
+                .bind(start)
+                .bind(end)
+                .execute(&mut *savepoint)
+                .await;
+            match batch {
+                Ok(_) => {
+                    savepoint.commit().await?;
+                    continue;
+                }
+                Err(sqlx::Error::Database(_)) => savepoint.rollback().await?,
+                Err(e) => return Err(e.into()),
+            }
+            for idx in start + 1..=end {
+                let mut savepoint = transaction.begin().await?;
+                let object = sqlx::query(&insert)
+                    .bind(idx - 1)
+                    .bind(idx)
+                    .execute(&mut *savepoint)
+                    .await;
+                match object {
+                    Ok(_) => savepoint.commit().await?,
+                    Err(sqlx::Error::Database(e)) => {
+                        savepoint.rollback().await?;
+                        sqlx::query("UPDATE PutStaging SET reason = $1 WHERE idx = $2")
+                            .bind(super::rejection(target.view, e.as_ref()))
+                            .bind(idx)
+                            .execute(&mut *transaction)
+                            .await?;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+
+        for modified in target.modified(Dialect::Postgres) {
+            sqlx::query(&modified).execute(&mut *transaction).await?;
+        }
+        // an object written again after it was purged is no longer deleted
+        sqlx::query(
+            "DELETE FROM Tombstones WHERE type = $1
+            AND sourcedId IN (SELECT sourcedId FROM PutStaging WHERE reason IS NULL)",
+        )
+        .bind(target.column)
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+
+        let rejected = sqlx::query_as::<_, (Option<String>, String)>(
+            "SELECT sourcedId, reason FROM PutStaging WHERE reason IS NOT NULL ORDER BY idx",
+        )
+        .fetch_all(&mut *connection)
+        .await?
+        .into_iter()
+        .map(|(sourced_id, reason)| Rejected { sourced_id, reason })
+        .collect::<Vec<_>>();
+        sqlx::query("TRUNCATE PutStaging, PutPrevious, PutCurrent")
+            .execute(&mut *connection)
+            .await?;
+        Ok(Written {
+            written: staged as u32 - rejected.len() as u32,
+            rejected,
+        })
+    }
+}
+
+/// A repeatable read transaction, which sees only what was committed before its first query
+struct PostgresSnapshot(sqlx::Transaction<'static, sqlx::Postgres>);
+
+#[tide::utils::async_trait]
+impl Snapshot for PostgresSnapshot {
+    async fn count(&mut self, query: &str, binds: &[Bind]) -> Result<i64> {
+        let query = numbered(query);
+        let arguments = super::arguments::<sqlx::Postgres>(binds)?;
+        Ok(sqlx::query_scalar_with(&query, arguments)
+            .fetch_one(&mut *self.0)
+            .await?)
+    }
+
+    async fn keys(&mut self, query: &str, binds: &[Bind]) -> Result<Vec<Key>> {
+        let query = numbered(query);
+        let arguments = super::arguments::<sqlx::Postgres>(binds)?;
+        Ok(sqlx::query_as_with(&query, arguments)
+            .fetch_all(&mut *self.0)
+            .await?)
+    }
+
+    async fn objects(&mut self, query: &str, binds: &[Bind]) -> Result<Vec<(String, String)>> {
+        let query = numbered(query);
+        let arguments = super::arguments::<sqlx::Postgres>(binds)?;
+        Ok(sqlx::query_as_with(&query, arguments)
+            .fetch_all(&mut *self.0)
+            .await?)
+    }
+}
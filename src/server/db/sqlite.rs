@@ -0,0 +1,529 @@
+use super::{
+    Bind, Dialect, Key, Pool, PutTarget, Reference, Rejected, Snapshot, Store, UserList, Written,
+};
+use crate::server::{Creds, Result, ServerError};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::{migrate::MigrateDatabase, sqlite, Acquire};
+use std::str::FromStr;
+
+/// schema migrations in version order, embedded from `db/migrations/sqlite`
+static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("db/migrations/sqlite");
+
+/// A SQLite database file, the json views are built with the JSON1 functions
+pub(super) struct SqliteStore {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteStore {
+    pub(super) async fn open(path: &str, create: bool, pool: Pool) -> Result<Self> {
+        init_db(path, create).await?;
+        Ok(Self {
+            pool: connect(path, pool).await?,
+        })
+    }
+}
+
+async fn init_db(path: &str, create: bool) -> Result<()> {
+    log::info!("seeking database...");
+    let exist = sqlx::Sqlite::database_exists(path).await?;
+    if exist {
+        log::info!("found existing database");
+    } else if create {
+        log::info!("no existing database, creating...");
+        sqlx::Sqlite::create_database(path).await?;
+    } else {
+        log::info!("no existing database found, exiting...");
+        return Err(ServerError::NoDatabaseFound);
+    }
+    Ok(())
+}
+
+/// Connects in WAL mode, so a collection read through a snapshot does not hold back writes
+async fn connect(path: &str, pool: Pool) -> Result<sqlx::Pool<sqlx::Sqlite>> {
+    log::info!("connecting to database...");
+    let options = SqliteConnectOptions::from_str(path)?.journal_mode(SqliteJournalMode::Wal);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(pool.max_connections)
+        .acquire_timeout(pool.acquire_timeout)
+        .connect_with(options)
+        .await?;
+    Ok(pool)
+}
+
+/// SQL rejecting each valid staged object with a value which does not exist in the
+/// database. References to the type being written may also be to other valid objects
+/// in the upload
+fn check(reference: &Reference, table: &str) -> String {
+    let label = reference.label();
+    let value = match (reference.list, reference.path) {
+        (Some(_), Some(path)) => format!("json_extract(e.value, '{}')", path),
+        (Some(_), None) => "e.value".to_string(),
+        (None, path) => format!("json_extract(s.data, '{}')", path.unwrap_or("$")),
+    };
+    let mut missing = format!(
+        "{value} IS NOT NULL AND NOT EXISTS (SELECT 1 FROM {} WHERE {} = {value})",
+        reference.table, reference.column
+    );
+    if reference.table == table {
+        missing += &format!(
+            " AND NOT EXISTS (SELECT 1 FROM temp.PutStaging p \
+            WHERE p.reason IS NULL AND p.sourcedId = {value})"
+        );
+    }
+    match reference.list {
+        None => format!(
+            "UPDATE temp.PutStaging AS s SET reason = 'unknown {label} ' || quote({value})
+            WHERE s.reason IS NULL AND {missing}"
+        ),
+        Some(list) => format!(
+            "UPDATE temp.PutStaging AS s SET reason = (
+                SELECT 'unknown {label} ' || quote({value})
+                FROM json_each(s.data, '{list}') AS e WHERE {missing} LIMIT 1
+            )
+            WHERE s.reason IS NULL
+            AND EXISTS (SELECT 1 FROM json_each(s.data, '{list}') AS e WHERE {missing})"
+        ),
+    }
+}
+
+#[tide::utils::async_trait]
+impl Store for SqliteStore {
+    fn dialect(&self) -> Dialect {
+        Dialect::Sqlite
+    }
+
+    /// Applies each pending migration in order, recording its version in `_sqlx_migrations`.
+    /// Databases created before migrations were versioned are taken to be at the first version,
+    /// the later migrations only add to it, and a database migrated by a newer release is refused
+    async fn migrate(&self) -> Result<()> {
+        let pool = &self.pool;
+        let versioned: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = '_sqlx_migrations')",
+        )
+        .fetch_one(pool)
+        .await?;
+        let unversioned: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'Orgs')")
+                .fetch_one(pool)
+                .await?;
+        if !versioned && unversioned {
+            log::info!("found unversioned database, recording it as the initial schema");
+            let initial = MIGRATOR.iter().next().expect("no migrations embedded");
+            sqlx::query(
+                "CREATE TABLE _sqlx_migrations ( \
+                    version BIGINT PRIMARY KEY, \
+                    description TEXT NOT NULL, \
+                    installed_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, \
+                    success BOOLEAN NOT NULL, \
+                    checksum BLOB NOT NULL, \
+                    execution_time BIGINT NOT NULL \
+                )",
+            )
+            .execute(pool)
+            .await?;
+            sqlx::query(
+                "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+                VALUES (?, ?, TRUE, ?, 0)",
+            )
+            .bind(initial.version)
+            .bind(&*initial.description)
+            .bind(&*initial.checksum)
+            .execute(pool)
+            .await?;
+        }
+        if versioned {
+            let applied: Option<i64> =
+                sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations")
+                    .fetch_one(pool)
+                    .await?;
+            let latest = MIGRATOR.iter().map(|m| m.version).max().unwrap_or(0);
+            if let Some(applied) = applied.filter(|v| *v > latest) {
+                return Err(ServerError::NewerSchema(applied));
+            }
+        }
+        log::info!("applying pending migrations...");
+        MIGRATOR.run(pool).await?;
+        Ok(())
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    async fn api_creds(&self, client_id: &str) -> Result<Option<Creds>> {
+        let creds = sqlx::query_as!(
+            Creds,
+            r#"
+            SELECT
+                c.client_id
+                , c.client_secret
+                , group_concat(s.scope,' ') AS "scope!: String"
+            FROM
+                credentials c
+                INNER JOIN credential_scopes cs ON c.id = cs.credential_id
+                INNER JOIN scopes s ON cs.scope_id = s.id
+            WHERE
+                c.client_id = ?
+                AND scope IS NOT NULL
+            GROUP BY
+                c.client_id
+            "#,
+            client_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(creds)
+    }
+
+    async fn api_users(&self) -> Result<Vec<UserList>> {
+        let rows = sqlx::query_as!(
+            UserList,
+            r#"
+            SELECT
+                c.client_id
+                , c.tag
+                , group_concat(s.scope,' ') AS "scope!: String"
+            FROM
+                credentials c
+                INNER JOIN credential_scopes cs ON c.id = cs.credential_id
+                INNER JOIN scopes s ON cs.scope_id = s.id
+            WHERE
+                scope IS NOT NULL
+            GROUP BY
+                c.client_id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn insert_api_user(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        tag: &str,
+        scopes: &[&str],
+    ) -> Result<()> {
+        let mut t = self.pool.begin().await?;
+        sqlx::query!(
+            "INSERT INTO credentials(client_id, client_secret, tag) VALUES (?, ?, ?)",
+            client_id,
+            client_secret,
+            tag,
+        )
+        .execute(&mut *t)
+        .await?;
+        for scope in scopes {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO credential_scopes (credential_id, scope_id) VALUES (
+                (SELECT id FROM credentials WHERE client_id = ?),
+                (SELECT id FROM scopes WHERE scope = ?)
+                )",
+                client_id,
+                scope,
+            )
+            .execute(&mut *t)
+            .await?;
+        }
+        t.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_api_user(&self, client_id: &str) -> Result<u64> {
+        let deleted = sqlx::query!("DELETE FROM credentials WHERE client_id = ?", client_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(deleted)
+    }
+
+    async fn object(&self, query: &str, binds: &[Bind]) -> Result<Option<String>> {
+        let arguments = super::arguments::<sqlx::Sqlite>(binds)?;
+        Ok(sqlx::query_scalar_with(query, arguments)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn snapshot(&self) -> Result<Box<dyn Snapshot>> {
+        Ok(Box::new(SqliteSnapshot(self.pool.begin().await?)))
+    }
+
+    async fn execute(&self, query: &str, binds: &[Bind]) -> Result<u64> {
+        let arguments = super::arguments::<sqlx::Sqlite>(binds)?;
+        Ok(sqlx::query_with(query, arguments)
+            .execute(&self.pool)
+            .await?
+            .rows_affected())
+    }
+
+    async fn transaction(
+        &self,
+        guard: Option<&(String, Vec<Bind>)>,
+        statements: &[(String, Vec<Bind>)],
+    ) -> Result<Option<Vec<u64>>> {
+        let mut tx = self.pool.begin().await?;
+        if let Some((query, binds)) = guard {
+            let arguments = super::arguments::<sqlx::Sqlite>(binds)?;
+            let count: i64 = sqlx::query_scalar_with(query, arguments)
+                .fetch_one(&mut *tx)
+                .await?;
+            if count > 0 {
+                return Ok(None);
+            }
+        }
+        let mut affected = Vec::with_capacity(statements.len());
+        for (query, binds) in statements {
+            let arguments = super::arguments::<sqlx::Sqlite>(binds)?;
+            affected.push(
+                sqlx::query_with(query, arguments)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected(),
+            );
+        }
+        tx.commit().await?;
+        Ok(Some(affected))
+    }
+
+    async fn put(
+        &self,
+        mut batches: BoxStream<'_, Result<Vec<serde_json::Value>>>,
+        target: &PutTarget,
+    ) -> Result<Written> {
+        // the upload is staged in a table of its connection as it is received, outside of a
+        // transaction so the database is not locked while a slow client sends it
+        let mut connection = self.pool.acquire().await?;
+        for staging in [
+            "CREATE TEMP TABLE IF NOT EXISTS PutStaging (
+                idx INTEGER PRIMARY KEY, sourcedId TEXT, data TEXT, reason TEXT
+            )",
+            "CREATE INDEX IF NOT EXISTS temp.PutStagingSourcedIdIndex ON PutStaging (sourcedId)",
+            "CREATE TEMP TABLE IF NOT EXISTS PutPrevious (
+                tbl TEXT, sourcedId TEXT, dateLastModified TEXT, object TEXT
+                , PRIMARY KEY (tbl, sourcedId)
+            )",
+            "CREATE TEMP TABLE IF NOT EXISTS PutCurrent (
+                tbl TEXT, sourcedId TEXT, object TEXT, PRIMARY KEY (tbl, sourcedId)
+            )",
+            "DELETE FROM temp.PutStaging",
+            "DELETE FROM temp.PutPrevious",
+            "DELETE FROM temp.PutCurrent",
+        ] {
+            sqlx::query(staging).execute(&mut *connection).await?;
+        }
+
+        while let Some(staged) = batches.next().await {
+            sqlx::query(
+                "INSERT INTO temp.PutStaging (sourcedId, data, reason)
+                SELECT value ->> '$.sourcedId', value -> '$.data', value ->> '$.reason'
+                FROM json_each(?)",
+            )
+            .bind(serde_json::to_string(&staged?)?)
+            .execute(&mut *connection)
+            .await?;
+        }
+
+        let mut transaction = connection.begin().await?;
+        // rejecting an object can leave others in the upload referring to it, so references to
+        // the type being written are checked again until no more are rejected
+        let mut checks: Vec<String> = target
+            .references
+            .iter()
+            .map(|r| check(r, target.table))
+            .collect();
+        loop {
+            let mut rejected = 0;
+            for check in &checks {
+                rejected += sqlx::query(check)
+                    .execute(&mut *transaction)
+                    .await?
+                    .rows_affected();
+            }
+            if rejected == 0 {
+                break;
+            }
+            checks = target
+                .references
+                .iter()
+                .filter(|r| r.table == target.table)
+                .map(|r| check(r, target.table))
+                .collect();
+        }
+
+        for previous in target.previous(Dialect::Sqlite) {
+            sqlx::query(&previous).execute(&mut *transaction).await?;
+        }
+        let insert = format!(
+            r#"INSERT INTO {}("{}") SELECT data FROM temp.PutStaging
+            WHERE reason IS NULL AND idx > ? AND idx <= ? ORDER BY idx"#,
+            target.view, target.column
+        );
+        let staged: i64 = sqlx::query_scalar("SELECT coalesce(max(idx), 0) FROM temp.PutStaging")
+            .fetch_one(&mut *transaction)
+            .await?;
+        for start in (0..staged).step_by(super::PUT_BATCH) {
+            let end = staged.min(start + super::PUT_BATCH as i64);
+            let batch = sqlx::query(&insert)
+                .bind(start)
+                .bind(end)
+                .execute(&mut *transaction)
+                .await;
+            match batch {
+                Ok(_) => continue,
+                // a failed statement is undone on its own, so the batch is written one object at
+                // a time to find those to reject
+                Err(sqlx::Error::Database(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+            for idx in start + 1..=end {
+                let object = sqlx::query(&insert)
+                    .bind(idx - 1)
+                    .bind(idx)
+                    .execute(&mut *transaction)
+                    .await;
+                match object {
+                    Ok(_) => {}
+                    Err(sqlx::Error::Database(e)) => {
+                        sqlx::query("UPDATE temp.PutStaging SET reason = ? WHERE idx = ?")
+                            .bind(super::rejection(target.view, e.as_ref()))
+                            .bind(idx)
+                            .execute(&mut *transaction)
+                            .await?;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+
+        for modified in target.modified(Dialect::Sqlite) {
+            sqlx::query(&modified).execute(&mut *transaction).await?;
+        }
+        // an object written again after it was purged is no longer deleted
+        sqlx::query(
+            "DELETE FROM Tombstones WHERE type = ?
+            AND sourcedId IN (SELECT sourcedId FROM temp.PutStaging WHERE reason IS NULL)",
+        )
+        .bind(target.column)
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+
+        let rejected = sqlx::query_as::<_, (Option<String>, String)>(
+            "SELECT sourcedId, reason FROM temp.PutStaging WHERE reason IS NOT NULL ORDER BY idx",
+        )
+        .fetch_all(&mut *connection)
+        .await?
+        .into_iter()
+        .map(|(sourced_id, reason)| Rejected { sourced_id, reason })
+        .collect::<Vec<_>>();
+        for staging in [
+            "DELETE FROM temp.PutStaging",
+            "DELETE FROM temp.PutPrevious",
+            "DELETE FROM temp.PutCurrent",
+        ] {
+            sqlx::query(staging).execute(&mut *connection).await?;
+        }
+        Ok(Written {
+            written: staged as u32 - rejected.len() as u32,
+            rejected,
+        })
+    }
+}
+
+/// A deferred transaction, which in WAL mode reads the database as it was at its first
+/// query without holding back writers
+struct SqliteSnapshot(sqlx::Transaction<'static, sqlx::Sqlite>);
+
+#[tide::utils::async_trait]
+impl Snapshot for SqliteSnapshot {
+    async fn count(&mut self, query: &str, binds: &[Bind]) -> Result<i64> {
+        let arguments = super::arguments::<sqlx::Sqlite>(binds)?;
+        Ok(sqlx::query_scalar_with(query, arguments)
+            .fetch_one(&mut *self.0)
+            .await?)
+    }
+
+    async fn keys(&mut self, query: &str, binds: &[Bind]) -> Result<Vec<Key>> {
+        let arguments = super::arguments::<sqlx::Sqlite>(binds)?;
+        Ok(sqlx::query_as_with(query, arguments)
+            .fetch_all(&mut *self.0)
+            .await?)
+    }
+
+    async fn objects(&mut self, query: &str, binds: &[Bind]) -> Result<Vec<(String, String)>> {
+        let arguments = super::arguments::<sqlx::Sqlite>(binds)?;
+        Ok(sqlx::query_as_with(query, arguments)
+            .fetch_all(&mut *self.0)
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn versions(store: &SqliteStore) -> Vec<i64> {
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations ORDER BY version")
+            .fetch_all(&store.pool)
+            .await
+            .unwrap()
+    }
+
+    #[async_std::test]
+    async fn migrates_an_unversioned_database() {
+        let path =
+            std::env::temp_dir().join(format!("oneroster_baseline_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let path = format!("sqlite:{}", path.to_str().unwrap());
+        let store = SqliteStore::open(&path, true, Pool::default())
+            .await
+            .unwrap();
+        // created from the schema before migrations were versioned
+        let baseline = include_str!("../../../db/migrations/sqlite/0001_initial.sql");
+        sqlx::raw_sql(baseline).execute(&store.pool).await.unwrap();
+        // written with the date as it was sent
+        sqlx::query(
+            "INSERT INTO Orgs (sourcedId, statusTypeId, dateLastModified, name, orgTypeId)
+            SELECT '015', s.id, '2012-04-23 18:25:43+01:00', 'school', o.id
+            FROM StatusType s, OrgType o WHERE s.token = 'active' AND o.token = 'school'",
+        )
+        .execute(&store.pool)
+        .await
+        .unwrap();
+
+        store.migrate().await.unwrap();
+        let latest: Vec<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+        assert_eq!(versions(&store).await, latest);
+        let date: String = sqlx::query_scalar("SELECT dateLastModified FROM Orgs")
+            .fetch_one(&store.pool)
+            .await
+            .unwrap();
+        assert_eq!(date, "2012-04-23T17:25:43.000Z");
+        let tombstones: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'Tombstones')",
+        )
+        .fetch_one(&store.pool)
+        .await
+        .unwrap();
+        assert!(tombstones);
+
+        // nothing is pending when migrated again
+        store.migrate().await.unwrap();
+        assert_eq!(versions(&store).await, latest);
+
+        sqlx::query(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+            VALUES (9999, 'newer', TRUE, x'00', 0)",
+        )
+        .execute(&store.pool)
+        .await
+        .unwrap();
+        let newer = store.migrate().await;
+        assert!(matches!(newer, Err(ServerError::NewerSchema(9999))));
+        store.close().await;
+    }
+}
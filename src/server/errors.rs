@@ -13,16 +13,29 @@ pub enum ServerError {
     Json(serde_json::Error),
     Io(std::io::Error),
     OpenSsl(openssl::error::ErrorStack),
+    Migrate(sqlx::migrate::MigrateError),
     InvalidLogin,
     NoAuthorizedScopes,
     NoPermission,
     NoBearerToken,
     NoRecordDeleted,
+    StillReferenced(String),
     NoContent,
-    InvalidFilterField,
+    UnknownObject(String),
+    InvalidFilterField(String),
+    InvalidSortField(String),
     InvalidParameters,
     InvalidBlankSelectionField,
+    SourcedIdMismatch,
+    UnsupportedContentEncoding(String),
+    InvalidBody(String),
+    InvalidObject(String),
+    SchemaViolations(Vec<String>),
+    InvalidSchema(String),
+    UnknownSchema(String),
+    PayloadTooLarge(u64),
     NoDatabaseFound,
+    NewerSchema(i64),
 }
 
 impl fmt::Display for ServerError {
@@ -36,18 +49,47 @@ impl fmt::Display for ServerError {
             ServerError::Json(ref e) => e.fmt(f),
             ServerError::Io(ref e) => e.fmt(f),
             ServerError::OpenSsl(ref e) => e.fmt(f),
+            ServerError::Migrate(ref e) => e.fmt(f),
             ServerError::InvalidLogin => write!(f, "Invalid username/password"),
             ServerError::NoAuthorizedScopes => write!(f, "No scopes were authorized for use"),
             ServerError::NoPermission => write!(f, "Incorrect scopes to access this resource"),
             ServerError::NoBearerToken => write!(f, "No bearer token found"),
             ServerError::NoRecordDeleted => write!(f, "No Record to delete"),
+            ServerError::StillReferenced(ref e) => {
+                write!(f, "Object is still needed by {}, which must be changed first", e)
+            }
             ServerError::NoContent => write!(f, "No Content"),
-            ServerError::InvalidFilterField => write!(f, "Invalid filter composition"),
+            ServerError::UnknownObject(ref e) => write!(f, "No object with sourcedId '{}'", e),
+            ServerError::InvalidFilterField(ref e) => write!(f, "Invalid filter: {}", e),
+            ServerError::InvalidSortField(ref e) => write!(f, "Invalid sort field '{}'", e),
             ServerError::InvalidParameters => write!(f, "Invalid parameter composition"),
-            ServerError::InvalidBlankSelectionField => write!(f, "Invalid field composition"),
+            ServerError::InvalidBlankSelectionField => {
+                write!(f, "Invalid fields, blank field names are not allowed")
+            }
+            ServerError::SourcedIdMismatch => {
+                write!(f, "sourcedId does not match the request path")
+            }
+            ServerError::UnsupportedContentEncoding(ref e) => {
+                write!(f, "Unsupported Content-Encoding '{}', use gzip or br", e)
+            }
+            ServerError::InvalidBody(ref e) => write!(f, "Could not read request body: {}", e),
+            ServerError::InvalidObject(ref e) => write!(f, "Object rejected: {}", e),
+            ServerError::SchemaViolations(ref e) => {
+                write!(f, "Body does not match its schema: {}", e.join("; "))
+            }
+            ServerError::InvalidSchema(ref e) => write!(f, "Invalid model schema: {}", e),
+            ServerError::UnknownSchema(ref e) => write!(f, "No schema named '{}'", e),
+            ServerError::PayloadTooLarge(limit) => {
+                write!(f, "Request body is larger than the {} byte limit", limit)
+            }
             ServerError::NoDatabaseFound => {
                 write!(f, "No database found, check path or use --init to create")
             }
+            ServerError::NewerSchema(version) => write!(
+                f,
+                "Database schema version {} is newer than this release supports, upgrade the server",
+                version
+            ),
         }
     }
 }
@@ -63,6 +105,7 @@ impl error::Error for ServerError {
             ServerError::Json(ref e) => Some(e),
             ServerError::Io(ref e) => Some(e),
             ServerError::OpenSsl(ref e) => Some(e),
+            ServerError::Migrate(ref e) => Some(e),
             _ => None,
         }
     }
@@ -81,6 +124,7 @@ macro_rules! into_error {
 into_error!(std::io::Error, ServerError::Io);
 into_error!(openssl::error::ErrorStack, ServerError::OpenSsl);
 into_error!(sqlx::Error, ServerError::Sqlx);
+into_error!(sqlx::migrate::MigrateError, ServerError::Migrate);
 into_error!(bcrypt::BcryptError, ServerError::Bcrypt);
 into_error!(std::time::SystemTimeError, ServerError::Time);
 into_error!(jsonwebtoken::errors::Error, ServerError::Jwt);
@@ -102,26 +146,93 @@ pub(crate) enum Severity {
     Warning,
 }
 
+// the spec tokens are not consistently cased
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub(crate) enum CodeMinor {
+    #[serde(rename = "fullsuccess")]
     FullSuccess,
+    #[serde(rename = "unknownobject")]
     UnknownObject,
+    #[serde(rename = "invaliddata")]
     InvalidData,
-    Unauthorized,
+    #[serde(rename = "unauthorisedrequest")]
+    UnauthorisedRequest,
     InvalidSortField,
     InvalidFilterField,
     InvalidSelectionField,
+    InvalidBlankSelectionField,
     Forbidden,
     ServerBusy,
+    InternalServerError,
 }
 
+/// The imsx_StatusInfo of a response, returned in a statusInfoSet
 #[derive(Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
 pub(crate) struct ErrorPayload {
+    #[serde(rename = "imsx_codeMajor")]
     pub(crate) code_major: CodeMajor,
+    #[serde(rename = "imsx_severity")]
     pub(crate) severity: Severity,
+    #[serde(rename = "imsx_CodeMinor", with = "code_minor_field")]
     pub(crate) code_minor: CodeMinor,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "imsx_description", skip_serializing_if = "Option::is_none")]
     pub(crate) description: Option<String>,
 }
+
+impl ErrorPayload {
+    /// the status of a request which failed
+    pub(crate) fn failure(code_minor: CodeMinor, description: Option<String>) -> Self {
+        Self {
+            code_major: CodeMajor::Failure,
+            severity: Severity::Error,
+            code_minor,
+            description,
+        }
+    }
+}
+
+/// The codeMinor is given as the value of the TargetEndSystem field
+/// e.g. { "imsx_codeMinorField": [{ "imsx_codeMinorFieldName": "TargetEndSystem", ... }] }
+mod code_minor_field {
+    use super::CodeMinor;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize, Serialize)]
+    struct Fields<T> {
+        #[serde(rename = "imsx_codeMinorField")]
+        fields: Vec<Field<T>>,
+    }
+
+    #[derive(Deserialize, Serialize)]
+    struct Field<T> {
+        #[serde(rename = "imsx_codeMinorFieldName")]
+        name: String,
+        #[serde(rename = "imsx_codeMinorFieldValue")]
+        value: T,
+    }
+
+    pub(super) fn serialize<S: Serializer>(
+        code_minor: &CodeMinor,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let field = Field {
+            name: "TargetEndSystem".to_string(),
+            value: code_minor,
+        };
+        Fields {
+            fields: vec![field],
+        }
+        .serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<CodeMinor, D::Error> {
+        let fields = Fields::<CodeMinor>::deserialize(deserializer)?;
+        match fields.fields.into_iter().next() {
+            Some(field) => Ok(field.value),
+            None => Err(de::Error::invalid_length(0, &"a codeMinor field")),
+        }
+    }
+}
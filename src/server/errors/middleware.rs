@@ -1,5 +1,6 @@
 use super::*;
 use crate::server;
+use http_types::StatusCode;
 use serde_json::json;
 
 pub(crate) struct ApiError {}
@@ -21,125 +22,418 @@ impl tide::Middleware<server::State> for tide::utils::After<ApiError> {
         log::trace!("{:?}", r);
         if let Some(err) = r.downcast_error::<ServerError>() {
             log::warn!("API request error: {:?}", err);
-            match err {
-                ServerError::NoAuthorizedScopes
-                | ServerError::NoBearerToken
-                | ServerError::NoPermission
-                | ServerError::InvalidLogin => {
-                    let ep = ErrorPayload {
-                        code_major: CodeMajor::Failure,
-                        code_minor: CodeMinor::Unauthorized,
-                        description: Some(format!("{}", err)),
-                        severity: Severity::Error,
-                    };
-                    r.set_status(403);
-                    r.set_body(json!(ep));
-                }
-                ServerError::Jwt(_) => {
-                    let ep = ErrorPayload {
-                        code_major: CodeMajor::Failure,
-                        code_minor: CodeMinor::Unauthorized,
-                        description: Some(format!("Invalid token")),
-                        severity: Severity::Error,
-                    };
-                    r.set_status(403);
-                    r.set_body(json!(ep));
-                }
-                ServerError::NoRecordDeleted => {
-                    let ep = ErrorPayload {
-                        code_major: CodeMajor::Failure,
-                        code_minor: CodeMinor::UnknownObject,
-                        description: Some(format!("{}", err)),
-                        severity: Severity::Error,
-                    };
-                    r.set_status(404);
-                    r.set_body(json!(ep));
-                }
-                ServerError::Sqlx(ref e) => {
-                    // TODO: handle errors for FK constraints?
-                    log::error!("API sql error: {:?}", e);
-                    let ep = ErrorPayload {
-                        code_major: CodeMajor::Failure,
-                        code_minor: CodeMinor::InvalidData, // None?
-                        description: Some(
-                            "Data rejected, try verifying parent \
-                            hierarchy insert order \
-                            (parent>child over child>parent) \
-                            or consult server logs for detailed error"
-                                .to_string(),
-                        ),
-                        severity: Severity::Error,
-                    };
-                    r.set_status(400);
-                    r.set_body(json!(ep));
-                }
-                ServerError::Bcrypt(ref e) => {
-                    log::error!("API bcrypt error: {}", e);
-                    let ep = ErrorPayload {
-                        code_major: CodeMajor::Failure,
-                        code_minor: CodeMinor::Forbidden, // None?
-                        description: None,
-                        severity: Severity::Error,
-                    };
-                    r.set_status(500);
-                    r.set_body(json!(ep));
-                }
-                ServerError::Time(ref e) => {
-                    log::error!("API time error: {}", e);
-                    let ep = ErrorPayload {
-                        code_major: CodeMajor::Failure,
-                        code_minor: CodeMinor::Forbidden, // None?
-                        description: None,
-                        severity: Severity::Error,
-                    };
-                    r.set_status(500);
-                    r.set_body(json!(ep));
-                }
-                ServerError::Regex(ref e) => {
-                    log::error!("API regex error: {}", e);
-                    let ep = ErrorPayload {
-                        code_major: CodeMajor::Failure,
-                        code_minor: CodeMinor::Forbidden, // None?
-                        description: None,
-                        severity: Severity::Error,
-                    };
-                    r.set_status(500);
-                    r.set_body(json!(ep));
-                }
-                ServerError::Json(ref e) => {
-                    log::error!("API serde_json error: {}", e);
-                    let ep = ErrorPayload {
-                        code_major: CodeMajor::Failure,
-                        code_minor: CodeMinor::InvalidData, // None?
-                        description: Some(format!("{}", e)),
-                        severity: Severity::Error,
-                    };
-                    r.set_status(400);
-                    r.set_body(json!(ep));
-                }
-                ServerError::InvalidFilterField
-                | ServerError::InvalidParameters
-                | ServerError::InvalidBlankSelectionField => {
-                    let ep = ErrorPayload {
-                        code_major: CodeMajor::Failure,
-                        code_minor: CodeMinor::InvalidData,
-                        description: Some(format!("{}", err)),
-                        severity: Severity::Error,
-                    };
-                    r.set_status(400);
-                    r.set_body(json!(ep));
-                }
-                ServerError::NoContent => {
-                    r.set_status(204);
-                }
-                ServerError::NoDatabaseFound => {
-                    r.set_status(500);
-                }
-                _ => {
-                    r.set_status(500);
-                }
+            let (status, status_info_set) = status_info(err);
+            r.set_status(status);
+            if !status_info_set.is_empty() {
+                r.set_body(json!({ "statusInfoSet": status_info_set }));
             }
-        };
+        } else if let Some(err) = r.error() {
+            // errors raised by tide, such as an invalid query string or form body
+            log::warn!("API request error: {:?}", err);
+            let status = match err.status() {
+                StatusCode::UnprocessableEntity => StatusCode::BadRequest,
+                status => status,
+            };
+            let description = match status.is_server_error() {
+                true => None,
+                false => Some(err.to_string()),
+            };
+            let ep = ErrorPayload::failure(code_minor(status), description);
+            r.set_status(status);
+            r.set_body(json!({ "statusInfoSet": [ep] }));
+        } else if (r.status().is_client_error() || r.status().is_server_error())
+            && r.is_empty() != Some(false)
+        {
+            // e.g. an unknown path or method
+            let ep = ErrorPayload::failure(
+                code_minor(r.status()),
+                Some(r.status().canonical_reason().to_string()),
+            );
+            r.set_body(json!({ "statusInfoSet": [ep] }));
+        }
         Ok(r)
     }
 }
+
+/// The HTTP status and statusInfoSet of an error, internal errors are logged rather than
+/// described
+fn status_info(err: &ServerError) -> (StatusCode, Vec<ErrorPayload>) {
+    let description = Some(err.to_string());
+    match err {
+        ServerError::NoBearerToken | ServerError::InvalidLogin => failure(
+            StatusCode::Unauthorized,
+            CodeMinor::UnauthorisedRequest,
+            description,
+        ),
+        ServerError::Jwt(_) => failure(
+            StatusCode::Unauthorized,
+            CodeMinor::UnauthorisedRequest,
+            Some("Invalid token".to_string()),
+        ),
+        ServerError::NoAuthorizedScopes | ServerError::NoPermission => {
+            failure(StatusCode::Forbidden, CodeMinor::Forbidden, description)
+        }
+        ServerError::NoContent => (StatusCode::NoContent, Vec::new()),
+        ServerError::NoRecordDeleted
+        | ServerError::UnknownObject(_)
+        | ServerError::UnknownSchema(_) => {
+            failure(StatusCode::NotFound, CodeMinor::UnknownObject, description)
+        }
+        ServerError::StillReferenced(_) => {
+            failure(StatusCode::Conflict, CodeMinor::InvalidData, description)
+        }
+        ServerError::InvalidFilterField(_) => failure(
+            StatusCode::BadRequest,
+            CodeMinor::InvalidFilterField,
+            description,
+        ),
+        ServerError::InvalidSortField(_) => failure(
+            StatusCode::BadRequest,
+            CodeMinor::InvalidSortField,
+            description,
+        ),
+        ServerError::InvalidBlankSelectionField => failure(
+            StatusCode::BadRequest,
+            CodeMinor::InvalidBlankSelectionField,
+            description,
+        ),
+        ServerError::Json(ref e) => failure(
+            StatusCode::BadRequest,
+            CodeMinor::InvalidData,
+            Some(e.to_string()),
+        ),
+        ServerError::InvalidParameters
+        | ServerError::SourcedIdMismatch
+        | ServerError::InvalidBody(_)
+        | ServerError::InvalidObject(_) => {
+            failure(StatusCode::BadRequest, CodeMinor::InvalidData, description)
+        }
+        ServerError::SchemaViolations(ref violations) => {
+            let status_info_set = violations
+                .iter()
+                .map(|v| ErrorPayload::failure(CodeMinor::InvalidData, Some(v.clone())))
+                .collect();
+            (StatusCode::BadRequest, status_info_set)
+        }
+        ServerError::UnsupportedContentEncoding(_) => failure(
+            StatusCode::UnsupportedMediaType,
+            CodeMinor::InvalidData,
+            description,
+        ),
+        ServerError::PayloadTooLarge(_) => failure(
+            StatusCode::PayloadTooLarge,
+            CodeMinor::InvalidData,
+            description,
+        ),
+        ServerError::Sqlx(ref e) => sqlx_status_info(e),
+        ServerError::Bcrypt(_)
+        | ServerError::Time(_)
+        | ServerError::Regex(_)
+        | ServerError::Io(_)
+        | ServerError::OpenSsl(_)
+        | ServerError::InvalidSchema(_)
+        | ServerError::Migrate(_)
+        | ServerError::NewerSchema(_)
+        | ServerError::NoDatabaseFound => {
+            log::error!("API internal error: {}", err);
+            internal_error()
+        }
+    }
+}
+
+fn failure(
+    status: StatusCode,
+    code_minor: CodeMinor,
+    description: Option<String>,
+) -> (StatusCode, Vec<ErrorPayload>) {
+    (status, vec![ErrorPayload::failure(code_minor, description)])
+}
+
+/// Constraint failures are the client's data, other database errors are internal
+fn sqlx_status_info(err: &sqlx::Error) -> (StatusCode, Vec<ErrorPayload>) {
+    match err {
+        sqlx::Error::RowNotFound => failure(
+            StatusCode::NotFound,
+            CodeMinor::UnknownObject,
+            Some("Object not found".to_string()),
+        ),
+        sqlx::Error::PoolTimedOut => failure(
+            StatusCode::TooManyRequests,
+            CodeMinor::ServerBusy,
+            Some("Server busy, try again later".to_string()),
+        ),
+        sqlx::Error::Database(e) => match e.kind() {
+            sqlx::error::ErrorKind::ForeignKeyViolation => failure(
+                StatusCode::BadRequest,
+                CodeMinor::InvalidData,
+                Some(
+                    "Data rejected, an object it refers to does not exist \
+                    or it is still referred to by other objects"
+                        .to_string(),
+                ),
+            ),
+            sqlx::error::ErrorKind::UniqueViolation
+            | sqlx::error::ErrorKind::NotNullViolation
+            | sqlx::error::ErrorKind::CheckViolation => failure(
+                StatusCode::BadRequest,
+                CodeMinor::InvalidData,
+                Some(format!("Data rejected: {}", e.message())),
+            ),
+            // SQLITE_BUSY and SQLITE_LOCKED, or a postgres serialization failure,
+            // deadlock or lock not available
+            _ if matches!(
+                e.code().as_deref(),
+                Some("5" | "6" | "40001" | "40P01" | "55P03")
+            ) =>
+            {
+                failure(
+                    StatusCode::TooManyRequests,
+                    CodeMinor::ServerBusy,
+                    Some("Server busy, try again later".to_string()),
+                )
+            }
+            _ => {
+                log::error!("API sql error: {:?}", e);
+                internal_error()
+            }
+        },
+        e => {
+            log::error!("API sql error: {:?}", e);
+            internal_error()
+        }
+    }
+}
+
+fn internal_error() -> (StatusCode, Vec<ErrorPayload>) {
+    failure(
+        StatusCode::InternalServerError,
+        CodeMinor::InternalServerError,
+        None,
+    )
+}
+
+/// the codeMinor of an error response from its status
+fn code_minor(status: StatusCode) -> CodeMinor {
+    match status {
+        StatusCode::Unauthorized => CodeMinor::UnauthorisedRequest,
+        StatusCode::Forbidden => CodeMinor::Forbidden,
+        StatusCode::NotFound => CodeMinor::UnknownObject,
+        StatusCode::TooManyRequests | StatusCode::ServiceUnavailable => CodeMinor::ServerBusy,
+        status if status.is_server_error() => CodeMinor::InternalServerError,
+        _ => CodeMinor::InvalidData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::Connection;
+
+    /// the status, codeMinor of each statusInfo and whether each is described
+    fn info(err: ServerError) -> (u16, Vec<serde_json::Value>, Vec<bool>) {
+        let (status, status_info_set) = status_info(&err);
+        let code_minor = status_info_set
+            .iter()
+            .map(|s| json!(s.code_minor))
+            .collect();
+        let described = status_info_set
+            .iter()
+            .map(|s| s.description.is_some())
+            .collect();
+        (status as u16, code_minor, described)
+    }
+
+    fn failure(
+        status: u16,
+        code_minor: CodeMinor,
+        described: bool,
+    ) -> (u16, Vec<serde_json::Value>, Vec<bool>) {
+        (status, vec![json!(code_minor)], vec![described])
+    }
+
+    #[test]
+    fn maps_each_error_to_a_status() {
+        let jwt = jsonwebtoken::errors::ErrorKind::InvalidToken.into();
+        let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let io = std::io::Error::other("disk");
+        for (err, expected) in [
+            (
+                ServerError::NoBearerToken,
+                failure(401, CodeMinor::UnauthorisedRequest, true),
+            ),
+            (
+                ServerError::InvalidLogin,
+                failure(401, CodeMinor::UnauthorisedRequest, true),
+            ),
+            (
+                ServerError::Jwt(jwt),
+                failure(401, CodeMinor::UnauthorisedRequest, true),
+            ),
+            (
+                ServerError::NoAuthorizedScopes,
+                failure(403, CodeMinor::Forbidden, true),
+            ),
+            (
+                ServerError::NoPermission,
+                failure(403, CodeMinor::Forbidden, true),
+            ),
+            (ServerError::NoContent, (204, vec![], vec![])),
+            (
+                ServerError::NoRecordDeleted,
+                failure(404, CodeMinor::UnknownObject, true),
+            ),
+            (
+                ServerError::StillReferenced("x".into()),
+                failure(409, CodeMinor::InvalidData, true),
+            ),
+            (
+                ServerError::UnknownObject("x".into()),
+                failure(404, CodeMinor::UnknownObject, true),
+            ),
+            (
+                ServerError::UnknownSchema("x".into()),
+                failure(404, CodeMinor::UnknownObject, true),
+            ),
+            (
+                ServerError::InvalidFilterField("x".into()),
+                failure(400, CodeMinor::InvalidFilterField, true),
+            ),
+            (
+                ServerError::InvalidSortField("x".into()),
+                failure(400, CodeMinor::InvalidSortField, true),
+            ),
+            (
+                ServerError::InvalidBlankSelectionField,
+                failure(400, CodeMinor::InvalidBlankSelectionField, true),
+            ),
+            (
+                ServerError::Json(json),
+                failure(400, CodeMinor::InvalidData, true),
+            ),
+            (
+                ServerError::InvalidParameters,
+                failure(400, CodeMinor::InvalidData, true),
+            ),
+            (
+                ServerError::SourcedIdMismatch,
+                failure(400, CodeMinor::InvalidData, true),
+            ),
+            (
+                ServerError::InvalidBody("x".into()),
+                failure(400, CodeMinor::InvalidData, true),
+            ),
+            (
+                ServerError::InvalidObject("x".into()),
+                failure(400, CodeMinor::InvalidData, true),
+            ),
+            (
+                ServerError::SchemaViolations(vec!["/a".into(), "/b".into()]),
+                (400, vec![json!(CodeMinor::InvalidData); 2], vec![true; 2]),
+            ),
+            (
+                ServerError::UnsupportedContentEncoding("x".into()),
+                failure(415, CodeMinor::InvalidData, true),
+            ),
+            (
+                ServerError::PayloadTooLarge(1),
+                failure(413, CodeMinor::InvalidData, true),
+            ),
+            (
+                ServerError::Sqlx(sqlx::Error::RowNotFound),
+                failure(404, CodeMinor::UnknownObject, true),
+            ),
+            (
+                ServerError::Sqlx(sqlx::Error::PoolTimedOut),
+                failure(429, CodeMinor::ServerBusy, true),
+            ),
+            (
+                ServerError::Sqlx(sqlx::Error::PoolClosed),
+                failure(500, CodeMinor::InternalServerError, false),
+            ),
+            (
+                ServerError::Io(io),
+                failure(500, CodeMinor::InternalServerError, false),
+            ),
+            (
+                ServerError::InvalidSchema("x".into()),
+                failure(500, CodeMinor::InternalServerError, false),
+            ),
+            (
+                ServerError::NewerSchema(9),
+                failure(500, CodeMinor::InternalServerError, false),
+            ),
+            (
+                ServerError::NoDatabaseFound,
+                failure(500, CodeMinor::InternalServerError, false),
+            ),
+        ] {
+            let name = format!("{:?}", err);
+            assert_eq!(info(err), expected, "{}", name);
+        }
+    }
+
+    #[async_std::test]
+    async fn rejects_data_failing_a_constraint() {
+        let mut db = sqlx::SqliteConnection::connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::raw_sql(
+            "PRAGMA foreign_keys = 1;
+            CREATE TABLE Orgs (sourcedId TEXT PRIMARY KEY, name TEXT NOT NULL);
+            CREATE TABLE Users (orgSourcedId TEXT REFERENCES Orgs (sourcedId));
+            INSERT INTO Orgs VALUES ('1', 'org');",
+        )
+        .execute(&mut db)
+        .await
+        .unwrap();
+        for statement in [
+            "INSERT INTO Users VALUES ('2')",
+            "INSERT INTO Orgs VALUES ('1', 'again')",
+            "INSERT INTO Orgs VALUES ('3', NULL)",
+        ] {
+            let err = sqlx::query(statement).execute(&mut db).await.unwrap_err();
+            let expected = failure(400, CodeMinor::InvalidData, true);
+            assert_eq!(info(ServerError::Sqlx(err)), expected, "{}", statement);
+        }
+        let err = sqlx::query("SELECT * FROM Unknown")
+            .execute(&mut db)
+            .await
+            .unwrap_err();
+        let expected = failure(500, CodeMinor::InternalServerError, false);
+        assert_eq!(info(ServerError::Sqlx(err)), expected);
+    }
+
+    #[async_std::test]
+    async fn describes_errors_raised_outside_the_api() {
+        let mut app = tide::with_state(server::test_state("api_error").await);
+        app.with(tide::utils::After(ApiError::new()));
+        app.at("/json")
+            .post(|mut req: tide::Request<server::State>| async move {
+                let json: serde_json::Value = req.body_json().await?;
+                Ok(json)
+            });
+        for (req, status, code_minor) in [
+            (
+                http_types::Request::get("https://localhost/unknown"),
+                404,
+                "unknownobject",
+            ),
+            // tide rejects an unreadable body as unprocessable
+            (
+                http_types::Request::post("https://localhost/json"),
+                400,
+                "invaliddata",
+            ),
+        ] {
+            let mut req = req;
+            req.set_body("{");
+            let mut res: http_types::Response = app.respond(req).await.unwrap();
+            assert_eq!(res.status(), status);
+            let body: serde_json::Value = res.body_json().await.unwrap();
+            let info = &body["statusInfoSet"][0];
+            let field = &info["imsx_CodeMinor"]["imsx_codeMinorField"][0];
+            assert_eq!(field["imsx_codeMinorFieldValue"], code_minor);
+            assert!(info["imsx_description"].is_string());
+        }
+    }
+}
@@ -1,15 +1,25 @@
+mod fields;
+mod filter;
+
+pub(crate) use fields::exists;
+
+use super::errors::{CodeMajor, CodeMinor, ErrorPayload, Severity};
 use super::{Result, ServerError, State};
-use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub(crate) struct Parameters {
-    pub(crate) limit: u32,  // 10
-    pub(crate) offset: u32, // 20
-    pub(crate) sort: Option<String>,
+    pub(crate) limit: u32,           // 10
+    pub(crate) offset: u32,          // 20
+    pub(crate) sort: Option<String>, // familyName,givenName
+    #[serde(rename = "orderBy")]
+    pub(crate) order_by: Option<String>, // asc | desc
     pub(crate) filter: Option<String>, // name=bob AND age>20
     pub(crate) fields: Option<String>, // name,age
+    /// how each filter and sort field of the returned objects is read from the stored json
+    #[serde(skip)]
+    pub(crate) stored: fn(&str) -> Option<Stored>,
 }
 
 impl Default for Parameters {
@@ -18,137 +28,704 @@ impl Default for Parameters {
             limit: 100,
             offset: 0,
             sort: None,
+            order_by: None,
             filter: None,
             fields: None,
+            stored: as_stored,
         }
     }
 }
 
-pub(crate) async fn apply_parameters(
-    json: &String,
-    params: &Parameters,
-    wrapper: &str,
-) -> Result<(String, String)> {
-    let (query, length) = parameter_builder(&params, wrapper).await?;
-    let output = jq_rs::run(&query, &json).map_err(|e| {
-        log::debug!("{}", json);
-        log::debug!("jq error: {}: query: {}", e, query);
-        ServerError::InvalidParameters
-    })?;
-    let total = jq_rs::run(&length, &json).map_err(|_| ServerError::InvalidParameters)?;
-    Ok((output, total))
+/// How a filter or sort field of the returned objects is read from the stored json
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Stored {
+    /// at the same path as it is returned
+    Path,
+    /// computed from the stored json by an SQL text expression, for a field whose value
+    /// is converted when it is returned
+    Computed(fn(Dialect) -> String),
 }
 
-async fn parameter_builder(params: &Parameters, wrapper: &str) -> Result<(String, String)> {
-    let mut builder = format!("{{ {}: [.{}[] ", wrapper, wrapper);
+/// every field of objects returned as they are stored is read from the same path
+fn as_stored(_field: &str) -> Option<Stored> {
+    Some(Stored::Path)
+}
 
-    if let Some(fields) = parse_fields(params).await {
-        let f = format!("| {{ {} }} ", fields);
-        builder.push_str(&f);
+/// Reads the request parameters, rejecting a limit of zero, which would never reach the
+/// last page, or over the configured maximum
+pub(crate) fn query(req: &tide::Request<State>) -> tide::Result<Parameters> {
+    let params: Parameters = req.query()?;
+    if params.limit == 0 || params.limit > req.state().max_limit {
+        return Err(ServerError::InvalidParameters.into());
     }
+    Ok(params)
+}
+
+/// SQL clauses built from the request parameters
+/// each clause is applied to the `data` json column of a collection query, with the
+/// field paths and values passed as bind parameters in the order they appear
+#[derive(Debug, Default)]
+pub(crate) struct Clauses {
+    pub(crate) filter: String,
+    pub(crate) filter_binds: Vec<Bind>,
+    pub(crate) order: String,
+    pub(crate) order_binds: Vec<Bind>,
+}
+
+/// A typed SQL bind parameter
+#[derive(Debug, Clone)]
+pub(crate) enum Bind {
+    Text(String),
+    Number(f64),
+    Integer(i64),
+}
 
-    if let Some(filter) = parse_filter(params).await? {
-        let f = format!("| select({}) ", filter);
-        builder.push_str(&f);
+/// The SQL dialect of the database a query is run against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Dialect {
+    Sqlite,
+    Postgres,
+}
+
+impl Dialect {
+    /// a json column or value read as text
+    pub(crate) fn text(self, json: &str) -> String {
+        match self {
+            Dialect::Sqlite => json.to_string(),
+            Dialect::Postgres => format!("CAST({} AS text)", json),
+        }
     }
 
-    builder.push_str("] ");
-    let mut len: String = builder.clone();
-    len.push_str(&format!("| length }} | .{}", wrapper));
+    /// a json object of a view without its dateLastModified, to compare its content
+    pub(crate) fn undated(self, json: &str) -> String {
+        match self {
+            Dialect::Sqlite => format!("json_remove({}, '$.dateLastModified')", json),
+            Dialect::Postgres => format!("{} - 'dateLastModified'", json),
+        }
+    }
 
-    let pagination = format!("| .[{}:{}]", params.offset, params.offset + params.limit);
-    builder.push_str(&pagination);
+    /// the current time in the UTC format dateLastModified is stored in
+    pub(crate) fn now(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')",
+            Dialect::Postgres => "utc_now()",
+        }
+    }
 
-    if let Some(sort) = parse_sort(params).await {
-        let f = format!("| sort_by(.{}) ", sort);
-        builder.push_str(&f);
+    /// the value of the `data` json column at a bound json path as text, booleans as
+    /// 'true' or 'false'. Text is compared bytewise in both databases
+    fn field_text(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => {
+                "CASE json_type(data, ?) WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' \
+                ELSE CAST(json_extract(data, ?) AS TEXT) END"
+            }
+            Dialect::Postgres => {
+                "(jsonb_path_query_first(data, CAST(? AS jsonpath), '{}', true) #>> '{}') COLLATE \"C\""
+            }
+        }
     }
-    builder.push_str("} ");
 
-    log::debug!("parameter jq builder: {}", builder);
-    Ok((builder, len))
-}
+    /// whether the value at a bound json path is a number, and that number
+    fn field_number(self) -> (&'static str, &'static str) {
+        match self {
+            Dialect::Sqlite => (
+                "json_type(data, ?) IN ('integer', 'real')",
+                "json_extract(data, ?)",
+            ),
+            Dialect::Postgres => (
+                "jsonb_typeof(jsonb_path_query_first(data, CAST(? AS jsonpath), '{}', true)) = 'number'",
+                "CAST(jsonb_path_query_first(data, CAST(? AS jsonpath), '{}', true) #>> '{}' AS double precision)",
+            ),
+        }
+    }
+
+    /// the value at a fixed path of the `data` json column as text, each part of the path
+    /// is a field name or an array index
+    pub(crate) fn value(self, path: &[&str]) -> String {
+        match self {
+            Dialect::Sqlite => {
+                let path: String = path
+                    .iter()
+                    .map(|p| match p.parse::<usize>() {
+                        Ok(i) => format!("[{}]", i),
+                        Err(_) => format!(".{}", p),
+                    })
+                    .collect();
+                format!("CAST(json_extract(data, '${}') AS TEXT)", path)
+            }
+            Dialect::Postgres => format!("(data #>> '{{{}}}')", path.join(",")),
+        }
+    }
+
+    /// a text expression compared bytewise, as text is in sqlite
+    fn bytewise(self, text: &str) -> String {
+        match self {
+            Dialect::Sqlite => text.to_string(),
+            Dialect::Postgres => format!("({}) COLLATE \"C\"", text),
+        }
+    }
+
+    /// whether `text` contains a bound value
+    fn contains(self, text: &str) -> String {
+        match self {
+            Dialect::Sqlite => format!("instr({}, ?) > 0", text),
+            Dialect::Postgres => format!("strpos({}, ?) > 0", text),
+        }
+    }
+
+    /// the sort key of the value at a bound json path, ordered as sqlite orders json values
+    fn sort_key(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => "json_extract(data, ?)",
+            Dialect::Postgres => {
+                "json_sort_key(jsonb_path_query_first(data, CAST(? AS jsonpath), '{}', true))"
+            }
+        }
+    }
 
-async fn parse_sort(params: &Parameters) -> Option<String> {
-    if let Some(q_sort) = &params.sort {
-        return Some(q_sort.to_owned());
+    /// the dateLastModified of the `data` json column as text
+    pub(crate) fn date_last_modified(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => "json_extract(data, '$.dateLastModified')",
+            Dialect::Postgres => "data ->> 'dateLastModified'",
+        }
+    }
+
+    /// the sourcedId of the `data` json column, compared bytewise
+    fn sourced_id(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => "json_extract(data, '$.sourcedId')",
+            Dialect::Postgres => "(data ->> 'sourcedId') COLLATE \"C\"",
+        }
     }
-    None
 }
 
-async fn parse_filter(params: &Parameters) -> Result<Option<String>> {
-    if let Some(q_filter) = &params.filter {
-        let rlogic = Regex::new(r" (AND|OR) ")?;
-        let mut logicals: Vec<String> = Vec::new();
-        for cap in rlogic.captures_iter(q_filter) {
-            if &cap[1] == "AND" {
-                logicals.push("and".to_string());
-            } else {
-                logicals.push("or".to_string());
+/// SQL and the values bound to its parameters
+type Bound = (String, Vec<Bind>);
+
+/// A filter or sort field read from the `data` json column
+#[derive(Debug)]
+enum Field {
+    /// a json path, bound to each placeholder of the expression reading it
+    Path(String),
+    /// an SQL text expression
+    Computed(String),
+}
+
+impl Field {
+    /// the expression reading a json path, with the path bound to each of its placeholders
+    fn bound(sql: &str, path: &str) -> Bound {
+        let binds = vec![Bind::Text(path.to_string()); sql.matches('?').count()];
+        (sql.to_string(), binds)
+    }
+
+    /// the field as text, booleans as 'true' or 'false'
+    fn text(&self, dialect: Dialect) -> Bound {
+        match self {
+            Field::Path(path) => Field::bound(dialect.field_text(), path),
+            Field::Computed(sql) => (dialect.bytewise(sql), Vec::new()),
+        }
+    }
+
+    /// whether the field is a number, and that number. A computed field is always text
+    fn number(&self, dialect: Dialect) -> Option<(Bound, Bound)> {
+        match self {
+            Field::Path(path) => {
+                let (is_number, number) = dialect.field_number();
+                Some((Field::bound(is_number, path), Field::bound(number, path)))
             }
+            Field::Computed(_) => None,
         }
+    }
 
-        let raw_filters: Vec<&str> = rlogic.split(q_filter).collect();
-        let mut filters: Vec<String> = Vec::new();
-        for raw in raw_filters {
-            let rfilter = Regex::new(r"(\w*)(!=|>=|<=|>|<|=|~)'(.*)'")?;
-            for cap in rfilter.captures_iter(raw) {
-                let mut predicate = &cap[2];
-                if predicate == "=" {
-                    predicate = "==";
-                };
-                let filter = format!(".{} {} \"{}\"", &cap[1], predicate, &cap[3].trim());
-                log::debug!("filter: {}", filter);
-                filters.push(filter);
+    /// the key the field is sorted by
+    fn sort_key(&self, dialect: Dialect) -> Bound {
+        match self {
+            Field::Path(path) => Field::bound(dialect.sort_key(), path),
+            Field::Computed(sql) => (dialect.bytewise(sql), Vec::new()),
+        }
+    }
+}
+
+/// finds a filter or sort field of the returned objects in the objects `T` read from the
+/// database, or why it cannot be read when either does not have it or it is inside an array
+fn field<T: DeserializeOwned>(
+    params: &Parameters,
+    name: &str,
+    dialect: Dialect,
+) -> std::result::Result<Field, &'static str> {
+    const UNKNOWN: &str = "unknown field";
+    match (params.stored)(name).ok_or(UNKNOWN)? {
+        Stored::Path if fields::exists_outside_arrays::<T>(name) => {
+            json_path(name, dialect).map(Field::Path).ok_or(UNKNOWN)
+        }
+        Stored::Path if fields::exists::<T>(name) => Err("field inside an array"),
+        Stored::Path => Err(UNKNOWN),
+        Stored::Computed(sql) => Ok(Field::Computed(sql(dialect))),
+    }
+}
+
+/// `table` is the entity table of the collection, used to find changes by dateLastModified.
+/// Filter and sort fields are checked against the objects `T` read from the database
+pub(crate) async fn sql_clauses<T: DeserializeOwned>(
+    params: &Parameters,
+    table: &str,
+    dialect: Dialect,
+) -> Result<Clauses> {
+    let mut clauses = Clauses {
+        filter: "1 = 1".to_string(),
+        order: dialect.sourced_id().to_string(),
+        ..Default::default()
+    };
+
+    if let Some(q_filter) = &params.filter {
+        let field = |name: &str| field::<T>(params, name, dialect);
+        let (filter, binds) = filter::parse(q_filter, table, dialect, &field)?;
+        clauses.filter = filter;
+        clauses.filter_binds = binds;
+    }
+
+    if let Some(fields) = parse_sort::<T>(params, dialect).await? {
+        let direction = parse_order_by(params).await?;
+        let mut order: Vec<String> = fields
+            .into_iter()
+            .map(|field| {
+                let (key, binds) = field.sort_key(dialect);
+                clauses.order_binds.extend(binds);
+                format!("{} {}", key, direction)
+            })
+            .collect();
+        // sourcedId is unique so breaks any ties, giving stable pages
+        order.push(format!("{} {}", dialect.sourced_id(), direction));
+        clauses.order = order.join(", ");
+    }
+
+    log::debug!("parameter sql clauses: {:?}", clauses);
+    Ok(clauses)
+}
+
+/// The fields requested in the fields parameter which exist on the returned objects,
+/// with a statusInfoSet warning for each unknown field which is ignored
+pub(crate) struct Selection {
+    paths: Option<Vec<Vec<String>>>,
+    pub(crate) warnings: Vec<ErrorPayload>,
+}
+
+impl Selection {
+    /// keeps only the selected fields of an object, if no requested fields are known
+    /// the full object is kept
+    pub(crate) fn project(&self, object: serde_json::Value) -> serde_json::Value {
+        match &self.paths {
+            Some(paths) => {
+                let paths: Vec<&[String]> = paths.iter().map(|p| p.as_slice()).collect();
+                fields::project(&object, &paths)
             }
+            None => object,
         }
+    }
+}
 
-        let mut filter_builder: Vec<String> = Vec::new();
-        filter_builder.push(filters.pop().ok_or(ServerError::InvalidFilterField)?);
-        for _ in 0..logicals.len() {
-            filter_builder.push(logicals.pop().ok_or(ServerError::InvalidFilterField)?);
-            filter_builder.push(filters.pop().ok_or(ServerError::InvalidFilterField)?);
+/// Checks the fields parameter against the objects `T` of a collection
+pub(crate) async fn selection<T: DeserializeOwned>(params: &Parameters) -> Result<Selection> {
+    let mut selection = Selection {
+        paths: None,
+        warnings: Vec::new(),
+    };
+    if let Some(fields) = parse_fields(params).await? {
+        let (known, unknown): (Vec<&str>, Vec<&str>) =
+            fields.into_iter().partition(|f| fields::exists::<T>(f));
+        if !known.is_empty() {
+            selection.paths = Some(
+                known
+                    .iter()
+                    .map(|f| f.split('.').map(String::from).collect())
+                    .collect(),
+            );
         }
+        selection.warnings = unknown
+            .iter()
+            .map(|f| ErrorPayload {
+                code_major: CodeMajor::Success,
+                code_minor: CodeMinor::InvalidSelectionField,
+                description: Some(format!("Unknown field '{}' ignored", f)),
+                severity: Severity::Warning,
+            })
+            .collect();
+    }
+    Ok(selection)
+}
+
+/// converts a field name, including dotted sub-fields, into a json path for json_extract
+/// or a postgres jsonpath. Each name is quoted as it may start with a digit, and the path
+/// is strict so arrays are not searched, as with json_extract
+fn json_path(field: &str, dialect: Dialect) -> Option<String> {
+    let valid = field
+        .split('.')
+        .all(|f| !f.is_empty() && f.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+    valid.then(|| match dialect {
+        Dialect::Sqlite => format!("$.{}", field),
+        Dialect::Postgres => format!("strict $.\"{}\"", field.replace('.', "\".\"")),
+    })
+}
+
+/// finds each of the comma separated sort keys in the returned objects
+async fn parse_sort<T: DeserializeOwned>(
+    params: &Parameters,
+    dialect: Dialect,
+) -> Result<Option<Vec<Field>>> {
+    match &params.sort {
+        Some(q_sort) => q_sort
+            .split(',')
+            .map(|key| {
+                field::<T>(params, key.trim(), dialect)
+                    .map_err(|_| ServerError::InvalidSortField(key.to_string()))
+            })
+            .collect::<Result<Vec<Field>>>()
+            .map(Some),
+        None => Ok(None),
+    }
+}
 
-        return Ok(Some(filter_builder.join(" ")));
+async fn parse_order_by(params: &Parameters) -> Result<&'static str> {
+    match params.order_by.as_deref().map(|o| o.to_ascii_lowercase()) {
+        None => Ok("ASC"),
+        Some(o) if o == "asc" => Ok("ASC"),
+        Some(o) if o == "desc" => Ok("DESC"),
+        Some(_) => Err(ServerError::InvalidParameters),
     }
-    Ok(None)
 }
 
-async fn parse_fields(params: &Parameters) -> Option<String> {
-    if let Some(q_field) = &params.fields {
-        let mut fields = Vec::new();
-        match q_field.contains(',') {
-            true => {
-                for f in q_field.split(',') {
-                    fields.push(format!("{}: .{}", f, f));
-                }
+/// normalises a date or datetime into the UTC format dateLastModified is stored in,
+/// so timestamps can be compared as text
+pub(crate) fn timestamp(value: &str) -> Option<String> {
+    let value = value.trim();
+    let datetime = chrono::DateTime::parse_from_rfc3339(value)
+        .map(|d| d.naive_utc())
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f"))
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f"))
+        .ok()
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })?;
+    Some(datetime.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+}
+
+async fn parse_fields(params: &Parameters) -> Result<Option<Vec<&str>>> {
+    match &params.fields {
+        Some(q_fields) => {
+            let fields: Vec<&str> = q_fields.split(',').map(|f| f.trim()).collect();
+            if fields.iter().any(|f| f.is_empty()) {
+                return Err(ServerError::InvalidBlankSelectionField);
             }
-            false => fields.push(format!("{}: .{}", q_field, q_field)),
+            Ok(Some(fields))
+        }
+        None => Ok(None),
+    }
+}
+
+/// the scheme, host and port the client used to reach the server, taken from the
+/// configured external url, then the request url. X-Forwarded-* headers are only used
+/// when the server is configured to trust its reverse proxy, as any client can set them
+fn origin(req: &tide::Request<State>) -> String {
+    if let Some(url) = &req.state().external_url {
+        return url.clone();
+    }
+    let trusted = req.state().trust_proxy;
+    let forwarded = |name: &str| {
+        req.header(name)
+            .filter(|_| trusted)
+            .and_then(|h| h.as_str().split(',').next().map(|v| v.trim().to_string()))
+            .filter(|v| !v.is_empty())
+    };
+    let url = req.url();
+    let scheme = forwarded("X-Forwarded-Proto").unwrap_or_else(|| url.scheme().to_string());
+    let (mut host, port) = match forwarded("X-Forwarded-Host") {
+        Some(host) => (host, forwarded("X-Forwarded-Port")),
+        None => (
+            url.host_str().unwrap_or("localhost").to_string(),
+            url.port().map(|p| p.to_string()),
+        ),
+    };
+    if let Some(port) = port.filter(|_| !host.contains(':')) {
+        let default = matches!(
+            (scheme.as_str(), port.as_str()),
+            ("https", "443") | ("http", "80")
+        );
+        if !default {
+            host = format!("{}:{}", host, port);
         }
-        return Some(fields.join(","));
     }
-    None
+    format!("{}://{}", scheme, host)
 }
 
-// TODO: review url building, issue with .path() not returning sub router prefix
+/// builds the RFC 5988 link header with first, prev, next and last pages, keeping
+/// all other query parameters. `base` is the path of the router the request was
+/// nested under as tide strips it from the request url
 pub(super) async fn link_header_builder(
     req: &tide::Request<State>,
     params: &Parameters,
-    data_len: usize,
+    total: u32,
+    base: &str,
 ) -> String {
-    println!("{:?}", req.url());
-    let mut link = String::from("");
-    if params.offset <= data_len as u32 {
-        link = format!(
-            "<{}://{}:{}/ims/oneroster/v1p1{}?offset={}&limit={}>; rel=\"next\",",
-            req.url().scheme(),
-            req.url().domain().unwrap_or("localhost"),
-            req.url().port().unwrap_or(443),
-            req.url().path(),
-            params.offset + params.limit,
-            params.limit
+    let mut url = req.url().clone();
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| k != "offset" && k != "limit")
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    let prefix = format!("{}{}{}", origin(req), base, url.path());
+    let mut link = |offset: u32, rel: &str| {
+        url.query_pairs_mut()
+            .clear()
+            .extend_pairs(&kept)
+            .append_pair("offset", &offset.to_string())
+            .append_pair("limit", &params.limit.to_string());
+        format!(
+            "<{}?{}>; rel=\"{}\"",
+            prefix,
+            url.query().unwrap_or(""),
+            rel
         )
     };
-    link
+
+    let limit = params.limit;
+    let last = total.saturating_sub(1) / limit * limit;
+    let mut links = vec![link(0, "first")];
+    if params.offset > 0 {
+        links.push(link(params.offset.saturating_sub(limit).min(last), "prev"));
+    }
+    if params.offset.saturating_add(limit) < total {
+        links.push(link(params.offset + limit, "next"));
+    }
+    links.push(link(last, "last"));
+    links.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model;
+    use crate::server::db;
+
+    fn params(filter: Option<&str>, sort: Option<&str>, order_by: Option<&str>) -> Parameters {
+        Parameters {
+            filter: filter.map(String::from),
+            sort: sort.map(String::from),
+            order_by: order_by.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[async_std::test]
+    async fn rejects_fields_the_model_does_not_have() {
+        let clauses =
+            |p| async move { sql_clauses::<model::User>(&p, "Users", Dialect::Sqlite).await };
+        match clauses(params(Some("nmae='x'"), None, None)).await {
+            Err(ServerError::InvalidFilterField(m)) => {
+                assert_eq!(m, "unknown field 'nmae' at position 0")
+            }
+            _ => panic!("an unknown filter field is accepted"),
+        }
+        match clauses(params(Some("givenName='x' OR orgs.nmae='x'"), None, None)).await {
+            Err(ServerError::InvalidFilterField(m)) => assert!(m.contains("'orgs.nmae'")),
+            _ => panic!("an unknown filter sub-field is accepted"),
+        }
+        match clauses(params(None, Some("familyName,nmae"), None)).await {
+            Err(ServerError::InvalidSortField(key)) => assert_eq!(key, "nmae"),
+            _ => panic!("an unknown sort field is accepted"),
+        }
+        // a json path into an array never reads a value, so would silently match nothing
+        match clauses(params(Some("orgs.sourcedId='x'"), None, None)).await {
+            Err(ServerError::InvalidFilterField(m)) => {
+                assert_eq!(m, "field inside an array 'orgs.sourcedId' at position 0")
+            }
+            _ => panic!("a filter on a field inside an array is accepted"),
+        }
+        match clauses(params(None, Some("orgs.type"), None)).await {
+            Err(ServerError::InvalidSortField(key)) => assert_eq!(key, "orgs.type"),
+            _ => panic!("a sort on a field inside an array is accepted"),
+        }
+        assert!(
+            clauses(params(Some("givenName='x'"), Some("familyName"), None))
+                .await
+                .is_ok()
+        );
+    }
+
+    /// the sourcedIds of the orgs of a page
+    async fn orgs(db: &db::Database, params: Parameters) -> Vec<String> {
+        let rows = db::get_all_orgs(db, &params).await.unwrap().rows();
+        let mut orgs = Vec::new();
+        while let Ok(org) = rows.recv().await {
+            orgs.push(org.unwrap().sourced_id);
+        }
+        orgs
+    }
+
+    #[async_std::test]
+    async fn sorts_by_each_key_then_sourced_id() {
+        let db = db::scratch("sort").await;
+        let content = std::fs::read_to_string("./sample/orgs.json").unwrap();
+        let json: model::Orgs = serde_json::from_str(&content).unwrap();
+        let (sender, objects) = async_std::channel::unbounded();
+        for org in json.orgs {
+            let object = serde_json::to_value(org).unwrap();
+            let violations = Vec::new();
+            sender
+                .try_send(Ok(db::PutObject { object, violations }))
+                .unwrap();
+        }
+        drop(sender);
+        db::put_orgs(objects, &db).await.unwrap();
+
+        // 017 and 015 are local orgs, 018 is a district and 016 a state
+        let sorted = |sort, order_by| orgs(&db, params(None, Some(sort), order_by));
+        assert_eq!(
+            sorted("status,type", None).await,
+            ["018", "017", "015", "016"]
+        );
+        assert_eq!(
+            sorted("status, type", Some("DESC")).await,
+            ["016", "015", "017", "018"]
+        );
+        assert!(matches!(
+            sql_clauses::<model::Org>(
+                &params(None, Some("type"), Some("up")),
+                "Orgs",
+                Dialect::Sqlite
+            )
+            .await,
+            Err(ServerError::InvalidParameters)
+        ));
+
+        // pages of orgs with the same status are in sourcedId order, in the sort direction
+        for (order_by, expected) in [
+            ("asc", ["017", "018", "015", "016"]),
+            ("desc", ["016", "015", "018", "017"]),
+        ] {
+            let mut paged = Vec::new();
+            for offset in 0..4 {
+                let page = Parameters {
+                    limit: 1,
+                    offset,
+                    ..params(None, Some("status"), Some(order_by))
+                };
+                paged.extend(orgs(&db, page).await);
+            }
+            assert_eq!(paged, expected);
+        }
+    }
+
+    /// the link header of a page of `total` objects nested under `/ims/oneroster/v1p1`
+    async fn links(state: &State, total: u32, url: &str, headers: &[(&str, &str)]) -> String {
+        let mut app = tide::with_state(state.clone());
+        app.at("/users")
+            .get(move |req: tide::Request<State>| async move {
+                let params = query(&req)?;
+                Ok(link_header_builder(&req, &params, total, "/ims/oneroster/v1p1").await)
+            });
+        let mut req = http_types::Request::get(url);
+        for (name, value) in headers {
+            req.insert_header(*name, *value);
+        }
+        let mut res: tide::http::Response = app.respond(req).await.unwrap();
+        res.body_string().await.unwrap()
+    }
+
+    /// the rel and offset of each link
+    fn pages(links: &str) -> Vec<(String, u32)> {
+        links
+            .split(", ")
+            .map(|link| {
+                let (url, rel) = link.split_once("; rel=").unwrap();
+                let url =
+                    tide::http::Url::parse(url.trim_matches(|c| c == '<' || c == '>')).unwrap();
+                let offset = url
+                    .query_pairs()
+                    .find(|(k, _)| k == "offset")
+                    .map(|(_, v)| v.parse().unwrap())
+                    .unwrap();
+                (rel.trim_matches('"').to_string(), offset)
+            })
+            .collect()
+    }
+
+    #[async_std::test]
+    async fn links_each_page_keeping_the_query() {
+        let state = crate::server::test_state("links").await;
+        let url = "http://localhost:8080/users?filter=status%3D%27active%27&limit=10&offset=10";
+        assert_eq!(
+            links(&state, 25, url, &[]).await,
+            [("first", 0), ("prev", 0), ("next", 20), ("last", 20)]
+                .map(|(rel, offset)| format!(
+                    "<http://localhost:8080/ims/oneroster/v1p1/users\
+                ?filter=status%3D%27active%27&offset={}&limit=10>; rel=\"{}\"",
+                    offset, rel
+                ))
+                .join(", ")
+        );
+
+        let state = &state;
+        let page = |offset, total| async move {
+            let url = format!("http://localhost/users?limit=10&offset={}", offset);
+            pages(&links(state, total, &url, &[]).await)
+        };
+        let rels = |rels: &[(&str, u32)]| -> Vec<(String, u32)> {
+            rels.iter().map(|(r, o)| (r.to_string(), *o)).collect()
+        };
+        // the last page has no next
+        assert_eq!(
+            page(20, 25).await,
+            rels(&[("first", 0), ("prev", 10), ("last", 20)])
+        );
+        // past the end the previous page is the last
+        assert_eq!(
+            page(40, 25).await,
+            rels(&[("first", 0), ("prev", 20), ("last", 20)])
+        );
+        assert_eq!(page(0, 0).await, rels(&[("first", 0), ("last", 0)]));
+        state.db.close().await;
+    }
+
+    #[async_std::test]
+    async fn links_from_the_external_or_trusted_forwarded_url() {
+        let mut state = crate::server::test_state("links_origin").await;
+        let url = "http://10.0.0.2:8080/users?limit=10";
+        let forwarded = [
+            ("X-Forwarded-Proto", "https"),
+            ("X-Forwarded-Host", "roster.example.com"),
+            ("X-Forwarded-Port", "443"),
+        ];
+        let origin = |links: String| links.split("/ims/").next().unwrap().to_string();
+
+        // forwarded headers are ignored unless the proxy is trusted
+        assert_eq!(
+            origin(links(&state, 5, url, &forwarded).await),
+            "<http://10.0.0.2:8080"
+        );
+        state.trust_proxy = true;
+        assert_eq!(
+            origin(links(&state, 5, url, &forwarded).await),
+            "<https://roster.example.com"
+        );
+        let port = [
+            ("X-Forwarded-Host", "roster.example.com"),
+            ("X-Forwarded-Port", "8443"),
+        ];
+        assert_eq!(
+            origin(links(&state, 5, url, &port).await),
+            "<http://roster.example.com:8443"
+        );
+
+        // the external url is used whatever the request says
+        state.external_url = Some("https://example.com/oneroster".to_string());
+        assert_eq!(
+            origin(links(&state, 5, url, &forwarded).await),
+            "<https://example.com/oneroster"
+        );
+        state.db.close().await;
+    }
 }
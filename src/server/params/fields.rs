@@ -0,0 +1,263 @@
+//! Field selection for the fields query parameter
+//!
+//! Requested fields are checked against the serde definition of the returned model by
+//! walking a field path through a deserializer which never produces a value, so the
+//! 1.1 and 1.2 models are both supported without maintaining a list of field names
+use serde::de::{self, DeserializeSeed, Deserializer, IntoDeserializer, Visitor};
+use serde_json::{Map, Value};
+use std::cell::Cell;
+
+/// Deserializer following a path of field names, setting `found` when the full path
+/// exists in the model. It always returns an error to stop deserialization once the
+/// path has been followed. Sub-fields of array elements are only followed with `arrays`
+struct PathWalker<'a> {
+    path: &'a [&'a str],
+    found: &'a Cell<bool>,
+    arrays: bool,
+}
+
+impl<'a> PathWalker<'a> {
+    fn end<T>(self, found: bool) -> Result<T, de::value::Error> {
+        self.found.set(found);
+        Err(de::Error::custom("path walked"))
+    }
+}
+
+/// Ends the walk on a scalar type, which only matches when no sub-fields remain
+macro_rules! end_on_scalar {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+                let found = self.path.is_empty();
+                self.end(found)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> Deserializer<'de> for PathWalker<'a> {
+    type Error = de::value::Error;
+
+    /// only free form types such as serde_json::Value are self describing
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        self.end(true)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        self.end(true)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.path.split_first() {
+            Some((head, tail)) => match fields.iter().find(|f| *f == head) {
+                Some(field) => visitor.visit_map(FieldAccess {
+                    field: Some(field),
+                    walker: Some(PathWalker {
+                        path: tail,
+                        found: self.found,
+                        arrays: self.arrays,
+                    }),
+                }),
+                None => self.end(false),
+            },
+            None => self.end(true),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if !self.arrays && !self.path.is_empty() {
+            return self.end(false);
+        }
+        visitor.visit_seq(ElementAccess { walker: Some(self) })
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    end_on_scalar! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string deserialize_bytes
+        deserialize_byte_buf deserialize_unit deserialize_identifier deserialize_ignored_any
+    }
+}
+
+/// Map of a single struct field, used to walk into the field's type
+struct FieldAccess<'a> {
+    field: Option<&'static str>,
+    walker: Option<PathWalker<'a>>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for FieldAccess<'a> {
+    type Error = de::value::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.field.take() {
+            Some(field) => seed.deserialize(field.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.walker.take() {
+            Some(walker) => seed.deserialize(walker),
+            None => Err(de::Error::custom("value requested before key")),
+        }
+    }
+}
+
+/// Sequence of a single element, used to walk into the element type
+struct ElementAccess<'a> {
+    walker: Option<PathWalker<'a>>,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for ElementAccess<'a> {
+    type Error = de::value::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        match self.walker.take() {
+            Some(walker) => seed.deserialize(walker).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// follows a dotted field through objects of type `T`
+fn walk<T: de::DeserializeOwned>(field: &str, arrays: bool) -> bool {
+    let found = Cell::new(false);
+    let path: Vec<&str> = field.split('.').collect();
+    let _ = T::deserialize(PathWalker {
+        path: &path,
+        found: &found,
+        arrays,
+    });
+    found.get()
+}
+
+/// checks a dotted field exists on objects of type `T`, free form json such as
+/// metadata accepts any sub-field
+pub(crate) fn exists<T: de::DeserializeOwned>(field: &str) -> bool {
+    walk::<T>(field, true)
+}
+
+/// checks a dotted field exists on objects of type `T` without passing through an array,
+/// as a json path into an array such as `orgs.sourcedId` never reads a value
+pub(crate) fn exists_outside_arrays<T: de::DeserializeOwned>(field: &str) -> bool {
+    walk::<T>(field, false)
+}
+
+/// keeps only the requested field paths of a json value, applying sub-fields to
+/// each element of an array
+pub(super) fn project<S: AsRef<str>>(value: &Value, paths: &[&[S]]) -> Value {
+    if paths.iter().any(|p| p.is_empty()) {
+        return value.clone();
+    }
+    match value {
+        Value::Array(elements) => {
+            Value::Array(elements.iter().map(|e| project(e, paths)).collect())
+        }
+        Value::Object(object) => {
+            let mut projected = Map::new();
+            for (key, value) in object {
+                let tails: Vec<&[S]> = paths
+                    .iter()
+                    .filter(|p| p[0].as_ref() == key)
+                    .map(|p| &p[1..])
+                    .collect();
+                if !tails.is_empty() {
+                    projected.insert(key.clone(), project(value, &tails));
+                }
+            }
+            Value::Object(projected)
+        }
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model;
+
+    #[test]
+    fn checks_fields_against_the_model() {
+        assert!(exists::<model::Class>("title"));
+        assert!(exists::<model::Class>("course.sourcedId"));
+        assert!(exists::<model::User>("orgs.type"));
+        assert!(exists::<model::v1p2::User>("metadata.anything"));
+        assert!(!exists::<model::Class>("titel"));
+        assert!(!exists::<model::Class>("course.sourceId"));
+        assert!(!exists::<model::Class>("title.sub"));
+        assert!(exists_outside_arrays::<model::Class>("course.sourcedId"));
+        assert!(exists_outside_arrays::<model::User>("orgs"));
+        assert!(!exists_outside_arrays::<model::User>("orgs.type"));
+    }
+
+    #[test]
+    fn projects_sub_fields() {
+        let value = serde_json::json!({"title": "a", "status": "active",
+            "orgs": [{"sourcedId": "1", "type": "org"}, {"sourcedId": "2", "type": "org"}]});
+        let paths: [&[&str]; 2] = [&["title"], &["orgs", "sourcedId"]];
+        assert_eq!(
+            project(&value, &paths),
+            serde_json::json!({"title": "a", "orgs": [{"sourcedId": "1"}, {"sourcedId": "2"}]})
+        );
+    }
+}
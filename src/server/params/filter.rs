@@ -0,0 +1,295 @@
+//! Parser for the filter query parameter
+//!
+//! filter    := predicate ( logical predicate )*
+//! predicate := field operator value
+//! field     := name ( '.' name )*
+//! operator  := '=' | '!=' | '>' | '>=' | '<' | '<=' | '~'
+//! logical   := 'AND' | 'OR'
+//! value     := a single quoted string, quotes within the value are escaped as '' or \'
+//!
+//! Predicates are converted into SQL against the `data` json column of a collection query,
+//! AND takes precedence over OR as in SQL. Comparisons on dateLastModified use the indexed
+//! column of the entity table instead, so delta queries only build the changed objects.
+//! A delta query includes soft deleted objects but not purged ones, see the changes feed.
+//! A field the returned objects do not have, or one inside an array, is rejected rather
+//! than matching nothing
+use super::{timestamp, Bind, Dialect, Field};
+use crate::server::{Result, ServerError};
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Operator(&'static str),
+    Value(String),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Word(w) => write!(f, "{}", w),
+            Token::Operator(o) => write!(f, "{}", o),
+            Token::Value(v) => write!(f, "'{}'", v),
+        }
+    }
+}
+
+fn invalid(message: String) -> ServerError {
+    ServerError::InvalidFilterField(message)
+}
+
+/// splits the filter into tokens alongside their character position
+fn tokenize(filter: &str) -> Result<Vec<(usize, Token)>> {
+    let mut tokens = Vec::new();
+    let mut chars = filter.chars().enumerate().peekable();
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\'')) if chars.peek().map(|(_, c)| *c) == Some('\'') => {
+                            chars.next();
+                            value.push('\'');
+                        }
+                        Some((_, '\'')) => break,
+                        Some((_, '\\')) if chars.peek().map(|(_, c)| *c) == Some('\'') => {
+                            chars.next();
+                            value.push('\'');
+                        }
+                        Some((_, c)) => value.push(c),
+                        None => {
+                            return Err(invalid(format!(
+                                "unterminated value starting at position {}",
+                                pos
+                            )))
+                        }
+                    }
+                }
+                tokens.push((pos, Token::Value(value)));
+            }
+            '=' => tokens.push((pos, Token::Operator("="))),
+            '~' => tokens.push((pos, Token::Operator("~"))),
+            '!' | '<' | '>' => {
+                let operator = match (c, chars.peek().map(|(_, c)| *c)) {
+                    ('!', Some('=')) => "!=",
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    _ => return Err(invalid(format!("unknown operator '!' at position {}", pos))),
+                };
+                if operator.len() == 2 {
+                    chars.next();
+                }
+                tokens.push((pos, Token::Operator(operator)));
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' || c == '.' => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || *c == '_' || *c == '.') {
+                        break;
+                    }
+                    word.push(*c);
+                    chars.next();
+                }
+                tokens.push((pos, Token::Word(word)));
+            }
+            c => return Err(invalid(format!("unexpected '{}' at position {}", c, pos))),
+        }
+    }
+    Ok(tokens)
+}
+
+/// builds the SQL for a single predicate, comparing numbers numerically when both the
+/// field and value are numeric and everything else, including dates, as text
+fn predicate(field: Field, operator: &str, value: String, dialect: Dialect) -> (String, Vec<Bind>) {
+    let (text, text_binds) = field.text(dialect);
+
+    if operator == "~" {
+        let mut binds = text_binds;
+        binds.push(Bind::Text(value));
+        return (dialect.contains(&text), binds);
+    }
+
+    match (value.trim().parse::<f64>(), field.number(dialect)) {
+        (Ok(number), Some(((is_number, mut binds), (field_number, number_binds))))
+            if number.is_finite() =>
+        {
+            binds.extend(number_binds);
+            binds.push(Bind::Number(number));
+            binds.extend(text_binds);
+            binds.push(Bind::Text(value));
+            (
+                format!(
+                    "(CASE WHEN {is_number} THEN {field_number} {op} ? ELSE {text} {op} ? END)",
+                    op = operator,
+                ),
+                binds,
+            )
+        }
+        _ => {
+            let mut binds = text_binds;
+            binds.push(Bind::Text(value));
+            (format!("{} {} ?", text, operator), binds)
+        }
+    }
+}
+
+/// builds the SQL for a comparison of dateLastModified, using the indexed column of the
+/// entity table `table`. Purged objects are not in the table, so their tombstones are only
+/// listed by the changes feed
+fn date_last_modified(operator: &str, value: String, table: &str) -> Result<(String, Vec<Bind>)> {
+    let since = timestamp(&value).ok_or_else(|| {
+        invalid(format!(
+            "invalid dateLastModified '{}', expected an ISO 8601 date or datetime",
+            value
+        ))
+    })?;
+    Ok((
+        format!(
+            "sourcedId IN (SELECT sourcedId FROM {} WHERE dateLastModified {} ?)",
+            table, operator
+        ),
+        vec![Bind::Text(since)],
+    ))
+}
+
+/// parses a filter expression into an SQL condition and its bind parameters
+/// `table` is the entity table of the collection, `fields` finds each field in the
+/// returned objects or says why it cannot be filtered
+pub(super) fn parse(
+    filter: &str,
+    table: &str,
+    dialect: Dialect,
+    fields: &dyn Fn(&str) -> std::result::Result<Field, &'static str>,
+) -> Result<(String, Vec<Bind>)> {
+    let mut tokens = tokenize(filter)?.into_iter();
+    let mut sql: Vec<String> = Vec::new();
+    let mut binds: Vec<Bind> = Vec::new();
+
+    loop {
+        let (position, field) = match tokens.next() {
+            Some((pos, Token::Word(field))) => (pos, field),
+            Some((pos, t)) => {
+                return Err(invalid(format!(
+                    "expected a field but found {} at position {}",
+                    t, pos
+                )))
+            }
+            None => return Err(invalid("expected a field at end of filter".to_string())),
+        };
+        let operator = match tokens.next() {
+            Some((_, Token::Operator(o))) => o,
+            Some((pos, t)) => {
+                return Err(invalid(format!(
+                    "expected an operator after '{}' but found {} at position {}",
+                    field, t, pos
+                )))
+            }
+            None => return Err(invalid(format!("expected an operator after '{}'", field))),
+        };
+        let value = match tokens.next() {
+            Some((_, Token::Value(v))) => v,
+            Some((pos, t)) => {
+                return Err(invalid(format!(
+                    "expected a quoted value after '{}{}' but found {} at position {}",
+                    field, operator, t, pos
+                )))
+            }
+            None => {
+                return Err(invalid(format!(
+                    "expected a quoted value after '{}{}'",
+                    field, operator
+                )))
+            }
+        };
+        let (condition, condition_binds) = match fields(&field) {
+            Ok(_) if field == "dateLastModified" && operator != "~" => {
+                date_last_modified(operator, value, table)?
+            }
+            Ok(f) => predicate(f, operator, value, dialect),
+            Err(reason) => {
+                return Err(invalid(format!(
+                    "{} '{}' at position {}",
+                    reason, field, position
+                )))
+            }
+        };
+        sql.push(condition);
+        binds.extend(condition_binds);
+
+        match tokens.next() {
+            None => break,
+            Some((_, Token::Word(w))) if w.eq_ignore_ascii_case("AND") => sql.push("AND".into()),
+            Some((_, Token::Word(w))) if w.eq_ignore_ascii_case("OR") => sql.push("OR".into()),
+            Some((pos, t)) => {
+                return Err(invalid(format!(
+                    "expected AND or OR but found {} at position {}",
+                    t, pos
+                )))
+            }
+        }
+    }
+
+    Ok((sql.join(" "), binds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::params::json_path;
+
+    /// any valid field name, as a json path
+    fn path(name: &str) -> std::result::Result<Field, &'static str> {
+        json_path(name, Dialect::Sqlite)
+            .map(Field::Path)
+            .ok_or("unknown field")
+    }
+
+    #[test]
+    fn parses_logical_chains_and_sub_fields() {
+        let (sql, binds) = parse(
+            "school.sourcedId='x' AND givenName~'o''b' OR score>='9.5'",
+            "Users",
+            Dialect::Sqlite,
+            &path,
+        )
+        .unwrap();
+        assert_eq!(sql.matches(" AND ").count(), 1);
+        assert_eq!(sql.matches(" OR ").count(), 1);
+        assert!(matches!(&binds[0], Bind::Text(p) if p == "$.school.sourcedId"));
+        assert!(binds
+            .iter()
+            .any(|b| matches!(b, Bind::Text(v) if v == "o'b")));
+        assert!(binds
+            .iter()
+            .any(|b| matches!(b, Bind::Number(n) if *n == 9.5)));
+    }
+
+    #[test]
+    fn rejects_invalid_filters_naming_the_token() {
+        let err = |f| match parse(f, "Users", Dialect::Sqlite, &path) {
+            Err(ServerError::InvalidFilterField(m)) => m,
+            _ => panic!("{} should not parse", f),
+        };
+        assert!(err("givenName='bob").contains("unterminated"));
+        assert!(err("givenName bob").contains("bob"));
+        assert!(err("givenName='bob' NOT role='student'").contains("NOT"));
+        assert!(err("given..name='bob'").contains("unknown field 'given..name' at position 0"));
+        assert!(err("dateLastModified>'yesterday'").contains("yesterday"));
+    }
+
+    #[test]
+    fn uses_the_entity_table_for_delta_queries() {
+        let (sql, binds) = parse(
+            "dateLastModified>='2021-01-01'",
+            "Users",
+            Dialect::Sqlite,
+            &path,
+        )
+        .unwrap();
+        assert!(sql.contains("FROM Users WHERE dateLastModified >= ?"));
+        assert!(matches!(&binds[0], Bind::Text(t) if t == "2021-01-01T00:00:00.000Z"));
+    }
+}
@@ -0,0 +1,165 @@
+//! JSON Schemas of PUT bodies
+//!
+//! Schemas are generated from the 1.1 models and served under `/schemas` so a payload can be
+//! checked before it is sent. Bodies are validated against them as they are read, with each
+//! violation reported at its JSON pointer e.g. `/users/42/role`
+use crate::model;
+use crate::server::{Result, ServerError, State};
+use http_types::mime;
+use jsonschema::Validator;
+use schemars::JsonSchema;
+use serde_json::Value;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tide::prelude::*;
+
+lazy_static::lazy_static! {
+    /// validators compiled by the model type their schema is generated from
+    static ref VALIDATORS: Mutex<HashMap<TypeId, Arc<Validator>>> = Mutex::new(HashMap::new());
+}
+
+/// Creates the lookup of the schemas served by the name of their model type, or by the
+/// wire name given as `Name = Type` where the model type is named differently
+macro_rules! create_schemas {
+    ($($name:ident $(= $data:ident)?),* $(,)?) => {
+        /// names of the schemas served, for each collection and single object PUT body
+        const NAMES: &[&str] = &[$(stringify!($name)),*];
+
+        fn schema(name: &str) -> Option<schemars::Schema> {
+            match name {
+                $(stringify!($name) => Some(schemars::schema_for!(schema_model!($name $(, $data)?))),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+macro_rules! schema_model {
+    ($name:ident) => {
+        model::$name
+    };
+    ($name:ident, $data:ident) => {
+        model::$data
+    };
+}
+
+create_schemas!(
+    AcademicSessions,
+    AcademicSessionSingle,
+    Periods,
+    PeriodSingle,
+    Subjects,
+    SubjectSingle,
+    Classes,
+    ClassSingle,
+    Courses,
+    CourseSingle,
+    Orgs,
+    OrgSingle,
+    Users,
+    UserSingle,
+    Enrollments,
+    EnrollmentSingle,
+    Demographics,
+    DemographicSingle,
+    LineItems,
+    LineItemSingle,
+    Categories,
+    CategorySingle,
+    Results = GradebookResults,
+    ResultSingle = GradebookResultSingle,
+    Resources,
+    ResourceSingle,
+);
+
+/// The validator of the schema generated from `T`. Dates are checked as the models read
+/// them, which also accept a space in place of the `T` of a date-time
+pub(super) fn validator<T: JsonSchema + 'static>() -> Result<Arc<Validator>> {
+    let mut validators = VALIDATORS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(validator) = validators.get(&TypeId::of::<T>()) {
+        return Ok(validator.clone());
+    }
+    let schema = schemars::schema_for!(T);
+    let validator = jsonschema::options()
+        .should_validate_formats(true)
+        .with_format("date-time", |d| {
+            chrono::DateTime::<chrono::Utc>::from_str(d).is_ok()
+        })
+        .with_format("date", |d| chrono::NaiveDate::from_str(d).is_ok())
+        .build(schema.as_value())
+        .map_err(|e| ServerError::InvalidSchema(e.to_string()))?;
+    let validator = Arc::new(validator);
+    validators.insert(TypeId::of::<T>(), validator.clone());
+    Ok(validator)
+}
+
+/// each violation of the schema in an object found at `pointer` within the body
+pub(super) fn violations(validator: &Validator, object: &Value, pointer: &str) -> Vec<String> {
+    validator
+        .iter_errors(object)
+        .map(|e| match format!("{}{}", pointer, e.instance_path()) {
+            at if at.is_empty() => format!("/: {}", e),
+            at => format!("{}: {}", at, e),
+        })
+        .collect()
+}
+
+pub(super) async fn get_schemas(_req: tide::Request<State>) -> tide::Result {
+    Ok(tide::Response::builder(200)
+        .content_type(mime::JSON)
+        .body(json!({ "schemas": NAMES }))
+        .build())
+}
+
+pub(super) async fn get_schema(req: tide::Request<State>) -> tide::Result {
+    let name = req.param("name")?;
+    let schema = schema(name).ok_or_else(|| ServerError::UnknownSchema(name.to_string()))?;
+    Ok(tide::Response::builder(200)
+        .content_type("application/schema+json")
+        .body(schema.as_value().to_string())
+        .build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_samples_with_paths() {
+        for name in NAMES {
+            let schema = schema(name).unwrap();
+            assert!(
+                jsonschema::validator_for(schema.as_value()).is_ok(),
+                "{}",
+                name
+            );
+        }
+        let results = schema("Results").unwrap();
+        assert_eq!(results.as_value()["title"], json!("Results"));
+        let content = std::fs::read_to_string("./sample/users.json").unwrap();
+        let mut users: Value = serde_json::from_str(&content).unwrap();
+        let validator = validator::<model::Users>().unwrap();
+        assert!(violations(&validator, &users, "").is_empty());
+
+        users["users"][1]["role"] = json!("wizard");
+        users["users"][1]["dateLastModified"] = json!("2021-01-01 00:00:00Z");
+        let found = violations(&validator, &users, "");
+        assert_eq!(found.len(), 1);
+        assert!(found[0].starts_with("/users/1/role: "));
+    }
+
+    #[test]
+    fn requires_an_org_for_each_user() {
+        let content = std::fs::read_to_string("./sample/users.json").unwrap();
+        let mut users: Value = serde_json::from_str(&content).unwrap();
+        let validator = validator::<model::Users>().unwrap();
+        users["users"][0]["orgs"] = json!([]);
+        users["users"][2].as_object_mut().unwrap().remove("orgs");
+        let found = violations(&validator, &users, "");
+        assert_eq!(found.len(), 2);
+        assert!(found[0].starts_with("/users/0/orgs: "));
+        assert!(found[1].starts_with("/users/2: "));
+    }
+}
@@ -0,0 +1,127 @@
+//! Streamed collection responses
+//!
+//! A page of a collection is written to the client one object at a time as it is read
+//! from the database, so memory use stays flat however large the page is. The ETag is a
+//! hash of the request and the total, key and dateLastModified of each object of the page,
+//! read together before any object, so a client whose copy is current receives a 304
+//! without the page being read. It is strong as the server sets dateLastModified whenever
+//! the json of an object changes, including through objects it includes values of, rather
+//! than keeping the date a client sent
+use crate::server::conditional::{Conditions, ValidatorBuilder};
+use crate::server::db::Collection;
+use crate::server::params::{self, Parameters, Selection};
+use crate::server::{Result, ServerError, State};
+use futures::{stream, StreamExt, TryStreamExt};
+use http_types::{mime, Body};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// the end of the collection array and object
+const CLOSE: &str = "]}";
+
+/// Builds the response for a page of a collection within `wrapper`, converting each object
+/// read from the database with `convert`, e.g. into its 1.2 model
+pub(super) async fn respond<T, U, F>(
+    req: &tide::Request<State>,
+    params: &Parameters,
+    collection: Collection<T>,
+    wrapper: &str,
+    base: &str,
+    convert: F,
+) -> tide::Result
+where
+    T: DeserializeOwned + Send + 'static,
+    U: Serialize + DeserializeOwned,
+    F: Fn(T) -> U + Send + Sync + 'static,
+{
+    let selection = params::selection::<U>(params).await?;
+    let links = params::link_header_builder(req, params, collection.total, base).await;
+    let open = open(&selection, wrapper)?;
+
+    let mut builder = ValidatorBuilder::new();
+    for part in request(req) {
+        builder.update(part.as_bytes());
+        builder.update(b"\0");
+    }
+    builder.update(open.as_bytes());
+    builder.update(collection.total.to_string().as_bytes());
+    for (key, date) in collection.keys() {
+        builder.update(b"\0");
+        builder.update(key.as_bytes());
+        if let Some(date) = date {
+            builder.update(b" ");
+            builder.update(date.as_bytes());
+            builder.modified(date);
+        }
+    }
+    let validators = builder.finish();
+
+    if validators.not_modified(&Conditions::from_request(req)) {
+        let mut res = tide::Response::new(304);
+        validators.apply(&mut res);
+        return Ok(res);
+    }
+
+    let total = collection.total;
+    let objects = collection
+        .rows()
+        .enumerate()
+        .map(move |(i, object)| render(object, &convert, &selection, i == 0));
+    let body = stream::iter([Ok(open)])
+        .chain(objects)
+        .chain(stream::iter([Ok(CLOSE.to_string())]))
+        .map_ok(String::into_bytes)
+        .map_err(|e: ServerError| {
+            // the status has been sent, so the client only sees the body end early
+            log::error!("collection stream error: {}", e);
+            std::io::Error::other(e.to_string())
+        })
+        .into_async_read();
+
+    let mut res = tide::Response::builder(200)
+        .header("link", links)
+        .header("x-total-count", total.to_string())
+        .content_type(mime::JSON)
+        .body(Body::from_reader(futures::io::BufReader::new(body), None))
+        .build();
+    validators.apply(&mut res);
+    Ok(res)
+}
+
+/// the path and query of a request, with the query parameters sorted so the same request
+/// has the same validators whichever order its parameters are given in
+fn request(req: &tide::Request<State>) -> Vec<String> {
+    let mut query: Vec<String> = req
+        .url()
+        .query_pairs()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    query.sort();
+    std::iter::once(req.url().path().to_string())
+        .chain(query)
+        .collect()
+}
+
+/// the start of the collection up to its array of objects, with any field warnings
+fn open(selection: &Selection, wrapper: &str) -> Result<String> {
+    let mut open = String::from("{");
+    if !selection.warnings.is_empty() {
+        open += &format!(
+            "\"statusInfoSet\":{},",
+            serde_json::to_string(&selection.warnings)?
+        );
+    }
+    open += &format!("{}:[", serde_json::to_string(wrapper)?);
+    Ok(open)
+}
+
+/// converts an object and renders its selected fields as an element of the collection array
+fn render<T, U: Serialize>(
+    object: Result<T>,
+    convert: &impl Fn(T) -> U,
+    selection: &Selection,
+    first: bool,
+) -> Result<String> {
+    let object = serde_json::to_value(convert(object?))?;
+    let json = selection.project(object).to_string();
+    Ok(if first { json } else { format!(",{}", json) })
+}
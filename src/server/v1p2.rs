@@ -0,0 +1,643 @@
+//! OneRoster 1.2 rostering service
+//!
+//! Reads from the same database calls as the 1.1 service, which filter and sort the stored
+//! 1.1 json, converting each object of the page into its 1.2 model. Filter and sort fields
+//! are read from the 1.1 fields their 1.2 values are converted from, and fields selected
+//! are checked against the 1.2 models. Writes are validated against the 1.2 models then
+//! converted into the 1.1 models the database stores
+use crate::model::{self, v1p2};
+use crate::server::db::PutObject;
+use crate::server::params::{Dialect, Parameters, Stored};
+use crate::server::{auth, body, db, params, stream, Result, ServerError, State, V1P2};
+use async_std::channel::Receiver;
+use http_types::mime;
+use serde::{de::DeserializeOwned, Serialize};
+use tide::prelude::*;
+use tide::Request;
+
+/// Reads a filter or sort field of the 1.2 objects `U` from the stored 1.1 json. A field
+/// whose value is converted is computed from the 1.1 fields it comes from, others are read
+/// from the same path. A field only 1.2 has is not stored, so is unknown to the 1.1 model
+fn stored<U: DeserializeOwned>(field: &str) -> Option<Stored> {
+    if !params::exists::<U>(field) {
+        return None;
+    }
+    let computed: fn(Dialect) -> String = match field {
+        "status" => |d| {
+            let status = d.value(&["status"]);
+            format!(
+                "CASE {0} WHEN 'inactive' THEN 'active' ELSE {0} END",
+                status
+            )
+        },
+        "metadata.inactive" => |d| {
+            let status = d.value(&["status"]);
+            format!("CASE {} WHEN 'inactive' THEN 'true' END", status)
+        },
+        "enabledUser" => |d| {
+            format!(
+                "CASE WHEN {} <> '0' THEN 'true' ELSE 'false' END",
+                d.value(&["enabledUser"])
+            )
+        },
+        "primary" => |d| {
+            let primary = d.value(&["primary"]);
+            format!(
+                "CASE WHEN {0} IS NULL THEN NULL WHEN {0} = '0' THEN 'false' ELSE 'true' END",
+                primary
+            )
+        },
+        "role" => |d| {
+            let role = d.value(&["role"]);
+            format!(
+                "CASE {0} WHEN 'administrator' THEN 'siteAdministrator' ELSE {0} END",
+                role
+            )
+        },
+        "primaryOrg.sourcedId" => |d| d.value(&["orgs", "0", "sourcedId"]),
+        "primaryOrg.href" => |d| d.value(&["orgs", "0", "href"]),
+        "primaryOrg.type" => |d| d.value(&["orgs", "0", "type"]),
+        _ => return Some(Stored::Path),
+    };
+    Some(Stored::Computed(computed))
+}
+
+/// Reads the request parameters of a collection of the 1.2 objects `U`
+fn query<U: DeserializeOwned>(req: &Request<State>) -> tide::Result<Parameters> {
+    let mut params = params::query(req)?;
+    params.stored = stored::<U>;
+    Ok(params)
+}
+
+/// Creates a 1.2 GET endpoint function for a collection
+/// $name is the name of the matching 1.1 DB req function
+/// $data is the 1.2 struct each 1.1 object is converted into
+/// $wrapper is the top level json object within the collection
+macro_rules! create_get_endpoint {
+    ($name:ident, $data:ty, $wrapper:literal) => {
+        async fn $name(req: Request<State>) -> tide::Result {
+            let params = query::<$data>(&req)?;
+            let collection = db::$name(&req.state().db, &params).await?;
+            stream::respond(&req, &params, collection, $wrapper, V1P2, <$data>::from).await
+        }
+    };
+}
+
+create_get_endpoint!(
+    get_all_academic_sessions,
+    v1p2::AcademicSession,
+    "academicSessions"
+);
+create_get_endpoint!(get_all_classes, v1p2::Class, "classes");
+create_get_endpoint!(get_all_courses, v1p2::Course, "courses");
+create_get_endpoint!(get_all_demographics, v1p2::Demographic, "demographics");
+create_get_endpoint!(get_all_enrollments, v1p2::Enrollment, "enrollments");
+create_get_endpoint!(
+    get_all_grading_periods,
+    v1p2::AcademicSession,
+    "academicSessions"
+);
+create_get_endpoint!(get_all_orgs, v1p2::Org, "orgs");
+create_get_endpoint!(get_all_schools, v1p2::Org, "orgs");
+create_get_endpoint!(get_all_students, v1p2::User, "users");
+create_get_endpoint!(get_all_teachers, v1p2::User, "users");
+create_get_endpoint!(get_all_terms, v1p2::AcademicSession, "academicSessions");
+create_get_endpoint!(get_all_users, v1p2::User, "users");
+
+/// Creates a 1.2 GET endpoint function for a single object by sourcedId
+/// $data is the 1.2 single object struct the 1.1 data is converted into
+macro_rules! create_get_endpoint_by_id {
+    ($name:ident, $data:ty) => {
+        async fn $name(req: Request<State>) -> tide::Result {
+            let id = req.param("id")?;
+            let data: $data = db::$name(&req.state().db, id).await?.into();
+            Ok(tide::Response::builder(200)
+                .content_type(mime::JSON)
+                .header("x-total-count", "1")
+                .body(json!(data).to_string())
+                .build())
+        }
+    };
+}
+
+create_get_endpoint_by_id!(get_academic_session, v1p2::AcademicSessionSingle);
+create_get_endpoint_by_id!(get_class, v1p2::ClassSingle);
+create_get_endpoint_by_id!(get_course, v1p2::CourseSingle);
+create_get_endpoint_by_id!(get_demographics, v1p2::DemographicSingle);
+create_get_endpoint_by_id!(get_enrollment, v1p2::EnrollmentSingle);
+create_get_endpoint_by_id!(get_grading_period, v1p2::AcademicSessionSingle);
+create_get_endpoint_by_id!(get_org, v1p2::OrgSingle);
+create_get_endpoint_by_id!(get_school, v1p2::OrgSingle);
+create_get_endpoint_by_id!(get_student, v1p2::UserSingle);
+create_get_endpoint_by_id!(get_teacher, v1p2::UserSingle);
+create_get_endpoint_by_id!(get_term, v1p2::AcademicSessionSingle);
+create_get_endpoint_by_id!(get_user, v1p2::UserSingle);
+
+/// Creates a 1.2 GET endpoint function for a collection nested under a parent object
+macro_rules! create_get_collection_endpoint_by_id {
+    ($name:ident, $data:ty, $wrapper:literal) => {
+        async fn $name(req: Request<State>) -> tide::Result {
+            let id = req.param("id")?;
+            let params = query::<$data>(&req)?;
+            let collection = db::$name(&req.state().db, id, &params).await?;
+            stream::respond(&req, &params, collection, $wrapper, V1P2, <$data>::from).await
+        }
+    };
+}
+
+create_get_collection_endpoint_by_id!(get_classes_for_school, v1p2::Class, "classes");
+create_get_collection_endpoint_by_id!(get_students_for_school, v1p2::User, "users");
+create_get_collection_endpoint_by_id!(get_teachers_for_school, v1p2::User, "users");
+create_get_collection_endpoint_by_id!(get_enrollments_for_school, v1p2::Enrollment, "enrollments");
+create_get_collection_endpoint_by_id!(get_courses_for_school, v1p2::Course, "courses");
+create_get_collection_endpoint_by_id!(
+    get_terms_for_school,
+    v1p2::AcademicSession,
+    "academicSessions"
+);
+create_get_collection_endpoint_by_id!(get_students_for_class, v1p2::User, "users");
+create_get_collection_endpoint_by_id!(get_teachers_for_class, v1p2::User, "users");
+create_get_collection_endpoint_by_id!(get_classes_for_course, v1p2::Class, "classes");
+create_get_collection_endpoint_by_id!(get_classes_for_user, v1p2::Class, "classes");
+create_get_collection_endpoint_by_id!(get_classes_for_student, v1p2::Class, "classes");
+create_get_collection_endpoint_by_id!(get_classes_for_teacher, v1p2::Class, "classes");
+create_get_collection_endpoint_by_id!(get_classes_for_term, v1p2::Class, "classes");
+create_get_collection_endpoint_by_id!(
+    get_grading_periods_for_term,
+    v1p2::AcademicSession,
+    "academicSessions"
+);
+
+/// Creates a 1.2 GET endpoint function for a collection nested under two parent objects
+macro_rules! create_get_collection_endpoint_by_ids {
+    ($name:ident, $data:ty, $wrapper:literal) => {
+        async fn $name(req: Request<State>) -> tide::Result {
+            let id = req.param("id")?;
+            let sub_id = req.param("sub_id")?;
+            let params = query::<$data>(&req)?;
+            let collection = db::$name(&req.state().db, id, sub_id, &params).await?;
+            stream::respond(&req, &params, collection, $wrapper, V1P2, <$data>::from).await
+        }
+    };
+}
+
+create_get_collection_endpoint_by_ids!(
+    get_enrollments_for_class_in_school,
+    v1p2::Enrollment,
+    "enrollments"
+);
+create_get_collection_endpoint_by_ids!(get_students_for_class_in_school, v1p2::User, "users");
+
+/// Converts each 1.2 object of a PUT body into its 1.1 model `T` as it is read, an object
+/// which cannot be read or has no 1.1 equivalent is rejected with the reason
+fn into_v1p1<S, T>(objects: Receiver<Result<PutObject>>) -> Receiver<Result<PutObject>>
+where
+    S: DeserializeOwned + TryInto<T, Error = String>,
+    T: Serialize,
+{
+    let (sender, converted) = async_std::channel::bounded(1);
+    async_std::task::spawn(async move {
+        while let Ok(object) = objects.recv().await {
+            let object = object.and_then(
+                |PutObject {
+                     object,
+                     mut violations,
+                 }| {
+                    if !violations.is_empty() {
+                        return Ok(PutObject { object, violations });
+                    }
+                    let v1p1 = serde_json::from_value::<S>(object.clone())
+                        .map_err(|e| e.to_string())
+                        .and_then(S::try_into);
+                    match v1p1 {
+                        Ok(v1p1) => Ok(PutObject {
+                            object: serde_json::to_value(v1p1)?,
+                            violations,
+                        }),
+                        Err(reason) => {
+                            violations.push(reason);
+                            Ok(PutObject { object, violations })
+                        }
+                    }
+                },
+            );
+            // the reader has gone, e.g. a database error
+            if sender.send(object).await.is_err() {
+                break;
+            }
+        }
+    });
+    converted
+}
+
+/// Creates a 1.2 PUT endpoint function for a collection, written through the matching
+/// 1.1 DB req function
+/// $data is the 1.2 struct each object is validated against and read into
+/// $v1p1 is the 1.1 struct it is converted into
+/// $wrapper is the top level json object within the collection
+macro_rules! create_put_endpoint {
+    ($name:ident, $data:ty, $v1p1:ty, $wrapper:literal) => {
+        async fn $name(mut req: Request<State>) -> tide::Result {
+            let objects = body::objects::<$data>(&mut req, $wrapper)?;
+            let objects = into_v1p1::<$data, $v1p1>(objects);
+            let written = db::$name(objects, &req.state().db).await?;
+            super::put_response(written)
+        }
+    };
+}
+
+create_put_endpoint!(
+    put_academic_sessions,
+    v1p2::AcademicSession,
+    model::AcademicSession,
+    "academicSessions"
+);
+create_put_endpoint!(put_classes, v1p2::Class, model::Class, "classes");
+create_put_endpoint!(put_courses, v1p2::Course, model::Course, "courses");
+create_put_endpoint!(
+    put_demographics,
+    v1p2::Demographic,
+    model::Demographic,
+    "demographics"
+);
+create_put_endpoint!(
+    put_enrollments,
+    v1p2::Enrollment,
+    model::Enrollment,
+    "enrollments"
+);
+create_put_endpoint!(put_orgs, v1p2::Org, model::Org, "orgs");
+create_put_endpoint!(put_users, v1p2::User, model::User, "users");
+
+/// Creates a 1.2 PUT endpoint function for a single object by sourcedId
+/// $data is the 1.2 single object struct, converted into its 1.1 wrapper
+/// $object is the json object contained in it, its sourcedId must match the :id param
+macro_rules! create_put_endpoint_by_id {
+    ($name:ident, $data:ty, $object:ident) => {
+        async fn $name(mut req: Request<State>) -> tide::Result {
+            let json: $data = body::read(&mut req).await?;
+            if json.$object.sourced_id != req.param("id")? {
+                return Err(ServerError::SourcedIdMismatch.into());
+            }
+            let data = json.try_into().map_err(ServerError::InvalidObject)?;
+            db::$name(data, &req.state().db).await?;
+            Ok(tide::Response::builder(200).build())
+        }
+    };
+}
+
+create_put_endpoint_by_id!(
+    put_academic_session,
+    v1p2::AcademicSessionSingle,
+    academic_session
+);
+create_put_endpoint_by_id!(put_class, v1p2::ClassSingle, class);
+create_put_endpoint_by_id!(put_course, v1p2::CourseSingle, course);
+create_put_endpoint_by_id!(put_demographic, v1p2::DemographicSingle, demographics);
+create_put_endpoint_by_id!(put_enrollment, v1p2::EnrollmentSingle, enrollment);
+create_put_endpoint_by_id!(put_org, v1p2::OrgSingle, org);
+create_put_endpoint_by_id!(put_user, v1p2::UserSingle, user);
+
+/// Creates a 1.2 DELETE endpoint function, the same soft delete as the 1.1 service
+macro_rules! create_delete_endpoint {
+    ($name:ident) => {
+        async fn $name(req: Request<State>) -> tide::Result {
+            let id = req.param("id")?;
+            log::debug!("delete request for: {}", id);
+            db::$name(&req.state().db, id).await?;
+            Ok(tide::Response::builder(200).build())
+        }
+    };
+}
+
+create_delete_endpoint!(delete_academic_session);
+create_delete_endpoint!(delete_class);
+create_delete_endpoint!(delete_course);
+create_delete_endpoint!(delete_demographic);
+create_delete_endpoint!(delete_enrollment);
+create_delete_endpoint!(delete_grading_period);
+create_delete_endpoint!(delete_org);
+create_delete_endpoint!(delete_school);
+create_delete_endpoint!(delete_student);
+create_delete_endpoint!(delete_teacher);
+create_delete_endpoint!(delete_term);
+create_delete_endpoint!(delete_user);
+
+/// Builds the 1.2 rostering service
+pub(super) fn rostering(state: State) -> tide::Server<State> {
+    let mut srv = tide::with_state(state);
+    let mut roster = srv.at("");
+    roster.with(auth::middleware::Jwt::new(vec![
+        "roster-core".to_string(),
+        "roster".to_string(),
+    ]));
+    roster
+        .at("/academicSessions")
+        .get(get_all_academic_sessions)
+        .put(put_academic_sessions);
+    roster
+        .at("/academicSessions/:id")
+        .get(get_academic_session)
+        .put(put_academic_session)
+        .delete(delete_academic_session);
+    roster.at("/classes").get(get_all_classes).put(put_classes);
+    roster
+        .at("/classes/:id")
+        .get(get_class)
+        .put(put_class)
+        .delete(delete_class);
+    roster
+        .at("/classes/:id/students")
+        .get(get_students_for_class);
+    roster
+        .at("/classes/:id/teachers")
+        .get(get_teachers_for_class);
+    roster.at("/courses").get(get_all_courses).put(put_courses);
+    roster
+        .at("/courses/:id")
+        .get(get_course)
+        .put(put_course)
+        .delete(delete_course);
+    roster
+        .at("/courses/:id/classes")
+        .get(get_classes_for_course);
+    roster
+        .at("/enrollments")
+        .get(get_all_enrollments)
+        .put(put_enrollments);
+    roster
+        .at("/enrollments/:id")
+        .get(get_enrollment)
+        .put(put_enrollment)
+        .delete(delete_enrollment);
+    roster.at("/gradingPeriods").get(get_all_grading_periods);
+    roster
+        .at("/gradingPeriods/:id")
+        .get(get_grading_period)
+        .delete(delete_grading_period);
+    roster.at("/orgs").get(get_all_orgs).put(put_orgs);
+    roster
+        .at("/orgs/:id")
+        .get(get_org)
+        .put(put_org)
+        .delete(delete_org);
+    roster.at("/schools").get(get_all_schools);
+    roster
+        .at("/schools/:id")
+        .get(get_school)
+        .delete(delete_school);
+    roster
+        .at("/schools/:id/classes")
+        .get(get_classes_for_school);
+    roster
+        .at("/schools/:id/classes/:sub_id/enrollments")
+        .get(get_enrollments_for_class_in_school);
+    roster
+        .at("/schools/:id/classes/:sub_id/students")
+        .get(get_students_for_class_in_school);
+    roster
+        .at("/schools/:id/courses")
+        .get(get_courses_for_school);
+    roster
+        .at("/schools/:id/enrollments")
+        .get(get_enrollments_for_school);
+    roster
+        .at("/schools/:id/students")
+        .get(get_students_for_school);
+    roster
+        .at("/schools/:id/teachers")
+        .get(get_teachers_for_school);
+    roster.at("/schools/:id/terms").get(get_terms_for_school);
+    roster.at("/students").get(get_all_students);
+    roster
+        .at("/students/:id")
+        .get(get_student)
+        .delete(delete_student);
+    roster
+        .at("/students/:id/classes")
+        .get(get_classes_for_student);
+    roster.at("/teachers").get(get_all_teachers);
+    roster
+        .at("/teachers/:id")
+        .get(get_teacher)
+        .delete(delete_teacher);
+    roster
+        .at("/teachers/:id/classes")
+        .get(get_classes_for_teacher);
+    roster.at("/terms").get(get_all_terms);
+    roster.at("/terms/:id").get(get_term).delete(delete_term);
+    roster.at("/terms/:id/classes").get(get_classes_for_term);
+    roster
+        .at("/terms/:id/gradingPeriods")
+        .get(get_grading_periods_for_term);
+    roster.at("/users").get(get_all_users).put(put_users);
+    roster
+        .at("/users/:id")
+        .get(get_user)
+        .put(put_user)
+        .delete(delete_user);
+    roster.at("/users/:id/classes").get(get_classes_for_user);
+    // 1.2 moves demographics into the rostering service
+    let mut demographics = srv.at("");
+    demographics.with(auth::middleware::Jwt::new(vec![
+        "roster-demographics".to_string()
+    ]));
+    demographics
+        .at("/demographics")
+        .get(get_all_demographics)
+        .put(put_demographics);
+    demographics
+        .at("/demographics/:id")
+        .get(get_demographics)
+        .put(put_demographic)
+        .delete(delete_demographic);
+    srv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::errors::middleware::ApiError;
+
+    /// the objects of a sample collection ready to be written
+    fn sample(name: &str, change: impl Fn(&mut serde_json::Value)) -> Receiver<Result<PutObject>> {
+        let content = std::fs::read_to_string(format!("./sample/{}.json", name)).unwrap();
+        let mut json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        let (sender, receiver) = async_std::channel::unbounded();
+        for mut object in json[name].as_array_mut().unwrap().drain(..) {
+            change(&mut object);
+            let violations = Vec::new();
+            sender
+                .try_send(Ok(PutObject { object, violations }))
+                .unwrap();
+        }
+        receiver
+    }
+
+    /// the sourcedIds of the 1.2 users matching a query, or the error status and body
+    async fn users(
+        app: &tide::Server<State>,
+        query: &str,
+    ) -> std::result::Result<Vec<String>, (u16, String)> {
+        let url = format!("https://localhost/users?{}", query);
+        let mut res: http_types::Response = app
+            .respond(http_types::Request::get(url.as_str()))
+            .await
+            .unwrap();
+        let body = res.body_string().await.unwrap();
+        if res.status() != 200 {
+            return Err((res.status().into(), body));
+        }
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        Ok(json["users"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|u| u["sourcedId"].as_str().unwrap().to_string())
+            .collect())
+    }
+
+    #[async_std::test]
+    async fn filters_and_sorts_users_on_their_1p2_fields() {
+        let state = crate::server::test_state("v1p2_users").await;
+        db::put_orgs(sample("orgs", |_| {}), &state.db)
+            .await
+            .unwrap();
+        // 002 is inactive, which 1.2 shows as active, and 003 has a primary org of 018
+        let written = sample("users", |user| match user["sourcedId"].as_str() {
+            Some("002") => user["status"] = json!("inactive"),
+            Some("003") => user["orgs"] = json!([{"sourcedId": "018", "type": "org"}]),
+            _ => {}
+        });
+        db::put_users(written, &state.db).await.unwrap();
+        let mut app = tide::with_state(state);
+        app.with(tide::utils::After(ApiError::new()));
+        app.at("/users").get(get_all_users);
+
+        let all = ["001", "002", "003"].map(String::from).to_vec();
+        assert_eq!(users(&app, "filter=status%3D'active'").await, Ok(all));
+        let inactive = vec!["002".to_string()];
+        assert_eq!(
+            users(&app, "filter=metadata.inactive%3D'true'").await,
+            Ok(inactive.clone())
+        );
+        assert_eq!(
+            users(&app, "filter=enabledUser%3D'false'").await,
+            Ok(inactive)
+        );
+        assert_eq!(
+            users(&app, "filter=primaryOrg.sourcedId%3D'018'").await,
+            Ok(vec!["003".to_string()])
+        );
+        assert_eq!(
+            users(&app, "sort=primaryOrg.sourcedId&orderBy=desc").await,
+            Ok(["003", "002", "001"].map(String::from).to_vec())
+        );
+
+        // fields only 1.2 has are not stored, and those only 1.1 has are not returned
+        for filter in [
+            "roles.role%3D'teacher'",
+            "userProfiles.profileId%3D'x'",
+            "pronouns%3D'they'",
+            "role%3D'teacher'",
+        ] {
+            let (status, body) = users(&app, &format!("filter={}", filter))
+                .await
+                .unwrap_err();
+            assert_eq!(status, 400);
+            assert!(body.contains("invalid_filter_field"), "{}", body);
+        }
+        let (status, body) = users(&app, "sort=preferredFirstName").await.unwrap_err();
+        assert_eq!(status, 400);
+        assert!(body.contains("invalid_sort_field"), "{}", body);
+    }
+
+    /// the response status and body of a request to `app`
+    async fn respond(app: &tide::Server<State>, req: http_types::Request) -> (u16, String) {
+        let mut res: http_types::Response = app.respond(req).await.unwrap();
+        (res.status().into(), res.body_string().await.unwrap())
+    }
+
+    /// a PUT of `body` to `path`
+    fn put(path: &str, body: serde_json::Value) -> http_types::Request {
+        let mut req = http_types::Request::put(format!("https://localhost{}", path).as_str());
+        req.set_body(body.to_string());
+        req
+    }
+
+    #[async_std::test]
+    async fn writes_back_the_users_it_reads() {
+        let state = crate::server::test_state("v1p2_round_trip").await;
+        db::put_orgs(sample("orgs", |_| {}), &state.db)
+            .await
+            .unwrap();
+        // 001 is inactive but enabled, 002 is disabled
+        let written = sample("users", |user| {
+            if user["sourcedId"] == "001" {
+                user["status"] = json!("inactive");
+            }
+        });
+        db::put_users(written, &state.db).await.unwrap();
+        let mut app = tide::with_state(state.clone());
+        app.with(tide::utils::After(ApiError::new()));
+        app.at("/users/:id").get(get_user).put(put_user);
+
+        for id in ["001", "002"] {
+            let stored = json!(db::get_user(&state.db, id).await.unwrap());
+            let path = format!("/users/{}", id);
+            let req = http_types::Request::get(format!("https://localhost{}", path).as_str());
+            let (status, body) = respond(&app, req).await;
+            assert_eq!(status, 200);
+            let read: serde_json::Value = serde_json::from_str(&body).unwrap();
+            let (status, body) = respond(&app, put(&path, read)).await;
+            assert_eq!(status, 200, "{}", body);
+            assert_eq!(json!(db::get_user(&state.db, id).await.unwrap()), stored);
+        }
+        state.db.close().await;
+    }
+
+    #[async_std::test]
+    async fn rejects_users_1p1_cannot_hold() {
+        let state = crate::server::test_state("v1p2_rejected").await;
+        db::put_orgs(sample("orgs", |_| {}), &state.db)
+            .await
+            .unwrap();
+        db::put_users(sample("users", |_| {}), &state.db)
+            .await
+            .unwrap();
+        let mut app = tide::with_state(state.clone());
+        app.with(tide::utils::After(ApiError::new()));
+        app.at("/users").put(put_users);
+        app.at("/users/:id").get(get_user).put(put_user);
+
+        let req = http_types::Request::get("https://localhost/users/001");
+        let (_, body) = respond(&app, req).await;
+        let read: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let user = read["user"].clone();
+        let stored = json!(db::get_user(&state.db, "001").await.unwrap());
+
+        let mut pronouns = user.clone();
+        pronouns["pronouns"] = json!("they/them");
+        let (status, body) = respond(&app, put("/users/001", json!({ "user": pronouns }))).await;
+        assert_eq!(status, 400);
+        assert!(body.contains("pronouns cannot be stored"), "{}", body);
+
+        let mut roles = user.clone();
+        let mut teacher = roles["roles"][0].clone();
+        teacher["roleType"] = json!("secondary");
+        teacher["role"] = json!("teacher");
+        teacher["org"]["sourcedId"] = json!("018");
+        roles["roles"].as_array_mut().unwrap().push(teacher);
+        let (status, body) = respond(&app, put("/users", json!({ "users": [roles] }))).await;
+        assert_eq!(status, 400);
+        assert!(
+            body.contains("'001' rejected: roles must all have the same role"),
+            "{}",
+            body
+        );
+
+        // nothing is written
+        assert_eq!(json!(db::get_user(&state.db, "001").await.unwrap()), stored);
+        state.db.close().await;
+    }
+}